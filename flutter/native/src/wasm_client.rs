@@ -6,8 +6,9 @@ use moksha_core::blind::BlindedMessage;
 use moksha_core::keyset::Keysets;
 use moksha_core::primitives::CashuErrorResponse;
 use moksha_core::primitives::{
-    CheckFeesRequest, CheckFeesResponse, MintLegacyInfoResponse, PaymentRequest, PostMeltRequest,
-    PostMeltResponse, PostMintRequest, PostMintResponse, PostSplitRequest, PostSplitResponse,
+    CheckFeesRequest, CheckFeesResponse, CheckSpendableRequest, CheckSpendableResponse,
+    MintLegacyInfoResponse, PaymentRequest, PostMeltRequest, PostMeltResponse, PostMintRequest,
+    PostMintResponse, PostSplitRequest, PostSplitResponse,
 };
 use moksha_core::proof::Proofs;
 use moksha_wallet::{client::LegacyClient, error::MokshaWalletError};
@@ -77,6 +78,20 @@ impl LegacyClient for WasmClient {
         extract_response_data::<CheckFeesResponse>(resp).await
     }
 
+    async fn post_check_spendable(
+        &self,
+        mint_url: &Url,
+        proofs: Proofs,
+    ) -> Result<CheckSpendableResponse, MokshaWalletError> {
+        let resp = Request::post(mint_url.join("check")?.as_str())
+            .header("content-type", "application/json")
+            .json(&CheckSpendableRequest { proofs })?
+            .send()
+            .await?;
+
+        extract_response_data::<CheckSpendableResponse>(resp).await
+    }
+
     async fn get_mint_keys(
         &self,
         mint_url: &Url,
@@ -1,10 +1,16 @@
+mod harness;
+
+use harness::MockLightning;
 use moksha_wallet::client::reqwest::HttpClient;
 use moksha_wallet::client::LegacyClient;
 use moksha_wallet::localstore::sqlite::SqliteLocalStore;
 use moksha_wallet::wallet::WalletBuilder;
+use mokshamint::database::memory::InMemoryDatabase;
 use mokshamint::lightning::{LightningType, LnbitsLightningSettings};
-use mokshamint::mint::Mint;
+use mokshamint::mint::{LightningFeeConfig, Mint};
 use reqwest::Url;
+use std::net::TcpListener;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 use tokio::runtime::Runtime;
@@ -44,6 +50,7 @@ pub fn test_integration() -> anyhow::Result<()> {
                 "127.0.0.1:8686".parse().expect("invalid address"),
                 None,
                 None,
+                None,
             )
             .await;
             drop(tmp);
@@ -118,7 +125,7 @@ pub fn test_integration() -> anyhow::Result<()> {
         assert_eq!(5_010, balance);
 
         // send 10 tokens
-        let result_send = wallet.send_tokens(10).await;
+        let result_send = wallet.send_tokens(10, None).await;
         assert!(result_send.is_ok());
         assert_eq!(10, result_send.unwrap().total_amount());
         let balance = wallet.get_balance().await.expect("Could not get balance");
@@ -128,6 +135,78 @@ pub fn test_integration() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_mint_swap_melt_over_http() -> anyhow::Result<()> {
+    let invoice_1000 = read_fixture("invoice_1000.txt")?;
+    let mint = Mint::new(
+        "my_private_key".to_string(),
+        "".to_string(),
+        Arc::new(MockLightning::new(invoice_1000.clone())),
+        LightningType::Lnbits(Default::default()),
+        Arc::new(InMemoryDatabase::default()),
+        LightningFeeConfig::new(0.0, 0),
+        Default::default(),
+    );
+
+    // reserve an ephemeral port for the mint
+    let addr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    tokio::spawn(mokshamint::server::run_server(mint, addr, None, None, None));
+
+    let client = HttpClient::default();
+    let mint_url = Url::parse(&format!("http://{addr}"))?;
+    wait_for_mint(&client, &mint_url).await?;
+
+    let tmp = tempfile::tempdir()?;
+    let tmp_dir = tmp
+        .path()
+        .to_str()
+        .expect("Could not create tmp dir for wallet");
+    let localstore = SqliteLocalStore::with_path(format!("{tmp_dir}/test_wallet.db")).await?;
+    let wallet = WalletBuilder::default()
+        .with_client(client)
+        .with_localstore(localstore)
+        .with_mint_url(mint_url)
+        .build()
+        .await?;
+    assert_eq!(0, wallet.get_balance().await?);
+
+    // mint, the mock lightning backend always hands out the same invoice
+    let payment_request = wallet.get_mint_payment_request(1_000).await?;
+    let minted = wallet
+        .mint_tokens(1_000.into(), payment_request.hash)
+        .await?;
+    assert_eq!(1_000, minted.total_amount());
+    assert_eq!(1_000, wallet.get_balance().await?);
+
+    // swap: send tokens and receive them again
+    let token = wallet.send_tokens(10, None).await?;
+    assert_eq!(10, token.total_amount());
+    assert_eq!(990, wallet.get_balance().await?);
+    wallet.receive_tokens(&token).await?;
+    assert_eq!(1_000, wallet.get_balance().await?);
+
+    // receiving the same token twice must fail
+    assert!(wallet.receive_tokens(&token).await.is_err());
+    assert_eq!(1_000, wallet.get_balance().await?);
+
+    // melt
+    let melt_response = wallet.pay_invoice(invoice_1000).await?;
+    assert!(melt_response.paid);
+    assert_eq!("preimage", melt_response.preimage);
+    assert_eq!(0, wallet.get_balance().await?);
+    Ok(())
+}
+
+async fn wait_for_mint(client: &HttpClient, mint_url: &Url) -> anyhow::Result<()> {
+    for _ in 0..50 {
+        if client.get_mint_keys(mint_url).await.is_ok() {
+            return Ok(());
+        }
+        sleep_until(Instant::now() + Duration::from_millis(100)).await;
+    }
+    anyhow::bail!("mint did not start")
+}
+
 fn read_fixture(name: &str) -> anyhow::Result<String> {
     let base_dir = std::env::var("CARGO_MANIFEST_DIR")?;
     let raw_token = std::fs::read_to_string(format!("{base_dir}/tests/fixtures/{name}"))?;
@@ -1,10 +1,15 @@
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use moksha_core::token::TokenV3;
+use moksha_wallet::{
+    client::LegacyClient, error::MokshaWalletError, localstore::LocalStore, wallet::WalletBuilder,
+};
+use std::{future::Future, path::PathBuf, time::Duration};
 use url::Url;
 
 #[derive(Parser)]
 #[command(version)]
 struct Opts {
+    /// Mint used for minting, sending and paying. Tokens are received at the mint that issued them
     #[clap(short, long)]
     mint_url: Url,
 
@@ -19,27 +24,87 @@ struct Opts {
 enum Command {
     /// Mint tokens
     Mint {
+        #[clap(value_parser = clap::value_parser!(u64).range(1..))]
         amount: u64,
+        /// Seconds between checking whether the invoice has been paid
+        #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        poll_interval: u64,
+        /// Seconds to wait for the invoice to be paid before giving up
+        #[clap(long, default_value_t = 600)]
+        timeout: u64,
+        /// Wait for the enter key instead of polling the mint
+        #[clap(long)]
+        manual: bool,
+        /// Also print the minted tokens, e.g. to transfer them manually. The tokens are stored in
+        /// the wallet either way
+        #[clap(long)]
+        print_token: bool,
     },
 
     /// Pay Lightning invoice
     Pay {
         invoice: String,
+        /// Pay from the mint with the lowest fee reserve among all mints with enough tokens
+        #[clap(long)]
+        cheapest: bool,
     },
 
     /// Send tokens
     Send {
         amount: u64,
+        /// Optional memo that is embedded in the token
+        #[clap(long)]
+        memo: Option<String>,
     },
 
-    /// Receive tokens
+    /// Receive tokens. The proofs are swapped for proofs of the current keyset of the mint
     Receive {
         token: String,
+        /// Only claim this amount and print the remainder as a new token, e.g. to return it to the
+        /// sender
+        #[clap(long)]
+        amount: Option<u64>,
+        /// Also swap the stored proofs of old keysets for proofs of the current keyset. Received
+        /// proofs are always reissued on the current keyset
+        #[clap(long)]
+        upgrade_keyset: bool,
     },
 
-    /// Show local balance
-    Balance,
+    /// Show local balance per mint and in total. The balance is computed from the local proofs
+    /// without contacting the mint
+    Balance {
+        /// Remove the proofs that have been spent at the mint before showing the balance
+        #[clap(long)]
+        online: bool,
+    },
+
+    /// Remove proofs that have already been spent from the local wallet
+    Cleanup,
+
+    /// List the stored proofs of the mint or freeze a proof. Frozen proofs are not used for
+    /// sending and paying
+    #[command(group(clap::ArgGroup::new("action").required(true).args(["list", "freeze", "unfreeze"])))]
+    Proofs {
+        #[clap(long)]
+        list: bool,
+        /// Id of the proof as shown by --list
+        #[clap(long)]
+        freeze: Option<String>,
+        /// Id of the proof as shown by --list
+        #[clap(long)]
+        unfreeze: Option<String>,
+    },
+
+    /// Show the transaction history of all mints, oldest entry first
+    History,
+
+    /// Decode a token and show its contents without receiving it
+    Decode {
+        /// The serialized cashu token
+        token: String,
+    },
 
+    /// Show the wallet configuration and the info of the mint, e.g. its supported NUTs
     Info,
 }
 
@@ -50,6 +115,23 @@ async fn main() -> anyhow::Result<()> {
 
     let cli = Opts::parse();
 
+    // decoding a token doesn't require a connection to the mint
+    if let Command::Decode { token } = cli.command {
+        let summary = TokenV3::deserialize(token)?.summary();
+        println!(
+            "Mint: {}\nUnit: {}\nTotal: {} sats",
+            summary
+                .mint
+                .map_or_else(|| "-".to_string(), |mint| mint.to_string()),
+            summary.unit,
+            summary.total
+        );
+        for (amount, count) in summary.denominations {
+            println!("  {amount} x {count}");
+        }
+        return Ok(());
+    }
+
     let db_path = match cli.db_dir {
         Some(dir) => {
             std::fs::create_dir_all(dir.clone())?;
@@ -61,86 +143,211 @@ async fn main() -> anyhow::Result<()> {
 
     let localstore = SqliteLocalStore::with_path(db_path.clone()).await?;
 
+    // proofs without a mint url belong to the configured mint, not to the mint of a received token
+    localstore.set_missing_mint_url(&cli.mint_url).await?;
+
     let client = moksha_wallet::client::reqwest::HttpClient::new();
 
-    let wallet = moksha_wallet::wallet::WalletBuilder::default()
-        .with_client(client)
-        .with_localstore(localstore)
-        .with_mint_url(cli.mint_url.clone())
+    // the balance is computed from the local proofs, so the mint doesn't have to be reachable
+    // unless the spent proofs are removed first
+    if let Command::Balance { online } = cli.command {
+        show_balance(online, client, &localstore, &cli.mint_url).await?;
+        return Ok(());
+    }
+
+    // tokens are received at the mint that issued them, all other commands use the given mint
+    let mint_url = match &cli.command {
+        Command::Receive { token, .. } => TokenV3::deserialize(token.clone())?
+            .mint()
+            .unwrap_or_else(|| cli.mint_url.clone()),
+        _ => cli.mint_url.clone(),
+    };
+
+    let wallet = WalletBuilder::default()
+        .with_client(client.clone())
+        .with_localstore(localstore.clone())
+        .with_mint_url(mint_url.clone())
         .build()
         .await?;
 
     match cli.command {
+        Command::Decode { .. } => unreachable!("decode is handled before connecting to the mint"),
         Command::Info => {
             let wallet_version = env!("CARGO_PKG_VERSION");
             println!(
-                "Version: {}\nDB: {}\nMint URL: {}",
-                wallet_version, db_path, cli.mint_url,
+                "Wallet version: {}\nDB: {}\nMint URL: {}\n",
+                wallet_version, db_path, mint_url,
             );
+            println!("{}", wallet.get_mint_info().await?);
         }
-        Command::Receive { token } => {
-            wallet.receive_tokens(&token.try_into()?).await?;
+        Command::Receive {
+            token,
+            amount,
+            upgrade_keyset,
+        } => {
+            let tokens: TokenV3 = token.try_into()?;
+            match amount {
+                Some(amount) => {
+                    let remainder = wallet.receive_tokens_partial(&tokens, amount).await?;
+                    let remainder_amount = remainder.total_amount();
+                    let ser: String = remainder.try_into()?;
+                    println!("Remainder {remainder_amount} sats:\n{ser}\n");
+                    println!("The remainder is kept as frozen proofs until it is claimed.\n");
+                }
+                None => wallet.receive_tokens(&tokens).await?,
+            }
+            if upgrade_keyset {
+                let upgraded = wallet.upgrade_keyset().await?;
+                println!("Upgraded {upgraded} sats of old keysets to the current keyset.\n");
+            }
             println!(
                 "Tokens received successfully.\nNew balance {} sats",
                 wallet.get_balance().await?
             );
         }
-        Command::Send { amount } => {
-            let result = wallet.send_tokens(amount).await?;
+        Command::Send { amount, memo } => {
+            let result = wallet.send_tokens(amount, memo).await?;
             let ser: String = result.try_into()?;
 
             println!("Result {amount} sats:\n{ser}");
             println!("\nNew balance: {:?} sats", wallet.get_balance().await?);
         }
 
-        Command::Balance => {
-            let balance = wallet.get_balance().await?;
-            println!("Balance: {balance:?} sats");
+        Command::Balance { .. } => unreachable!("balance is handled before connecting to the mint"),
+        Command::Cleanup => {
+            let pruned = wallet.cleanup().await?;
+            println!(
+                "Removed {pruned} spent proofs.\nNew balance: {} sats",
+                wallet.get_balance().await?
+            );
+        }
+        Command::Proofs {
+            list,
+            freeze,
+            unfreeze,
+        } => {
+            if let Some(id) = freeze {
+                wallet.freeze_proof(&id).await?;
+                println!("Proof {id} frozen");
+            }
+            if let Some(id) = unfreeze {
+                wallet.unfreeze_proof(&id).await?;
+                println!("Proof {id} unfrozen");
+            }
+            if list {
+                for proof in wallet.list_proofs().await? {
+                    println!(
+                        "{}  {} sats  keyset {}{}",
+                        proof.id,
+                        proof.amount,
+                        proof.keyset_id,
+                        if proof.frozen { "  frozen" } else { "" }
+                    );
+                }
+            }
         }
-        Command::Pay { invoice } => {
-            let response = wallet.pay_invoice(invoice).await?;
+        Command::History => {
+            for entry in wallet.get_history().await? {
+                println!(
+                    "{}  {}  {} sats  {}",
+                    entry.timestamp, entry.kind, entry.amount, entry.mint_url
+                );
+            }
+        }
+        Command::Pay { invoice, cheapest } => {
+            let wallet = if cheapest {
+                match wallet.cheapest_mint_for_invoice(&invoice).await? {
+                    None => {
+                        println!("Error: No mint has enough tokens to pay the invoice plus the fee reserve");
+                        return Ok(());
+                    }
+                    Some(cheapest_mint) if cheapest_mint != mint_url => {
+                        println!("Paying from {cheapest_mint}");
+                        moksha_wallet::wallet::WalletBuilder::default()
+                            .with_client(client)
+                            .with_localstore(localstore)
+                            .with_mint_url(cheapest_mint)
+                            .build()
+                            .await?
+                    }
+                    Some(_) => wallet,
+                }
+            } else {
+                wallet
+            };
+
+            let invoice_amount = wallet.get_invoice_amount(&invoice)?;
+            let balance = wallet.get_balance().await?;
 
-            // FIXME handle not enough tokens error
+            // the change for the unused fee reserve is stored by the wallet
+            let response = match wallet.pay_invoice(invoice).await {
+                Err(MokshaWalletError::NotEnoughTokens) => {
+                    println!(
+                        "Error: Not enough tokens to pay {invoice_amount} sats plus the fee reserve. Balance: {balance} sats"
+                    );
+                    return Ok(());
+                }
+                result => result?,
+            };
 
             if response.paid {
+                let new_balance = wallet.get_balance().await?;
+                let fees = balance
+                    .saturating_sub(new_balance)
+                    .saturating_sub(invoice_amount);
                 println!(
-                    "\nInvoice has been paid: Tokens melted successfully\nNew balance: {:?} sats",
-                    wallet.get_balance().await?
+                    "\nInvoice has been paid: {invoice_amount} sats, fees {fees} sats\nNew balance: {new_balance} sats"
                 );
-                // TODO NUT-08 create tokens from change
             } else {
                 println!("Error: Tokens not melted");
             }
         }
-        Command::Mint { amount } => {
+        Command::Mint {
+            amount,
+            poll_interval,
+            timeout,
+            manual,
+            print_token,
+        } => {
             let payment_request = wallet.get_mint_payment_request(amount).await?;
             let hash = payment_request.clone().hash;
             let invoice = payment_request.clone().pr;
 
             println!("Pay invoice to mint tokens:\n\n{invoice}");
 
-            loop {
-                tokio::time::sleep_until(
-                    tokio::time::Instant::now() + std::time::Duration::from_millis(1_000),
-                )
-                .await;
-                let mint_result = wallet.mint_tokens(amount.into(), hash.clone()).await;
-
-                match mint_result {
-                    Ok(_) => {
-                        println!(
-                            "Tokens minted successfully.\nNew balance {} sats",
-                            wallet.get_balance().await?
-                        );
-                        break;
-                    }
-                    Err(moksha_wallet::error::MokshaWalletError::InvoiceNotPaidYet(_, _)) => {
-                        continue;
+            let mint_result = if manual {
+                loop {
+                    println!("\nPress enter once the invoice has been paid");
+                    std::io::stdin().read_line(&mut String::new())?;
+                    match wallet.mint_tokens(amount.into(), hash.clone()).await {
+                        Err(MokshaWalletError::InvoiceNotPaidYet(_, _)) => {
+                            println!("Invoice has not been paid yet");
+                        }
+                        result => break result,
                     }
-                    Err(e) => {
-                        println!("General Error: {}", e);
-                        break;
+                }
+            } else {
+                poll_until_paid(
+                    Duration::from_secs(poll_interval),
+                    Duration::from_secs(timeout),
+                    || wallet.mint_tokens(amount.into(), hash.clone()),
+                )
+                .await
+            };
+
+            match mint_result {
+                Ok(tokens) => {
+                    if print_token {
+                        let ser: String = tokens.try_into()?;
+                        println!("\nTokens {amount} sats:\n{ser}");
                     }
+                    println!(
+                        "Tokens minted successfully.\nNew balance {} sats",
+                        wallet.get_balance().await?
+                    );
+                }
+                Err(e) => {
+                    println!("General Error: {}", e);
                 }
             }
         }
@@ -148,5 +355,267 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Prints the balance per mint and in total. The mint is only contacted if `online` is set, to
+/// remove the proofs that have been spent before.
+async fn show_balance<C: LegacyClient, L: LocalStore + Clone>(
+    online: bool,
+    client: C,
+    localstore: &L,
+    mint_url: &Url,
+) -> Result<(), MokshaWalletError> {
+    if online {
+        let wallet = WalletBuilder::default()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+        let pruned = wallet.cleanup().await?;
+        if pruned > 0 {
+            println!("Removed {pruned} spent proofs.");
+        }
+    }
+    print_balances(localstore).await
+}
+
+/// Prints the balance per mint and in total
+async fn print_balances(localstore: &impl LocalStore) -> Result<(), MokshaWalletError> {
+    let balances = localstore.get_balances().await?;
+    for (mint_url, balance) in balances.iter() {
+        println!("{mint_url}: {balance} sats");
+    }
+    println!("Total: {} sats", balances.values().sum::<u64>());
+    Ok(())
+}
+
+/// Calls `mint` every `interval` until the invoice has been paid. Returns the last
+/// `InvoiceNotPaidYet` error if it still isn't paid after `timeout`.
+async fn poll_until_paid<T, F, Fut>(
+    interval: Duration,
+    timeout: Duration,
+    mut mint: F,
+) -> Result<T, MokshaWalletError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, MokshaWalletError>>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        tokio::time::sleep(interval).await;
+        match mint().await {
+            Err(MokshaWalletError::InvoiceNotPaidYet(code, detail)) => {
+                if tokio::time::Instant::now() + interval > deadline {
+                    return Err(MokshaWalletError::InvoiceNotPaidYet(code, detail));
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 fn main() {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use moksha_core::{
+        blind::BlindedMessage,
+        keyset::Keysets,
+        primitives::{
+            CheckFeesResponse, CheckSpendableResponse, MintLegacyInfoResponse, PaymentRequest,
+            PostMeltResponse, PostMintResponse, PostSplitResponse,
+        },
+        proof::Proofs,
+        token::TokenV3,
+    };
+    use moksha_wallet::{
+        client::LegacyClient,
+        error::MokshaWalletError,
+        localstore::{memory::MemoryLocalStore, LocalStore},
+    };
+    use secp256k1::PublicKey;
+    use url::Url;
+
+    use crate::{poll_until_paid, show_balance};
+
+    /// Client that counts its calls and fails every call, as if the mint were unreachable
+    #[derive(Clone, Default)]
+    struct UnreachableClient {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl UnreachableClient {
+        fn unreachable<T>(&self) -> Result<T, MokshaWalletError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(MokshaWalletError::UnexpectedResponse(
+                "the mint is unreachable".to_owned(),
+            ))
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl LegacyClient for UnreachableClient {
+        async fn post_split_tokens(
+            &self,
+            _mint_url: &Url,
+            _proofs: Proofs,
+            _output: Vec<BlindedMessage>,
+        ) -> Result<PostSplitResponse, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn post_mint_payment_request(
+            &self,
+            _mint_url: &Url,
+            _hash: String,
+            _blinded_messages: Vec<BlindedMessage>,
+        ) -> Result<PostMintResponse, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn post_melt_tokens(
+            &self,
+            _mint_url: &Url,
+            _proofs: Proofs,
+            _pr: String,
+            _outputs: Vec<BlindedMessage>,
+        ) -> Result<PostMeltResponse, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn post_checkfees(
+            &self,
+            _mint_url: &Url,
+            _pr: String,
+        ) -> Result<CheckFeesResponse, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn post_check_spendable(
+            &self,
+            _mint_url: &Url,
+            _proofs: Proofs,
+        ) -> Result<CheckSpendableResponse, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn get_mint_keys(
+            &self,
+            _mint_url: &Url,
+        ) -> Result<HashMap<u64, PublicKey>, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn get_mint_keysets(&self, _mint_url: &Url) -> Result<Keysets, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn get_mint_payment_request(
+            &self,
+            _mint_url: &Url,
+            _amount: u64,
+        ) -> Result<PaymentRequest, MokshaWalletError> {
+            self.unreachable()
+        }
+
+        async fn get_info(
+            &self,
+            _mint_url: &Url,
+        ) -> Result<MintLegacyInfoResponse, MokshaWalletError> {
+            self.unreachable()
+        }
+    }
+
+    /// Mock for the mint that reports the invoice as unpaid for the first `unpaid_polls` calls
+    struct MockMint {
+        unpaid_polls: usize,
+        calls: AtomicUsize,
+    }
+
+    impl MockMint {
+        fn new(unpaid_polls: usize) -> Self {
+            Self {
+                unpaid_polls,
+                calls: AtomicUsize::new(0),
+            }
+        }
+
+        async fn mint_tokens(&self) -> Result<u64, MokshaWalletError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.unpaid_polls {
+                return Err(MokshaWalletError::InvoiceNotPaidYet(
+                    11,
+                    "Lightning invoice not paid yet.".to_string(),
+                ));
+            }
+            Ok(64)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_offline_balance_with_unreachable_mint() -> anyhow::Result<()> {
+        let tokens: TokenV3 = include_str!("../../moksha-core/src/fixtures/token_60.cashu")
+            .trim()
+            .to_string()
+            .try_into()?;
+        let mint_url = Url::parse("http://localhost:3338/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+        let client = UnreachableClient::default();
+
+        show_balance(false, client.clone(), &localstore, &mint_url).await?;
+        assert_eq!(0, client.calls.load(Ordering::SeqCst));
+        assert_eq!(
+            vec![60],
+            localstore
+                .get_balances()
+                .await?
+                .into_values()
+                .collect::<Vec<_>>()
+        );
+
+        // the online balance needs the mint to remove the spent proofs
+        let result = show_balance(true, client.clone(), &localstore, &mint_url).await;
+        assert!(result.is_err());
+        assert!(client.calls.load(Ordering::SeqCst) > 0);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_until_paid() -> anyhow::Result<()> {
+        let mint = MockMint::new(3);
+        let result = poll_until_paid(Duration::from_secs(2), Duration::from_secs(60), || {
+            mint.mint_tokens()
+        })
+        .await?;
+
+        assert_eq!(64, result);
+        assert_eq!(4, mint.calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_until_paid_timeout() -> anyhow::Result<()> {
+        let mint = MockMint::new(usize::MAX);
+        let result = poll_until_paid(Duration::from_secs(2), Duration::from_secs(10), || {
+            mint.mint_tokens()
+        })
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::InvoiceNotPaidYet(_, _))
+        ));
+        assert_eq!(5, mint.calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+}
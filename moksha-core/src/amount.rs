@@ -1,23 +1,158 @@
 //! This module defines the `Amount` and `SplitAmount` structs, which are used for representing and splitting amounts in Cashu.
 //!
-//! The `Amount` struct represents an amount in satoshis, with a single `u64` field for the amount. The struct provides a `split` method that splits the amount into a `SplitAmount` struct.
+//! The `Amount` struct represents an amount in satoshis, with a single `u64` field for the amount. The struct provides a `split` method that splits the amount into a `SplitAmount` struct and checked arithmetic, so untrusted amounts can't overflow.
 //!
 //! The `SplitAmount` struct represents a split amount, with a `Vec<u64>` field for the split amounts. The struct provides a `create_secrets` method that generates a vector of random strings for use as secrets in the split transaction. The struct also implements the `IntoIterator` trait, which allows it to be iterated over as a vector of `u64` values.
 //!
 //! Both the `Amount` and `SplitAmount` structs are serializable and deserializable using serde.
+use std::{fmt, iter::Sum, str::FromStr};
+
 use rand::distributions::Alphanumeric;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MokshaCoreError;
 
-#[derive(Debug, Clone)]
+/// An amount in the unit of the keyset. It is serialized as a plain number.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Amount(pub u64);
 
 impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
     pub fn split(&self) -> SplitAmount {
         split_amount(self.0).into()
     }
+
+    /// Splits the amount into powers of 2, which are the amounts a keyset has keys for
+    pub fn split_into_denominations(&self) -> Vec<Amount> {
+        split_amount(self.0).into_iter().map(Amount).collect()
+    }
+
+    /// Splits the amount into the given denominations, largest first, e.g. into the amounts a
+    /// mint has keys for. The result is in ascending order like `split`. Returns `None` if the
+    /// amount can't be represented exactly.
+    pub fn split_with(&self, denominations: &[u64]) -> Option<SplitAmount> {
+        let mut denominations = denominations.to_vec();
+        denominations.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut remaining = self.0;
+        let mut amounts = vec![];
+        for denomination in denominations.into_iter().filter(|d| *d > 0) {
+            while remaining >= denomination {
+                amounts.push(denomination);
+                remaining -= denomination;
+            }
+        }
+        if remaining != 0 {
+            return None;
+        }
+        amounts.reverse();
+        Some(amounts.into())
+    }
+
+    /// Returns the sum or `None` if it overflows a `u64`
+    pub fn checked_add(&self, other: &Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    /// Returns the difference or `None` if `other` is larger
+    pub fn checked_sub(&self, other: &Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    /// Returns the sum of all amounts or `None` if it overflows a `u64`
+    pub fn checked_sum<'a>(amounts: impl IntoIterator<Item = &'a Amount>) -> Option<Amount> {
+        amounts
+            .into_iter()
+            .try_fold(Amount::ZERO, |acc, amount| acc.checked_add(amount))
+    }
+}
+
+/// Panics on overflow, also in release builds. Use `Amount::checked_sum` for untrusted amounts
+impl Sum for Amount {
+    fn sum<I: Iterator<Item = Amount>>(iter: I) -> Self {
+        iter.fold(Amount::ZERO, |acc, amount| {
+            acc.checked_add(&amount).expect("amount overflow")
+        })
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<Amount> for u64 {
+    fn from(amount: Amount) -> Self {
+        amount.0
+    }
+}
+
+/// The scheme of the amounts a keyset has keys for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Denominations {
+    /// 1, 2, 4, 8, ...
+    #[default]
+    PowersOfTwo,
+    /// 1, 2, 5, 10, 20, 50, ...
+    OneTwoFive,
+}
+
+impl Denominations {
+    /// Returns the `count` smallest denominations in ascending order. Denominations that don't
+    /// fit into a u64 are omitted.
+    pub fn amounts(&self, count: u8) -> Vec<u64> {
+        match self {
+            Self::PowersOfTwo => (0..count.min(64)).map(|i| 2u64.pow(i as u32)).collect(),
+            Self::OneTwoFive => (0..)
+                .map_while(|decade| 10u64.checked_pow(decade))
+                .flat_map(|base| [1, 2, 5].map(|factor| base.checked_mul(factor)))
+                .map_while(|amount| amount)
+                .take(count as usize)
+                .collect(),
+        }
+    }
+
+    /// Returns true if the amount is a denomination of the scheme
+    pub fn contains(&self, amount: u64) -> bool {
+        match self {
+            Self::PowersOfTwo => amount.is_power_of_two(),
+            Self::OneTwoFive => {
+                let mut amount = amount;
+                while amount >= 10 && amount % 10 == 0 {
+                    amount /= 10;
+                }
+                matches!(amount, 1 | 2 | 5)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Denominations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PowersOfTwo => write!(f, "powers-of-two"),
+            Self::OneTwoFive => write!(f, "1-2-5"),
+        }
+    }
+}
+
+impl FromStr for Denominations {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "powers-of-two" => Ok(Self::PowersOfTwo),
+            "1-2-5" => Ok(Self::OneTwoFive),
+            _ => Err(MokshaCoreError::InvalidDenominations(s.to_owned())),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SplitAmount(Vec<u64>);
 
 impl From<Vec<u64>> for SplitAmount {
@@ -49,7 +184,7 @@ impl IntoIterator for SplitAmount {
     }
 }
 
-/// split a decimal amount into a vector of powers of 2
+/// split a decimal amount into a vector of powers of 2. An amount of 0 results in an empty vector
 fn split_amount(amount: u64) -> Vec<u64> {
     format!("{amount:b}")
         .chars()
@@ -74,7 +209,7 @@ pub fn generate_random_string() -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::amount::SplitAmount;
+    use crate::amount::{Amount, Denominations, SplitAmount};
 
     #[test]
     fn test_split_amount() -> anyhow::Result<()> {
@@ -86,6 +221,74 @@ mod tests {
 
         let bits = super::split_amount(64);
         assert_eq!(bits, vec![64]);
+
+        // zero can't be represented by any token
+        let bits = super::split_amount(0);
+        assert!(bits.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_into_denominations() -> anyhow::Result<()> {
+        assert_eq!(
+            vec![Amount(1), Amount(4), Amount(8)],
+            Amount(13).split_into_denominations()
+        );
+        assert!(Amount::ZERO.split_into_denominations().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_with_denominations() -> anyhow::Result<()> {
+        let one_two_five = Denominations::OneTwoFive.amounts(9);
+        assert_eq!(vec![1, 2, 5, 10, 20, 50, 100, 200, 500], one_two_five);
+        assert_eq!(
+            Some(SplitAmount::from(vec![2, 5, 10, 20])),
+            Amount(37).split_with(&one_two_five)
+        );
+        assert_eq!(
+            Some(SplitAmount::from(vec![1, 4, 8])),
+            Amount(13).split_with(&Denominations::PowersOfTwo.amounts(64))
+        );
+        assert_eq!(None, Amount(3).split_with(&[2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_denominations_contains() -> anyhow::Result<()> {
+        assert!(Denominations::OneTwoFive.contains(5));
+        assert!(Denominations::OneTwoFive.contains(2000));
+        assert!(!Denominations::OneTwoFive.contains(4));
+        assert!(!Denominations::OneTwoFive.contains(0));
+        assert!(!Denominations::PowersOfTwo.contains(5));
+        assert_eq!(Denominations::OneTwoFive, "1-2-5".parse::<Denominations>()?);
+        assert!("1-3-5".parse::<Denominations>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_checked_add_overflow() -> anyhow::Result<()> {
+        assert_eq!(Some(Amount(3)), Amount(1).checked_add(&Amount(2)));
+        assert_eq!(None, Amount(u64::MAX).checked_add(&Amount(1)));
+        assert_eq!(None, Amount(1).checked_sub(&Amount(2)));
+        assert_eq!(None, Amount::checked_sum(&[Amount(u64::MAX), Amount(1)]));
+        assert_eq!(
+            Amount(6),
+            vec![Amount(1), Amount(2), Amount(3)].into_iter().sum()
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "amount overflow")]
+    fn test_sum_overflow_panics() {
+        let _: Amount = vec![Amount(u64::MAX), Amount(1)].into_iter().sum();
+    }
+
+    #[test]
+    fn test_amount_serializes_as_number() -> anyhow::Result<()> {
+        assert_eq!("64", serde_json::to_string(&Amount(64))?);
+        assert_eq!(Amount(64), serde_json::from_str::<Amount>("64")?);
         Ok(())
     }
 
@@ -1,8 +1,8 @@
 //! This module defines the `BlindedMessage` and `BlindedSignature` structs, which are used for representing blinded messages and signatures in Cashu as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md)
 //!
-//! The `BlindedMessage` struct represents a blinded message, with an `amount` field for the amount in satoshis and a `b_` field for the public key of the blinding factor.
+//! The `BlindedMessage` struct represents a blinded message, with an `amount` field for the amount in satoshis, a `b_` field for the public key of the blinding factor, and an optional `id` field for the ID of the keyset that should sign it.
 //!
-//! The `BlindedSignature` struct represents a blinded signature, with an `amount` field for the amount in satoshis, a `c_` field for the public key of the blinding factor, and an optional `id` field for the ID of the signature.
+//! The `BlindedSignature` struct represents a blinded signature, with an `amount` field for the amount in satoshis, a `c_` field for the public key of the blinding factor, and an optional `id` field for the ID of the signature and an optional `dleq` proof that the mint signed with the key it advertises for the amount.
 //!
 //! Both the `BlindedMessage` and `BlindedSignature` structs are serializable and deserializable using serde.
 //!
@@ -25,6 +25,18 @@ pub struct BlindedSignature {
     #[schema(value_type=String)]
     pub c_: PublicKey,
     pub id: Option<String>,
+    /// proof that the signature was created with the key the mint advertises for the amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dleq: Option<Dleq>,
+}
+
+/// Discrete log equality proof as described in [Nut-12](https://github.com/cashubtc/nuts/blob/main/12.md)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct Dleq {
+    #[schema(value_type=String)]
+    pub e: SecretKey,
+    #[schema(value_type=String)]
+    pub s: SecretKey,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -33,6 +45,9 @@ pub struct BlindedMessage {
     #[serde(rename = "B_")]
     #[schema(value_type=String)]
     pub b_: PublicKey,
+    /// keyset the message should be signed with. Legacy wallets don't send it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
 impl BlindedMessage {
@@ -51,7 +66,15 @@ impl BlindedMessage {
             .map(|_| {
                 let secret = generate_random_string();
                 let (b_, alice_secret_key) = dhke.step1_alice(secret.clone(), None).unwrap(); // FIXME
-                (BlindedMessage { amount: 0, b_ }, alice_secret_key, secret)
+                (
+                    BlindedMessage {
+                        amount: 0,
+                        b_,
+                        id: None,
+                    },
+                    alice_secret_key,
+                    secret,
+                )
             })
             .collect::<Vec<(BlindedMessage, SecretKey, String)>>();
 
@@ -61,18 +84,36 @@ impl BlindedMessage {
 
 pub trait TotalAmount {
     fn total_amount(&self) -> u64;
+
+    /// Returns the total amount or `None` if the sum overflows a `u64`
+    fn checked_total_amount(&self) -> Option<u64>;
+
+    /// Returns the total amount as `Amount` or `None` if the sum overflows a `u64`
+    fn checked_total(&self) -> Option<Amount> {
+        self.checked_total_amount().map(Amount)
+    }
 }
 
 impl TotalAmount for Vec<BlindedSignature> {
     fn total_amount(&self) -> u64 {
         self.iter().fold(0, |acc, x| acc + x.amount)
     }
+
+    fn checked_total_amount(&self) -> Option<u64> {
+        self.iter()
+            .try_fold(0u64, |acc, x| acc.checked_add(x.amount))
+    }
 }
 
 impl TotalAmount for Vec<BlindedMessage> {
     fn total_amount(&self) -> u64 {
         self.iter().fold(0, |acc, x| acc + x.amount)
     }
+
+    fn checked_total_amount(&self) -> Option<u64> {
+        self.iter()
+            .try_fold(0u64, |acc, x| acc.checked_add(x.amount))
+    }
 }
 
 #[cfg(test)]
@@ -40,7 +40,7 @@
 //!```
 //! If true, C must have originated from Bob
 //!
-use crate::error::MokshaCoreError;
+use crate::{blind::Dleq, error::MokshaCoreError};
 use bitcoin_hashes::{sha256, Hash};
 use secp256k1::{All, PublicKey, Scalar, Secp256k1, SecretKey};
 
@@ -69,7 +69,7 @@ impl Dhke {
 
     /// Generates a point from the message hash and checks if the point lies on the curve.
     /// If it does not, iteratively tries to compute a new point from the hash.
-    fn hash_to_curve(message: &[u8]) -> PublicKey {
+    pub(crate) fn hash_to_curve(message: &[u8]) -> PublicKey {
         let mut point: Option<PublicKey> = None;
         let mut msg_to_hash = message.to_vec();
         while point.is_none() {
@@ -95,13 +95,24 @@ impl Dhke {
     ) -> Result<(PublicKey, SecretKey), MokshaCoreError> {
         let mut rng = rand::thread_rng();
 
-        let y = Dhke::hash_to_curve(secret_msg.into().as_bytes());
         let secret_key = match blinding_factor {
             Some(f) => SecretKey::from_slice(f)?,
             None => SecretKey::new(&mut rng),
         };
-        let b = y.combine(&PublicKey::from_secret_key(&self.secp, &secret_key))?;
-        Ok((b, secret_key))
+        self.step1_alice_with_blinding_factor(secret_msg, secret_key)
+    }
+
+    /// Blinds the secret with the given blinding factor instead of a random one, e.g. for
+    /// deterministic secrets as described in [Nut-13](https://github.com/cashubtc/nuts/blob/main/13.md).
+    /// The same secret and blinding factor always result in the same blinded message.
+    pub fn step1_alice_with_blinding_factor(
+        &self,
+        secret_msg: impl Into<String>,
+        blinding_factor: SecretKey,
+    ) -> Result<(PublicKey, SecretKey), MokshaCoreError> {
+        let y = Dhke::hash_to_curve(secret_msg.into().as_bytes());
+        let b = y.combine(&PublicKey::from_secret_key(&self.secp, &blinding_factor))?;
+        Ok((b, blinding_factor))
     }
 
     pub fn step2_bob(&self, b: PublicKey, a: &SecretKey) -> Result<PublicKey, MokshaCoreError> {
@@ -109,6 +120,60 @@ impl Dhke {
             .map_err(MokshaCoreError::Secp256k1Error)
     }
 
+    /// Creates a DLEQ proof (NUT-12) that `c_` is `b_` multiplied with the private key `a`,
+    /// without revealing `a`. Bob picks a random nonce `r` and proves
+    /// ```python
+    /// R1 = r*G
+    /// R2 = r*B'
+    /// e = hash(R1, R2, A, C')
+    /// s = r + e*a
+    /// ```
+    pub fn step2_bob_dleq(
+        &self,
+        b_: PublicKey,
+        c_: PublicKey,
+        a: &SecretKey,
+    ) -> Result<Dleq, MokshaCoreError> {
+        let r = SecretKey::new(&mut rand::thread_rng());
+        let r1 = PublicKey::from_secret_key(&self.secp, &r);
+        let r2 = b_.mul_tweak(&self.secp, &Scalar::from(r))?;
+        let e = Dhke::hash_e(&[r1, r2, a.public_key(&self.secp), c_])?;
+        let s = a.mul_tweak(&Scalar::from(e))?.add_tweak(&Scalar::from(r))?;
+        Ok(Dleq { e, s })
+    }
+
+    /// Verifies the DLEQ proof of the blinded signature `c_` for the blinded message `b_` against
+    /// the public key `a` the mint advertises for the amount
+    /// ```python
+    /// R1 = s*G - e*A
+    /// R2 = s*B' - e*C'
+    /// e == hash(R1, R2, A, C')
+    /// ```
+    pub fn verify_dleq(
+        &self,
+        dleq: &Dleq,
+        b_: PublicKey,
+        c_: PublicKey,
+        a: PublicKey,
+    ) -> Result<bool, MokshaCoreError> {
+        let e = Scalar::from(dleq.e);
+        let r1 = PublicKey::from_secret_key(&self.secp, &dleq.s)
+            .combine(&a.mul_tweak(&self.secp, &e)?.negate(&self.secp))?;
+        let r2 = b_
+            .mul_tweak(&self.secp, &Scalar::from(dleq.s))?
+            .combine(&c_.mul_tweak(&self.secp, &e)?.negate(&self.secp))?;
+        Ok(Dhke::hash_e(&[r1, r2, a, c_])? == dleq.e)
+    }
+
+    /// Hashes the hex encoded uncompressed public keys to the challenge of a DLEQ proof
+    fn hash_e(public_keys: &[PublicKey]) -> Result<SecretKey, MokshaCoreError> {
+        let message = public_keys
+            .iter()
+            .map(|key| hex::encode(key.serialize_uncompressed()))
+            .collect::<String>();
+        Ok(SecretKey::from_slice(&Dhke::get_hash(message.as_bytes()))?)
+    }
+
     pub fn step3_alice(
         &self,
         c_: PublicKey,
@@ -130,6 +195,17 @@ impl Dhke {
         secret_msg: impl Into<String>,
     ) -> Result<bool, MokshaCoreError> {
         let y = Dhke::hash_to_curve(secret_msg.into().as_bytes());
+        self.verify_with_y(a, c, y)
+    }
+
+    /// Verifies the signature `c` for a secret whose point `y = hash_to_curve(secret)` has
+    /// already been computed, e.g. by [`crate::proof::Proof::y`]
+    pub fn verify_with_y(
+        &self,
+        a: SecretKey,
+        c: PublicKey,
+        y: PublicKey,
+    ) -> Result<bool, MokshaCoreError> {
         Some(c == y.mul_tweak(&self.secp, &Scalar::from(a))?).ok_or(
             MokshaCoreError::Secp256k1Error(secp256k1::Error::InvalidPublicKey),
         )
@@ -144,6 +220,7 @@ pub fn public_key_from_hex(hex: &str) -> secp256k1::PublicKey {
 
 #[cfg(test)]
 mod tests {
+    use crate::blind::Dleq;
     use crate::dhke::{public_key_from_hex, Dhke};
     use anyhow::Ok;
 
@@ -213,6 +290,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_step1_alice_with_blinding_factor() -> anyhow::Result<()> {
+        let dhke = Dhke::new();
+        let blinding_factor = private_key_from_hex(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+        );
+
+        let (first, _) = dhke.step1_alice_with_blinding_factor("test_message", blinding_factor)?;
+        let (second, r) = dhke.step1_alice_with_blinding_factor("test_message", blinding_factor)?;
+        assert_eq!(first, second);
+        assert_eq!(blinding_factor, r);
+        assert_eq!(
+            first.to_string(),
+            "02a9acc1e48c25eeeb9289b5031cc57da9fe72f3fe2861d264bdc074209b107ba2"
+        );
+
+        let other_factor = private_key_from_hex(
+            "0000000000000000000000000000000000000000000000000000000000000002",
+        );
+        let (other, _) = dhke.step1_alice_with_blinding_factor("test_message", other_factor)?;
+        assert_ne!(first, other);
+        Ok(())
+    }
+
     #[test]
     fn test_step2_bob() -> anyhow::Result<()> {
         let dhke = Dhke::new();
@@ -295,4 +396,26 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_dleq() -> anyhow::Result<()> {
+        let dhke = Dhke::new();
+        let a = private_key_from_hex(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+        );
+        let other = private_key_from_hex(
+            "0000000000000000000000000000000000000000000000000000000000000002",
+        );
+
+        let (b_, _) = dhke.step1_alice("test_message", None)?;
+        let c_ = dhke.step2_bob(b_, &a)?;
+        let dleq = dhke.step2_bob_dleq(b_, c_, &a)?;
+
+        assert!(dhke.verify_dleq(&dleq, b_, c_, a.public_key(&dhke.secp))?);
+        // the signature of another key must not pass with the advertised key
+        assert!(!dhke.verify_dleq(&dleq, b_, c_, other.public_key(&dhke.secp))?);
+        let tampered = Dleq { e: other, ..dleq };
+        assert!(!dhke.verify_dleq(&tampered, b_, c_, a.public_key(&dhke.secp))?);
+        Ok(())
+    }
 }
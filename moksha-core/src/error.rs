@@ -25,4 +25,16 @@ pub enum MokshaCoreError {
 
     #[error("Not enough tokens")]
     NotEnoughTokens,
+
+    #[error("Invalid proof at index {0} - {1}")]
+    InvalidProof(usize, String),
+
+    #[error("Invalid currency unit {0}")]
+    InvalidCurrencyUnit(String),
+
+    #[error("Only tokens of the same mint can be merged")]
+    TokenMergeMismatch,
+
+    #[error("Invalid denominations {0} - expected powers-of-two or 1-2-5")]
+    InvalidDenominations(String),
 }
@@ -12,7 +12,7 @@
 
 use hex::ToHex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use utoipa::ToSchema;
 
 use base64::{engine::general_purpose, Engine as _};
@@ -22,9 +22,10 @@ use itertools::Itertools;
 use rand::RngCore;
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
 
-use crate::{error::MokshaCoreError, primitives::CurrencyUnit};
+use crate::{amount::Denominations, error::MokshaCoreError, primitives::CurrencyUnit};
 
-const MAX_ORDER: u64 = 64;
+/// number of keys of a keyset if not configured otherwise. The largest supported amount is 2^(max_order - 1)
+pub const MAX_ORDER: u8 = 64;
 
 pub fn generate_hash() -> String {
     let mut rng = rand::thread_rng();
@@ -39,30 +40,86 @@ pub struct MintKeyset {
     pub public_keys: HashMap<u64, PublicKey>,
     pub keyset_id: String,
     pub mint_pubkey: PublicKey,
+    unit: CurrencyUnit,
+    denominations: Denominations,
 }
 
 impl MintKeyset {
     pub fn legacy_new(seed: &str, derivation_path: &str) -> MintKeyset {
-        let priv_keys = derive_keys(seed, derivation_path);
+        Self::legacy_new_with_max_order(seed, derivation_path, MAX_ORDER)
+    }
+
+    /// Creates a legacy keyset with keys for the amounts 2^0 to 2^(max_order - 1)
+    pub fn legacy_new_with_max_order(
+        seed: &str,
+        derivation_path: &str,
+        max_order: u8,
+    ) -> MintKeyset {
+        let priv_keys = derive_keys_with_max_order(seed, derivation_path, max_order);
         let pub_keys = derive_pubkeys(&priv_keys);
         MintKeyset {
             private_keys: priv_keys,
             keyset_id: legacy_derive_keyset_id(&pub_keys),
             public_keys: pub_keys,
             mint_pubkey: derive_pubkey(seed).expect("invalid seed"),
+            unit: CurrencyUnit::Sat,
+            denominations: Denominations::PowersOfTwo,
         }
     }
 
     pub fn new(seed: &str, derivation_path: &str) -> MintKeyset {
-        let priv_keys = derive_keys(seed, derivation_path);
+        Self::new_with_max_order(seed, derivation_path, MAX_ORDER)
+    }
+
+    /// Creates a keyset with keys for the amounts 2^0 to 2^(max_order - 1)
+    pub fn new_with_max_order(seed: &str, derivation_path: &str, max_order: u8) -> MintKeyset {
+        Self::new_with_denominations(seed, derivation_path, max_order, Denominations::PowersOfTwo)
+    }
+
+    /// Creates a keyset with keys for the `max_order` smallest amounts of the denomination scheme
+    pub fn new_with_denominations(
+        seed: &str,
+        derivation_path: &str,
+        max_order: u8,
+        denominations: Denominations,
+    ) -> MintKeyset {
+        let priv_keys =
+            derive_keys_for_denominations(seed, derivation_path, max_order, denominations);
         let pub_keys = derive_pubkeys(&priv_keys);
         MintKeyset {
             private_keys: priv_keys,
-            keyset_id: derive_keyset_id(&pub_keys),
+            keyset_id: derive_keyset_id(&pub_keys.clone().into_iter().collect()),
             public_keys: pub_keys,
             mint_pubkey: derive_pubkey(seed).expect("invalid seed"),
+            unit: CurrencyUnit::Sat,
+            denominations,
+        }
+    }
+
+    /// Sets the unit of the amounts the keyset signs. Defaults to sat
+    pub fn with_unit(mut self, unit: CurrencyUnit) -> MintKeyset {
+        self.unit = unit;
+        self
+    }
+
+    /// Returns the keyset with the id moksha derived for v1 keysets before it followed NUT-02.
+    /// Wallets might still hold proofs with this id.
+    pub fn with_pre_nut02_id(&self) -> MintKeyset {
+        MintKeyset {
+            keyset_id: pre_nut02_derive_keyset_id(&self.public_keys),
+            ..self.clone()
         }
     }
+
+    /// The unit that is advertised for the keyset in the keys and keysets responses
+    pub fn unit(&self) -> CurrencyUnit {
+        self.unit.clone()
+    }
+
+    /// The denomination scheme of the amounts the keyset has keys for
+    pub fn denominations(&self) -> Denominations {
+        self.denominations
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
@@ -111,7 +168,7 @@ impl V1Keysets {
         &self,
         mint_keys: &HashMap<u64, PublicKey>,
     ) -> Result<String, MokshaCoreError> {
-        let computed_id = derive_keyset_id(mint_keys);
+        let computed_id = derive_keyset_id(&mint_keys.clone().into_iter().collect());
         if self.keysets.iter().any(|x| x.id.eq(&computed_id)) {
             Ok(computed_id)
         } else {
@@ -131,13 +188,47 @@ impl V1Keysets {
 ///
 /// A HashMap containing the derived secret keys, where the key is a u64 value and the value is a SecretKey.
 pub fn derive_keys(master_key: &str, derivation_path: &str) -> HashMap<u64, SecretKey> {
-    let mut keys = HashMap::new();
-    for i in 0..MAX_ORDER {
-        let hash = sha256::Hash::hash(format!("{master_key}{derivation_path}{i}").as_bytes());
-        let key = SecretKey::from_slice(hash.as_byte_array()).unwrap();
-        keys.insert(2u64.pow(i as u32), key);
-    }
-    keys
+    derive_keys_with_max_order(master_key, derivation_path, MAX_ORDER)
+}
+
+/// Derives the secret keys for the amounts 2^0 to 2^(max_order - 1). `max_order` is capped at
+/// `MAX_ORDER`, because larger amounts don't fit into a u64.
+pub fn derive_keys_with_max_order(
+    master_key: &str,
+    derivation_path: &str,
+    max_order: u8,
+) -> HashMap<u64, SecretKey> {
+    derive_keys_for_denominations(
+        master_key,
+        derivation_path,
+        max_order,
+        Denominations::PowersOfTwo,
+    )
+}
+
+/// Derives the secret keys for the `max_order` smallest amounts of the denomination scheme. The
+/// key of the i-th amount is derived from the index i. Other schemes than powers of two add the
+/// scheme to the derivation path, so they never reuse the keys or the keyset id of a powers of
+/// two keyset, while existing keysets of powers of two keep their keys.
+pub fn derive_keys_for_denominations(
+    master_key: &str,
+    derivation_path: &str,
+    max_order: u8,
+    denominations: Denominations,
+) -> HashMap<u64, SecretKey> {
+    let derivation_path = match denominations {
+        Denominations::PowersOfTwo => derivation_path.to_owned(),
+        _ => format!("{derivation_path}/{denominations}/"),
+    };
+    denominations
+        .amounts(max_order.min(MAX_ORDER))
+        .into_iter()
+        .enumerate()
+        .map(|(i, amount)| {
+            let hash = sha256::Hash::hash(format!("{master_key}{derivation_path}{i}").as_bytes());
+            (amount, SecretKey::from_slice(hash.as_byte_array()).unwrap())
+        })
+        .collect()
 }
 
 /// Derives public keys from a given set of secret keys.
@@ -175,7 +266,30 @@ pub fn legacy_derive_keyset_id(keys: &HashMap<u64, PublicKey>) -> String {
     general_purpose::STANDARD.encode(hashed_pubkeys)[..12].to_string()
 }
 
-fn derive_keyset_id(keys: &HashMap<u64, PublicKey>) -> String {
+/// Derives a keyset ID as described in [Nut-02](https://github.com/cashubtc/nuts/blob/main/02.md).
+///
+/// The compressed public keys are concatenated in ascending order of their amounts and hashed
+/// with sha256. The ID is the version byte `00` followed by the first 7 bytes of the hash.
+///
+/// # Arguments
+///
+/// * `keys` - A BTreeMap of public keys by amount.
+///
+/// # Returns
+///
+/// A hex string representing the derived keyset ID.
+pub fn derive_keyset_id(keys: &BTreeMap<u64, PublicKey>) -> String {
+    let pubkeys = keys
+        .values()
+        .flat_map(|pubkey| pubkey.serialize())
+        .collect::<Vec<u8>>();
+    let hashed_pubkeys: String = sha256::Hash::hash(&pubkeys).encode_hex();
+    format!("00{}", &hashed_pubkeys[..14])
+}
+
+/// Derives a v1 keyset ID the way moksha did before it followed NUT-02, by hashing the hex
+/// encoded public keys instead of their bytes.
+pub fn pre_nut02_derive_keyset_id(keys: &HashMap<u64, PublicKey>) -> String {
     let pubkeys = keys
         .iter()
         .sorted_by(|(amt_a, _), (amt_b, _)| amt_a.cmp(amt_b))
@@ -184,6 +298,19 @@ fn derive_keyset_id(keys: &HashMap<u64, PublicKey>) -> String {
     let hashed_pubkeys: String = sha256::Hash::hash(pubkeys.as_bytes()).encode_hex();
     format!("00{}", &hashed_pubkeys[..14])
 }
+
+/// Checks that the keyset ID advertised by the mint matches the ID derived from its keys, which
+/// detects keys that have been swapped by a man in the middle.
+pub fn verify_keyset_id(
+    keyset_id: &str,
+    keys: &BTreeMap<u64, PublicKey>,
+) -> Result<(), MokshaCoreError> {
+    if derive_keyset_id(keys) == keyset_id {
+        Ok(())
+    } else {
+        Err(MokshaCoreError::InvalidKeysetid)
+    }
+}
 ///
 /// # Arguments
 ///
@@ -201,9 +328,15 @@ pub fn derive_pubkey(seed: &str) -> Result<PublicKey, MokshaCoreError> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
-    use crate::keyset::{derive_pubkey, generate_hash};
+    use secp256k1::PublicKey;
+
+    use crate::{
+        amount::{Amount, Denominations},
+        fixture::read_fixture_as,
+        keyset::{derive_pubkey, generate_hash, MintKeyset},
+    };
 
     fn public_key_from_hex(hex: &str) -> secp256k1::PublicKey {
         use hex::FromHex;
@@ -217,6 +350,73 @@ mod tests {
         assert_eq!(hash.len(), 64);
     }
 
+    #[test]
+    fn test_keyset_with_max_order() {
+        let keyset = MintKeyset::new_with_max_order("supersecretprivatekey", "", 32);
+        assert_eq!(32, keyset.private_keys.len());
+        assert_eq!(32, keyset.public_keys.len());
+        assert!(keyset.public_keys.contains_key(&2u64.pow(31)));
+        assert!((32..64).all(|i| !keyset.public_keys.contains_key(&2u64.pow(i))));
+    }
+
+    #[test]
+    fn test_keyset_with_one_two_five_denominations() -> anyhow::Result<()> {
+        let keyset = MintKeyset::new_with_denominations(
+            "supersecretprivatekey",
+            "",
+            12,
+            Denominations::OneTwoFive,
+        );
+        assert_eq!(Denominations::OneTwoFive, keyset.denominations());
+        let mut amounts = keyset.public_keys.keys().copied().collect::<Vec<_>>();
+        amounts.sort();
+        assert_eq!(
+            vec![1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000],
+            amounts
+        );
+
+        let notes = Amount(37)
+            .split_with(&amounts)
+            .expect("37 can't be split")
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(vec![2, 5, 10, 20], notes);
+        assert!(notes
+            .iter()
+            .all(|amount| keyset.private_keys.contains_key(amount)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_denominations_derive_different_keysets() -> anyhow::Result<()> {
+        let powers_of_two = MintKeyset::new_with_denominations(
+            "supersecretprivatekey",
+            "",
+            12,
+            Denominations::PowersOfTwo,
+        );
+        let one_two_five = MintKeyset::new_with_denominations(
+            "supersecretprivatekey",
+            "",
+            12,
+            Denominations::OneTwoFive,
+        );
+
+        assert_ne!(powers_of_two.keyset_id, one_two_five.keyset_id);
+        // 1 and 2 are denominations of both schemes
+        for amount in [1, 2] {
+            assert_ne!(
+                powers_of_two.private_keys[&amount],
+                one_two_five.private_keys[&amount]
+            );
+        }
+        assert_eq!(
+            MintKeyset::new_with_max_order("supersecretprivatekey", "", 12).keyset_id,
+            powers_of_two.keyset_id
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_derive_pubkey() -> anyhow::Result<()> {
         let result = derive_pubkey("supersecretprivatekey")?;
@@ -245,8 +445,12 @@ mod tests {
         assert_eq!(keys.len(), 64);
 
         let pub_keys = super::derive_pubkeys(&keys);
-        let id = super::derive_keyset_id(&pub_keys);
-        //assert_eq!("00d31cecf59d18c0", id); // FIXME
+        assert_eq!(
+            "003df5c4c3afd66d",
+            super::pre_nut02_derive_keyset_id(&pub_keys)
+        );
+        let id = super::derive_keyset_id(&pub_keys.into_iter().collect());
+        assert_eq!("00d31cecf59d18c0", id);
         assert_eq!(id.len(), 16);
         Ok(())
     }
@@ -264,6 +468,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_public_keys_serialize_compressed() -> anyhow::Result<()> {
+        let keyset = MintKeyset::legacy_new("TEST_PRIVATE_KEY", "0/0/0/0");
+        let json = serde_json::to_value(&keyset.public_keys)?;
+
+        assert_eq!(
+            "03190ebc0c3e2726a5349904f572a2853ea021b0128b269b8b6906501d262edaa8",
+            json["1"]
+        );
+        // same value as in cashu test_mint.py
+        assert_eq!(
+            "032dc008b88b85fdc2301a499bfaaef774c191a6307d8c9434838fc2eaa2e48d51",
+            json["9223372036854775808"]
+        );
+        assert!(json
+            .as_object()
+            .expect("keys are not a map")
+            .values()
+            .all(|key| key.as_str().is_some_and(|key| key.len() == 66)));
+        Ok(())
+    }
+
     #[test]
     fn test_derive_keyset_id() -> anyhow::Result<()> {
         let mut pubs = HashMap::new();
@@ -287,4 +513,21 @@ mod tests {
         assert_eq!(keyset_id, "cNbjM0O6V/Kl");
         Ok(())
     }
+
+    // uses the test vector from nut-02
+    #[test]
+    fn test_derive_keyset_id_v1() -> anyhow::Result<()> {
+        let keys = read_fixture_as::<BTreeMap<u64, PublicKey>>("keys_nut02.json")?;
+        let keyset_id = super::derive_keyset_id(&keys);
+        assert_eq!("00456a94ab4e1c46", keyset_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_keyset_id() -> anyhow::Result<()> {
+        let keys = read_fixture_as::<BTreeMap<u64, PublicKey>>("keys_nut02.json")?;
+        assert!(super::verify_keyset_id("00456a94ab4e1c46", &keys).is_ok());
+        assert!(super::verify_keyset_id("00ffd48b8f5ecf80", &keys).is_err());
+        Ok(())
+    }
 }
@@ -1,7 +1,7 @@
 //! This module contains all the request and response objects that are used for interacting between the Mint and Wallet in Cashu.
 //! All of these structs are serializable and deserializable using serde.
 
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 use secp256k1::PublicKey;
 use serde::{Deserialize, Serialize};
@@ -11,13 +11,19 @@ use uuid::Uuid;
 
 use crate::{
     blind::{BlindedMessage, BlindedSignature},
+    error::MokshaCoreError,
+    keyset::verify_keyset_id,
     proof::Proofs,
 };
 
+#[skip_serializing_none]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PaymentRequest {
     pub pr: String,
     pub hash: String, // TODO use sha256::Hash
+    /// amount the invoice was created for, not sent by older mints
+    pub amount: Option<u64>,
+    pub unit: Option<CurrencyUnit>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -41,6 +47,18 @@ pub struct CheckFeesResponse {
     pub fee: u64,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckSpendableRequest {
+    pub proofs: Proofs,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct CheckSpendableResponse {
+    /// one entry per requested proof, in the same order
+    pub spendable: Vec<bool>,
+    pub pending: Vec<bool>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PostMeltRequest {
     pub proofs: Proofs,
@@ -83,6 +101,16 @@ pub struct PostSwapResponse {
     pub signatures: Vec<BlindedSignature>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PostAuthBlindMintRequest {
+    pub outputs: Vec<BlindedMessage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct PostAuthBlindMintResponse {
+    pub signatures: Vec<BlindedSignature>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct CashuErrorResponse {
     pub code: u64,
@@ -100,6 +128,7 @@ pub struct MintLegacyInfoResponse {
     pub contact: Option<Vec<Vec<String>>>,
     pub nuts: Vec<String>,
     pub motd: Option<String>,
+    pub tos_url: Option<String>,
     pub parameter: Parameter,
 }
 
@@ -113,26 +142,55 @@ pub struct KeysResponse {
     pub keysets: Vec<KeyResponse>,
 }
 
+impl KeysResponse {
+    /// Checks that the id of every keyset matches the id derived from its keys
+    pub fn verify_keyset_ids(&self) -> Result<(), MokshaCoreError> {
+        self.keysets
+            .iter()
+            .try_for_each(|keyset| verify_keyset_id(&keyset.id, &keyset.keys))
+    }
+}
+
 #[derive(serde::Deserialize, Serialize, Debug, PartialEq, Eq, ToSchema)]
 pub struct KeyResponse {
     pub id: String,
     pub unit: CurrencyUnit,
-    #[schema(value_type = HashMap<u64, String>)]
-    pub keys: HashMap<u64, PublicKey>,
+    /// keys by amount, sorted ascending so the serialized response is stable
+    #[schema(value_type = BTreeMap<u64, String>)]
+    pub keys: BTreeMap<u64, PublicKey>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, ToSchema, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum CurrencyUnit {
     Sat,
+    Msat,
     Usd,
+    /// unit of blind auth proofs (NUT-22)
+    Auth,
 }
 
 impl Display for CurrencyUnit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             CurrencyUnit::Sat => write!(f, "sat"),
+            CurrencyUnit::Msat => write!(f, "msat"),
             CurrencyUnit::Usd => write!(f, "usd"),
+            CurrencyUnit::Auth => write!(f, "auth"),
+        }
+    }
+}
+
+impl FromStr for CurrencyUnit {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sat" => Ok(CurrencyUnit::Sat),
+            "msat" => Ok(CurrencyUnit::Msat),
+            "usd" => Ok(CurrencyUnit::Usd),
+            "auth" => Ok(CurrencyUnit::Auth),
+            _ => Err(MokshaCoreError::InvalidCurrencyUnit(s.to_owned())),
         }
     }
 }
@@ -149,6 +207,20 @@ pub struct PostMintQuoteBolt11Request {
     pub unit: CurrencyUnit,
 }
 
+/// Requests a mint quote for every amount at once
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct PostMintQuotesBolt11Request {
+    pub amounts: Vec<u64>,
+    pub unit: CurrencyUnit,
+}
+
+/// The quotes in the order of the requested amounts
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct PostMintQuotesBolt11Response {
+    pub quotes: Vec<PostMintQuoteBolt11Response>,
+}
+
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 pub struct PostMintQuoteBolt11Response {
     pub quote: String,
@@ -156,6 +228,9 @@ pub struct PostMintQuoteBolt11Response {
     pub payment_request: String,
     pub paid: bool,
     pub expiry: u64,
+    /// amount the quote was created for, not sent by older mints
+    pub amount: Option<u64>,
+    pub unit: Option<CurrencyUnit>,
 }
 
 impl From<Bolt11MintQuote> for PostMintQuoteBolt11Response {
@@ -165,6 +240,8 @@ impl From<Bolt11MintQuote> for PostMintQuoteBolt11Response {
             payment_request: quote.payment_request,
             paid: quote.paid,
             expiry: quote.expiry,
+            amount: Some(quote.amount),
+            unit: Some(quote.unit),
         }
     }
 }
@@ -202,6 +279,8 @@ pub struct Bolt11MintQuote {
     pub payment_request: String,
     pub expiry: u64,
     pub paid: bool,
+    pub amount: u64,
+    pub unit: CurrencyUnit,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -230,7 +309,12 @@ impl From<Bolt11MeltQuote> for PostMeltQuoteBolt11Response {
 pub struct PostMeltBolt11Request {
     pub quote: String,
     pub inputs: Proofs,
+    /// blank outputs for the change. Wallets that omit them forgo the refund of the fee reserve
+    #[serde(default)]
     pub outputs: Vec<BlindedMessage>,
+    /// maximum fee in sats the wallet accepts. The melt is rejected if the fee reserve is higher
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
@@ -240,6 +324,15 @@ pub struct PostMeltBolt11Response {
     pub change: Vec<BlindedSignature>,
 }
 
+/// Lightweight liveness and version probe of the mint
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ToSchema)]
+pub struct MintVersionResponse {
+    pub name: Option<String>,
+    pub version: String,
+    /// seconds since the mint was started
+    pub uptime_secs: u64,
+}
+
 #[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ToSchema)]
 pub struct MintInfoResponse {
@@ -251,6 +344,7 @@ pub struct MintInfoResponse {
     pub description_long: Option<String>,
     pub contact: Option<Vec<Vec<String>>>,
     pub motd: Option<String>,
+    pub tos_url: Option<String>,
     pub nuts: Nuts,
 }
 
@@ -306,6 +400,10 @@ pub struct Nuts {
     #[serde(rename = "12")]
     /// DLEQ proofs
     pub nut12: Nut12,
+
+    /// Cached responses
+    #[serde(rename = "19", default)]
+    pub nut19: Nut19,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ToSchema)]
@@ -383,6 +481,20 @@ pub struct Nut12 {
     pub supported: bool,
 }
 
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Default, ToSchema)]
+pub struct Nut19 {
+    /// number of seconds for which responses are cached, `None` if they are cached forever
+    pub ttl: Option<u64>,
+    pub cached_endpoints: Vec<CachedEndpoint>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, ToSchema)]
+pub struct CachedEndpoint {
+    pub method: String,
+    pub path: String,
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -407,7 +519,7 @@ mod tests {
         let response = KeyResponse {
             id: "test".to_string(),
             unit: crate::primitives::CurrencyUnit::Sat,
-            keys: std::collections::HashMap::new(),
+            keys: std::collections::BTreeMap::new(),
         };
         let serialized = serde_json::to_string(&response)?;
         assert_eq!(serialized, "{\"id\":\"test\",\"unit\":\"sat\",\"keys\":{}}");
@@ -435,6 +547,7 @@ mod tests {
                 "NUT-08".to_string(),
             ],
             motd: Some("Message to display to users.".to_string()),
+            tos_url: None,
             parameter: Parameter {
                 peg_out_only: false,
             },
@@ -463,6 +576,7 @@ mod tests {
             ]),
             nuts: Nuts::default(),
             motd: Some("Message to display to users.".to_string()),
+            tos_url: None,
         };
         let out = serde_json::to_string_pretty(&mint_info)?;
         println!("{}", out);
@@ -2,20 +2,28 @@
 //!
 //! The `Proof` struct represents a proof, with an `amount` field for the amount in satoshis, a `secret` field for the secret string, a `c` field for the public key of the blinding factor, an `id` field for the ID of the proof, and an optional `script` field for the P2SH script.
 //!
-//! The `Proof` struct provides a `new` method for creating a new proof from its constituent fields.
+//! The `Proof` struct provides a `new` method for creating a new proof from its constituent fields. The `Y` point of the secret (`hash_to_curve(secret)`) is cached at runtime, so it is only computed once per proof. The cache is neither serialized nor compared.
 //!
-//! The `P2SHScript` struct represents a P2SH script, and is currently not implemented.
+//! The `P2SHScript` struct represents a legacy P2SH script. Spending conditions are not supported yet, so the mint rejects proofs with a script.
 //!
 //! The `Proofs` struct represents a collection of proofs, with a `Vec<Proof>` field for the proofs.
 //!
 //! Both the `Proof` and `Proofs` structs are serializable and deserializable using serde.
+//!
+//! The `AuthProof` struct represents a blind authentication proof as described in [Nut-22](https://github.com/cashubtc/nuts/blob/main/22.md), which a wallet presents to access protected routes.
 
+use base64::{engine::general_purpose, Engine as _};
 use secp256k1::PublicKey;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use utoipa::ToSchema;
 
-use crate::error::MokshaCoreError;
+use crate::{dhke::Dhke, error::MokshaCoreError, secret::Secret};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::OnceLock,
+};
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
@@ -28,6 +36,8 @@ pub struct Proof {
     #[serde(rename = "id")]
     pub keyset_id: String, // FIXME use keysetID as specific type
     pub script: Option<P2SHScript>,
+    #[serde(skip)]
+    y: YCache,
 }
 
 impl Proof {
@@ -38,12 +48,53 @@ impl Proof {
             c,
             keyset_id: id,
             script: None,
+            y: YCache::default(),
+        }
+    }
+
+    /// Returns the `Y` point of the secret. It is computed on the first call and cached for the
+    /// secret it was computed for.
+    pub fn y(&self) -> PublicKey {
+        let (secret, y) = self.y.0.get_or_init(|| {
+            (
+                self.secret.clone(),
+                Dhke::hash_to_curve(self.secret.as_bytes()),
+            )
+        });
+        if *secret == self.secret {
+            *y
+        } else {
+            // the secret has been changed after the point was cached
+            Dhke::hash_to_curve(self.secret.as_bytes())
+        }
+    }
+
+    /// Parses the secret, which is either a plain string or a well-known secret (Nut-10)
+    pub fn parsed_secret(&self) -> Secret {
+        match Secret::from_str(&self.secret) {
+            Ok(secret) => secret,
+            Err(never) => match never {},
         }
     }
 }
 
+/// Runtime-only cache of the `Y` point of a proof together with the secret it belongs to
+#[derive(Debug, Clone, Default)]
+struct YCache(OnceLock<(String, PublicKey)>);
+
+impl PartialEq for YCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
-pub struct P2SHScript;
+pub struct P2SHScript {
+    pub script: String,
+    pub signature: String,
+    pub address: Option<String>,
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
 pub struct Proofs(pub(super) Vec<Proof>);
@@ -65,6 +116,13 @@ impl Proofs {
         self.0.iter().map(|proof| proof.amount).sum()
     }
 
+    /// Returns the total amount of the proofs or `None` if the sum overflows a `u64`
+    pub fn checked_total_amount(&self) -> Option<u64> {
+        self.0
+            .iter()
+            .try_fold(0u64, |acc, proof| acc.checked_add(proof.amount))
+    }
+
     pub fn proofs(&self) -> Vec<Proof> {
         self.0.clone()
     }
@@ -78,27 +136,57 @@ impl Proofs {
     }
 
     pub fn proofs_for_amount(&self, amount: u64) -> Result<Proofs, MokshaCoreError> {
-        let mut all_proofs = self.0.clone();
+        self.split_at_amount(amount)
+            .map(|(selected, _)| selected)
+            .ok_or(MokshaCoreError::NotEnoughTokens)
+    }
+
+    /// Splits the proofs into the proofs that cover the given amount and the remaining proofs.
+    /// The largest proofs are selected first. Returns `None` if the total amount is too low.
+    pub fn split_at_amount(&self, amount: u64) -> Option<(Proofs, Proofs)> {
         if amount > self.total_amount() {
-            return Err(MokshaCoreError::NotEnoughTokens);
+            return None;
         }
 
-        all_proofs.sort_by(|a, b| a.amount.cmp(&b.amount));
+        let mut remaining_proofs = self.0.clone();
+        remaining_proofs.sort_by(|a, b| a.amount.cmp(&b.amount));
 
         let mut selected_proofs = vec![];
         let mut selected_amount = 0;
 
         while selected_amount < amount {
-            if all_proofs.is_empty() {
+            let Some(proof) = remaining_proofs.pop() else {
                 break;
-            }
-
-            let proof = all_proofs.pop().expect("proofs is empty");
+            };
             selected_amount += proof.amount;
             selected_proofs.push(proof);
         }
 
-        Ok(selected_proofs.into())
+        Some((selected_proofs.into(), remaining_proofs.into()))
+    }
+
+    /// Returns the secrets of all proofs. Callers that look up many secrets should build the set
+    /// once instead of calling `contains_secret` in a loop.
+    pub fn secrets_set(&self) -> HashSet<&str> {
+        self.0.iter().map(|proof| proof.secret.as_str()).collect()
+    }
+
+    pub fn contains_secret(&self, secret: &str) -> bool {
+        self.0.iter().any(|proof| proof.secret == secret)
+    }
+
+    /// Groups the proofs by their keyset id. Proofs without a keyset id (e.g. legacy proofs with
+    /// an empty id) are grouped under `None`. The order of the proofs within a group is kept.
+    pub fn grouped_by_keyset(&self) -> HashMap<Option<String>, Proofs> {
+        self.0.iter().fold(HashMap::new(), |mut groups, proof| {
+            let keyset_id = Some(proof.keyset_id.clone()).filter(|id| !id.is_empty());
+            groups
+                .entry(keyset_id)
+                .or_insert_with(Proofs::empty)
+                .0
+                .push(proof.clone());
+            groups
+        })
     }
 }
 
@@ -114,13 +202,56 @@ impl From<Proof> for Proofs {
     }
 }
 
+const AUTH_PREFIX_V1: &str = "authA";
+
+/// Proof signed by the auth keyset of the mint. Auth proofs always have an amount of 1 and can
+/// only be used once.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuthProof {
+    #[serde(rename = "id")]
+    pub keyset_id: String,
+    pub secret: String,
+    #[serde(rename = "C")]
+    pub c: PublicKey,
+}
+
+impl AuthProof {
+    pub const AMOUNT: u64 = 1;
+
+    pub fn serialize(&self) -> Result<String, MokshaCoreError> {
+        let json = serde_json::to_string(&self)?;
+        Ok(format!(
+            "{}{}",
+            AUTH_PREFIX_V1,
+            general_purpose::URL_SAFE.encode(json.as_bytes())
+        ))
+    }
+
+    pub fn deserialize(data: impl Into<String>) -> Result<AuthProof, MokshaCoreError> {
+        let json = general_purpose::URL_SAFE.decode(
+            data.into()
+                .strip_prefix(AUTH_PREFIX_V1)
+                .ok_or(MokshaCoreError::InvalidTokenPrefix)?
+                .as_bytes(),
+        )?;
+        Ok(serde_json::from_slice::<AuthProof>(&json)?)
+    }
+}
+
+impl From<AuthProof> for Proof {
+    fn from(from: AuthProof) -> Self {
+        Proof::new(AuthProof::AMOUNT, from.secret, from.c, from.keyset_id)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
 
     use crate::{
+        dhke::{self, Dhke},
         fixture::read_fixture,
-        proof::{Proof, Proofs},
+        proof::{AuthProof, P2SHScript, Proof, Proofs},
         token::TokenV3,
     };
 
@@ -150,6 +281,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_split_at_amount_exact() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // (4,8,16,32)
+
+        let (selected, rest) = token.proofs().split_at_amount(48).expect("enough proofs");
+        assert_eq!(48, selected.total_amount());
+        assert_eq!(2, selected.len());
+        assert_eq!(12, rest.total_amount());
+        assert_eq!(2, rest.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_at_amount_over_coverage() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // (4,8,16,32)
+
+        let (selected, rest) = token.proofs().split_at_amount(40).expect("enough proofs");
+        assert_eq!(48, selected.total_amount());
+        assert_eq!(12, rest.total_amount());
+        Ok(())
+    }
+
+    #[test]
+    fn test_grouped_by_keyset() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // (4,8,16,32)
+        let mut proofs = token.proofs().proofs();
+        let keyset_id = proofs[0].keyset_id.clone();
+        proofs[1].keyset_id = "00other".to_string();
+        proofs[3].keyset_id = "00other".to_string();
+        proofs[2].keyset_id = "".to_string();
+
+        let groups = Proofs::new(proofs.clone()).grouped_by_keyset();
+        assert_eq!(3, groups.len());
+        assert_eq!(
+            Proofs::new(vec![proofs[0].clone()]),
+            groups[&Some(keyset_id)]
+        );
+        assert_eq!(
+            Proofs::new(vec![proofs[1].clone(), proofs[3].clone()]),
+            groups[&Some("00other".to_string())]
+        );
+        assert_eq!(Proofs::new(vec![proofs[2].clone()]), groups[&None]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains_secret() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mut proofs = token.proofs().proofs();
+        // a duplicate secret is only contained once in the set
+        proofs.push(proofs[0].clone());
+        let proofs = Proofs::new(proofs);
+        assert_eq!(proofs.len() - 1, proofs.secrets_set().len());
+
+        let secrets = proofs
+            .proofs()
+            .into_iter()
+            .map(|proof| proof.secret)
+            .chain(["unknown".to_string(), "".to_string()])
+            .collect::<Vec<_>>();
+        for secret in secrets {
+            let linear = proofs.proofs().iter().any(|proof| proof.secret == secret);
+            assert_eq!(linear, proofs.contains_secret(&secret), "{secret}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_at_amount_insufficient() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+
+        assert!(token.proofs().split_at_amount(61).is_none());
+        Ok(())
+    }
+
     #[test]
     fn test_proof() -> anyhow::Result<()> {
         let js = json!(
@@ -171,4 +377,80 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_proof_y_cache() -> anyhow::Result<()> {
+        let js = json!(
+            {
+              "id": "DSAl9nvvyfva",
+              "amount": 2,
+              "secret": "EhpennC9qB3iFlW8FZ_pZw",
+              "C": "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4"
+            }
+        );
+        let mut proof = serde_json::from_value::<Proof>(js.clone())?;
+        let uncached = proof.clone();
+
+        assert_eq!(Dhke::hash_to_curve(b"EhpennC9qB3iFlW8FZ_pZw"), proof.y());
+        // the cache is neither serialized nor compared
+        assert_eq!(js, serde_json::to_value(&proof)?);
+        assert_eq!(uncached, proof);
+
+        proof.secret = "other_secret".to_string();
+        assert_eq!(Dhke::hash_to_curve(b"other_secret"), proof.y());
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_with_script() -> anyhow::Result<()> {
+        let js = json!(
+            {
+              "id": "DSAl9nvvyfva",
+              "amount": 2,
+              "secret": "EhpennC9qB3iFlW8FZ_pZw",
+              "C": "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
+              "script": {
+                "script": "OTXB1yYTvUpkLDjBkJRgSaGeYzS7WG2Jn0pV0ex5pHI=",
+                "signature": "MEQCIBsz8vZ..."
+              }
+            }
+        );
+
+        let proof = serde_json::from_value::<Proof>(js.clone())?;
+        assert_eq!(
+            Some(P2SHScript {
+                script: "OTXB1yYTvUpkLDjBkJRgSaGeYzS7WG2Jn0pV0ex5pHI=".to_string(),
+                signature: "MEQCIBsz8vZ...".to_string(),
+                address: None,
+            }),
+            proof.script
+        );
+        assert_eq!(js, serde_json::to_value(&proof)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_auth_proof_serialize() -> anyhow::Result<()> {
+        let auth_proof = AuthProof {
+            keyset_id: "00ad268c4d1f5826".to_string(),
+            secret: "auth_secret".to_string(),
+            c: dhke::public_key_from_hex(
+                "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
+            ),
+        };
+
+        let serialized = auth_proof.serialize()?;
+        assert!(serialized.starts_with("authA"));
+        assert_eq!(auth_proof, AuthProof::deserialize(serialized)?);
+
+        let proof: Proof = auth_proof.into();
+        assert_eq!(1, proof.amount);
+        Ok(())
+    }
+
+    #[test]
+    fn test_auth_proof_invalid_prefix() -> anyhow::Result<()> {
+        assert!(AuthProof::deserialize("cashuAeyJ9").is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,147 @@
+//! This module defines the `Secret` enum, which represents the secret of a proof. A secret is either a plain random string as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md) or a well-known secret as described in [Nut-10](https://github.com/cashubtc/nuts/blob/main/10.md).
+//!
+//! A well-known secret is serialized as a json array `[kind, {"nonce": .., "data": .., "tags": ..}]`, where the kind defines the spending condition (e.g. `P2PK`) and `data` and `tags` its parameters.
+
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Kind of the spending condition of a well-known secret
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Kind {
+    /// Pay to public key as described in [Nut-11](https://github.com/cashubtc/nuts/blob/main/11.md)
+    P2PK,
+    /// Hashed timelock contract as described in [Nut-14](https://github.com/cashubtc/nuts/blob/main/14.md)
+    HTLC,
+}
+
+/// random string that makes every well-known secret unique
+pub type Nonce = String;
+
+/// the parameter of the spending condition, e.g. the public key for `P2PK`
+pub type Data = String;
+
+/// optional additional parameters of the spending condition, e.g. `[["sigflag", "SIG_INPUTS"]]`
+pub type Tags = Option<Vec<Vec<String>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Secret {
+    Plain(String),
+    WellKnown(WellKnownSecret),
+}
+
+/// A well-known secret together with the string it has been parsed from. `Y` is derived from the
+/// string, so it is kept unchanged instead of being serialized again, e.g. if another wallet
+/// created the json with whitespace or a different key order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WellKnownSecret {
+    pub kind: Kind,
+    pub nonce: Nonce,
+    pub data: Data,
+    pub tags: Tags,
+    raw: String,
+}
+
+impl WellKnownSecret {
+    pub fn new(
+        kind: Kind,
+        nonce: Nonce,
+        data: Data,
+        tags: Tags,
+    ) -> Result<Self, serde_json::Error> {
+        let secret_data = SecretData {
+            nonce: nonce.clone(),
+            data: data.clone(),
+            tags: tags.clone(),
+        };
+        let raw = serde_json::to_string(&(kind, secret_data))?;
+        Ok(Self {
+            kind,
+            nonce,
+            data,
+            tags,
+            raw,
+        })
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize)]
+struct SecretData {
+    nonce: Nonce,
+    data: Data,
+    tags: Tags,
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    /// Every string that isn't a well-known secret is a plain secret, so parsing never fails.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match serde_json::from_str::<(Kind, SecretData)>(s) {
+            Ok((kind, secret_data)) => Self::WellKnown(WellKnownSecret {
+                kind,
+                nonce: secret_data.nonce,
+                data: secret_data.data,
+                tags: secret_data.tags,
+                raw: s.to_owned(),
+            }),
+            Err(_) => Self::Plain(s.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plain(secret) => write!(f, "{secret}"),
+            Self::WellKnown(secret) => write!(f, "{}", secret.raw),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::secret::{Kind, Secret, WellKnownSecret};
+
+    #[test]
+    fn test_plain_secret() -> anyhow::Result<()> {
+        let input = "EhpennC9qB3iFlW8FZ_pZw";
+        let secret = Secret::from_str(input)?;
+        assert_eq!(secret, Secret::Plain(input.to_string()));
+        assert_eq!(input, secret.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_p2pk_secret() -> anyhow::Result<()> {
+        let input = r#"["P2PK",{"nonce":"859d4935c4907062a6297cf4e663e2835d90d97ecdd510745d32f6816323a41f","data":"0249098aa8b9d2fbec49ff8598feb17b592b986e62319a4fa488a3dc36387157a7","tags":[["sigflag","SIG_INPUTS"]]}]"#;
+        let secret = Secret::from_str(input)?;
+        assert_eq!(
+            secret,
+            Secret::WellKnown(WellKnownSecret::new(
+                Kind::P2PK,
+                "859d4935c4907062a6297cf4e663e2835d90d97ecdd510745d32f6816323a41f".to_string(),
+                "0249098aa8b9d2fbec49ff8598feb17b592b986e62319a4fa488a3dc36387157a7".to_string(),
+                Some(vec![vec!["sigflag".to_string(), "SIG_INPUTS".to_string()]]),
+            )?)
+        );
+        assert_eq!(input, secret.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_canonical_secret_is_kept() -> anyhow::Result<()> {
+        let input = r#"[ "P2PK", { "data": "0249098aa8b9d2fbec49ff8598feb17b592b986e62319a4fa488a3dc36387157a7", "nonce": "859d4935c4907062a6297cf4e663e2835d90d97ecdd510745d32f6816323a41f" } ]"#;
+        let Secret::WellKnown(secret) = Secret::from_str(input)? else {
+            anyhow::bail!("secret is not well-known");
+        };
+        assert_eq!(Kind::P2PK, secret.kind);
+        assert_eq!(None, secret.tags);
+        assert_eq!(input, Secret::WellKnown(secret).to_string());
+        Ok(())
+    }
+}
@@ -1,20 +1,45 @@
 //! This module defines the `Token` struct, which is used for representing tokens in Cashu as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md)
 //!
 //! The `Token` struct represents a token, with an optional `mint` field for the URL of the Mint and a `proofs` field for the proofs associated with the token.
+//!
+//! The `TokenSummary` struct gives an overview of a `TokenV3` (mint, unit, total amount and denominations) without claiming it.
+
+use std::collections::BTreeMap;
 
-use base64::{engine::general_purpose, Engine as _};
+use base64::{
+    alphabet,
+    engine::{general_purpose, DecodePaddingMode, GeneralPurpose},
+    Engine as _,
+};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::skip_serializing_none;
 use url::Url;
 
-use crate::{error::MokshaCoreError, proof::Proofs};
+use crate::{
+    amount::Denominations, error::MokshaCoreError, primitives::CurrencyUnit, proof::Proofs,
+};
 
 const TOKEN_PREFIX_V3: &str = "cashuA";
 
+/// Wallets emit tokens in url-safe or standard base64, with or without padding. Tokens are always
+/// serialized url-safe with padding.
+const URL_SAFE_DECODER: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    general_purpose::PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+const STANDARD_DECODER: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    general_purpose::PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Token {
-    #[serde(serialize_with = "serialize_url", deserialize_with = "deserialize_url")]
+    #[serde(
+        default,
+        serialize_with = "serialize_url",
+        deserialize_with = "deserialize_url"
+    )]
     pub mint: Option<Url>,
     pub proofs: Proofs,
 }
@@ -101,13 +126,33 @@ impl TokenV3 {
         ))
     }
 
+    /// Serializes the token without the mint url, so it doesn't reveal where the funds came from.
+    /// The recipient has to know the mint to redeem the token.
+    pub fn serialize_without_mint(&self) -> Result<String, MokshaCoreError> {
+        Self {
+            tokens: self
+                .tokens
+                .iter()
+                .map(|token| Token {
+                    mint: None,
+                    ..token.clone()
+                })
+                .collect(),
+            memo: self.memo.clone(),
+        }
+        .serialize()
+    }
+
+    /// Accepts url-safe and standard base64, with or without padding
     pub fn deserialize(data: impl Into<String>) -> Result<TokenV3, MokshaCoreError> {
-        let json = general_purpose::URL_SAFE.decode(
-            data.into()
-                .strip_prefix(TOKEN_PREFIX_V3)
-                .ok_or(MokshaCoreError::InvalidTokenPrefix)?
-                .as_bytes(),
-        )?;
+        let data = data.into();
+        let encoded = data
+            .strip_prefix(TOKEN_PREFIX_V3)
+            .ok_or(MokshaCoreError::InvalidTokenPrefix)?
+            .as_bytes();
+        let json = URL_SAFE_DECODER
+            .decode(encoded)
+            .or_else(|_| STANDARD_DECODER.decode(encoded))?;
         Ok(serde_json::from_slice::<TokenV3>(&json)?)
     }
 
@@ -117,6 +162,92 @@ impl TokenV3 {
             .and_then(|token| token.mint.as_ref())
             .map(|url| url.to_owned())
     }
+
+    /// Checks that every proof of the token can be redeemed by the recipient: the secret must not
+    /// be empty and the amount must be a denomination of a supported scheme (a power of 2 or a
+    /// 1-2-5 amount), the mint rejects amounts it has no key for. Invalid `C`
+    /// points are already rejected while deserializing the token. The error contains the index
+    /// of the first invalid proof.
+    pub fn validate(&self) -> Result<(), MokshaCoreError> {
+        self.proofs()
+            .proofs()
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, proof)| {
+                if proof.secret.is_empty() {
+                    return Err(MokshaCoreError::InvalidProof(
+                        index,
+                        "secret must not be empty".to_owned(),
+                    ));
+                }
+                if !Denominations::PowersOfTwo.contains(proof.amount)
+                    && !Denominations::OneTwoFive.contains(proof.amount)
+                {
+                    return Err(MokshaCoreError::InvalidProof(
+                        index,
+                        format!("{} is not a valid denomination", proof.amount),
+                    ));
+                }
+                Ok(())
+            })
+    }
+
+    /// Combines the proofs of all tokens into a single token, e.g. to send several received
+    /// tokens at once. All tokens must be from the same mint, otherwise `TokenMergeMismatch` is
+    /// returned. TokenV3 doesn't contain a unit, so all tokens are sat. Memos are dropped.
+    pub fn merge(tokens: Vec<TokenV3>) -> Result<TokenV3, MokshaCoreError> {
+        let tokens = tokens
+            .into_iter()
+            .flat_map(|token| token.tokens)
+            .collect::<Vec<Token>>();
+        let Some(mint) = tokens.first().map(|token| token.mint.clone()) else {
+            return Ok(TokenV3::empty());
+        };
+        if tokens.iter().any(|token| token.mint != mint) {
+            return Err(MokshaCoreError::TokenMergeMismatch);
+        }
+
+        Ok(TokenV3::new(Token {
+            mint,
+            proofs: Proofs::new(
+                tokens
+                    .into_iter()
+                    .flat_map(|token| token.proofs.proofs())
+                    .collect(),
+            ),
+        }))
+    }
+
+    /// Returns an overview of the token without claiming it.
+    pub fn summary(&self) -> TokenSummary {
+        let denominations = self
+            .proofs()
+            .proofs()
+            .iter()
+            .fold(BTreeMap::new(), |mut acc, proof| {
+                *acc.entry(proof.amount).or_insert(0) += 1;
+                acc
+            })
+            .into_iter()
+            .collect::<Vec<(u64, usize)>>();
+
+        TokenSummary {
+            mint: self.mint(),
+            // TokenV3 doesn't contain a unit, so it is always sat
+            unit: CurrencyUnit::Sat,
+            total: self.total_amount(),
+            denominations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenSummary {
+    pub mint: Option<Url>,
+    pub unit: CurrencyUnit,
+    pub total: u64,
+    /// amount and number of proofs for each denomination, sorted by amount
+    pub denominations: Vec<(u64, usize)>,
 }
 
 impl TryFrom<TokenV3> for String {
@@ -154,6 +285,9 @@ mod tests {
 
     use crate::{
         dhke,
+        error::MokshaCoreError,
+        fixture::read_fixture,
+        primitives::CurrencyUnit,
         proof::Proof,
         token::{Token, TokenV3},
     };
@@ -190,15 +324,14 @@ mod tests {
         use base64::{engine::general_purpose, Engine as _};
         let token = Token {
             mint: Some(Url::parse("https://8333.space:3338/")?),
-            proofs: Proof {
-                amount: 21,
-                secret: "secret".to_string(),
-                c: dhke::public_key_from_hex(
+            proofs: Proof::new(
+                21,
+                "secret".to_string(),
+                dhke::public_key_from_hex(
                     "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
                 ),
-                keyset_id: "someid".to_string(),
-                script: None,
-            }
+                "someid".to_string(),
+            )
             .into(),
         };
         let tokens = super::TokenV3 {
@@ -226,4 +359,132 @@ mod tests {
         assert_eq!(tokens.tokens.len(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_tokens_deserialize_base64_variants() -> anyhow::Result<()> {
+        let mut tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        // the memo makes the standard encoding contain '+' and '/'
+        tokens.memo = Some("thank you???>>>".to_string());
+
+        let url_safe = tokens.serialize()?;
+        let standard = url_safe.replace('-', "+").replace('_', "/");
+        let unpadded = url_safe.trim_end_matches('=').to_string();
+        assert_ne!(url_safe, standard);
+        assert_ne!(url_safe, unpadded);
+
+        for encoded in [&url_safe, &standard, &unpadded] {
+            let decoded = TokenV3::try_from(encoded.to_owned())?;
+            assert_eq!(tokens.proofs(), decoded.proofs());
+            assert_eq!(tokens.memo, decoded.memo);
+            // decoded tokens are always serialized url-safe
+            assert_eq!(url_safe, decoded.serialize()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokens_serialize_without_mint() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        assert!(token.mint().is_some());
+
+        let serialized = token.serialize_without_mint()?;
+        let deserialized = TokenV3::deserialize(serialized)?;
+        assert_eq!(deserialized.mint(), None);
+        assert_eq!(deserialized.proofs(), token.proofs());
+        assert_eq!(deserialized.memo, token.memo);
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_validate() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        assert!(token.validate().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_validate_invalid_proof() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mut proofs = token.proofs().proofs();
+        proofs[1].secret = "".to_string();
+        let token = TokenV3::new(Token {
+            mint: token.mint(),
+            proofs: proofs.into(),
+        });
+
+        assert!(matches!(
+            token.validate(),
+            Err(MokshaCoreError::InvalidProof(1, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_with_malformed_c() -> anyhow::Result<()> {
+        // the x coordinate of C is larger than the field size, so it isn't a point on the curve
+        let js = json!(
+            {
+              "token": [{
+                "mint": "https://8333.space:3338",
+                "proofs": [{
+                  "id": "DSAl9nvvyfva",
+                  "amount": 2,
+                  "secret": "EhpennC9qB3iFlW8FZ_pZw",
+                  "C": "02ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+                }]
+              }]
+            }
+        );
+
+        assert!(serde_json::from_value::<TokenV3>(js).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_summary() -> anyhow::Result<()> {
+        let token: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let summary = token.summary();
+        assert_eq!(summary.mint, Some(Url::parse("http://127.0.0.1:3338")?));
+        assert_eq!(summary.unit, CurrencyUnit::Sat);
+        assert_eq!(summary.total, 60);
+        assert_eq!(
+            summary.denominations,
+            vec![(4, 1), (8, 1), (16, 1), (32, 1)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_tokens() -> anyhow::Result<()> {
+        let first: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let proof = Proof::new(
+            64,
+            "secret".to_string(),
+            dhke::public_key_from_hex(
+                "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
+            ),
+            "DSAl9nvvyfva".to_string(),
+        );
+        let second = TokenV3::from((first.mint().expect("mint is empty"), proof.into()));
+
+        let merged = TokenV3::merge(vec![first.clone(), second.clone()])?;
+        assert_eq!(1, merged.tokens.len());
+        assert_eq!(first.mint(), merged.mint());
+        assert_eq!(124, merged.total_amount());
+        assert_eq!(
+            first.proofs().len() + second.proofs().len(),
+            merged.proofs().len()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_tokens_of_different_mints() -> anyhow::Result<()> {
+        let first: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let second = TokenV3::from((Url::parse("https://other.mint:3338")?, first.proofs()));
+
+        let result = TokenV3::merge(vec![first, second]);
+        assert!(matches!(result, Err(MokshaCoreError::TokenMergeMismatch)));
+        Ok(())
+    }
 }
@@ -0,0 +1,4 @@
+-- invoices that have been reported as paid by a webhook of the lightning backend
+CREATE TABLE paid_invoices (
+    invoice_id TEXT NOT NULL PRIMARY KEY
+);
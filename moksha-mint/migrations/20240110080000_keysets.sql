@@ -0,0 +1,8 @@
+-- keysets that have been activated by the mint. The private keys are derived from the mint
+-- seed and the derivation path, so they are not stored
+CREATE TABLE keysets (
+    id SERIAL PRIMARY KEY,
+    keyset_id TEXT NOT NULL UNIQUE,
+    derivation_path TEXT NOT NULL,
+    activated_at BIGINT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- amount and unit the mint quote was created for
+ALTER TABLE bolt11_mint_quotes
+    ADD COLUMN amount BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN unit TEXT NOT NULL DEFAULT 'sat';
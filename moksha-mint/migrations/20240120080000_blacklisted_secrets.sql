@@ -0,0 +1,4 @@
+-- secrets of proofs that are known to be compromised, the mint refuses them even if unspent
+CREATE TABLE blacklisted_secrets (
+    secret TEXT NOT NULL PRIMARY KEY
+);
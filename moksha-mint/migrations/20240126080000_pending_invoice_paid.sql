@@ -0,0 +1,3 @@
+-- set once a pending invoice has been confirmed paid by the lightning backend
+ALTER TABLE pending_invoices
+    ADD COLUMN paid BOOLEAN NOT NULL DEFAULT FALSE;
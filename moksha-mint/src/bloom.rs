@@ -0,0 +1,80 @@
+//! A bloom filter of the secrets of spent proofs. It answers whether a secret has definitely not
+//! been spent without querying the database. A hit can be a false positive, so it has to be
+//! confirmed by the database.
+
+use bitcoin_hashes::{sha256, Hash};
+
+/// number of bits per expected item, together with `NUM_HASHES` this gives a false positive
+/// rate of about 1%
+const BITS_PER_ITEM: usize = 10;
+
+const NUM_HASHES: u64 = 7;
+
+/// minimum number of items the filter is sized for
+pub const MIN_FILTER_ITEMS: usize = 100_000;
+
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter that keeps the false positive rate at about 1% for up to
+    /// `expected_items` items
+    pub fn new(expected_items: usize) -> Self {
+        let num_bits = expected_items.max(1) * BITS_PER_ITEM;
+        Self {
+            bits: vec![0; num_bits.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, item: &str) {
+        for index in self.bit_indexes(item) {
+            self.bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    /// Returns false if the item has definitely not been inserted
+    pub fn might_contain(&self, item: &str) -> bool {
+        self.bit_indexes(item)
+            .all(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// Derives the bit indexes of the item from two halves of its sha256 hash (double hashing)
+    fn bit_indexes(&self, item: &str) -> impl Iterator<Item = usize> {
+        let hash = sha256::Hash::hash(item.as_bytes()).to_byte_array();
+        let h1 = u64::from_le_bytes(hash[..8].try_into().expect("hash has 32 bytes"));
+        let h2 = u64::from_le_bytes(hash[8..16].try_into().expect("hash has 32 bytes"));
+        let num_bits = self.bits.len() as u64 * 64;
+        (0..NUM_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BloomFilter;
+
+    #[test]
+    fn test_bloom_filter_contains_inserted_items() -> anyhow::Result<()> {
+        let mut filter = BloomFilter::new(1_000);
+        let items = (0..1_000)
+            .map(|i| format!("secret_{i}"))
+            .collect::<Vec<_>>();
+        items.iter().for_each(|item| filter.insert(item));
+
+        assert!(items.iter().all(|item| filter.might_contain(item)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bloom_filter_false_positive_rate() -> anyhow::Result<()> {
+        let mut filter = BloomFilter::new(1_000);
+        (0..1_000).for_each(|i| filter.insert(&format!("secret_{i}")));
+
+        let false_positives = (0..10_000)
+            .filter(|i| filter.might_contain(&format!("unspent_{i}")))
+            .count();
+        assert!(false_positives < 300, "{false_positives} false positives");
+        Ok(())
+    }
+}
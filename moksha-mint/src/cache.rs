@@ -0,0 +1,152 @@
+//! A cache of the responses of mint, melt and swap requests as described in [Nut-19](https://github.com/cashubtc/nuts/blob/main/19.md).
+//! If a wallet retries a request, e.g. after a network error, the mint returns the cached
+//! response instead of processing the request again.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bitcoin_hashes::{sha256, Hash};
+
+/// default time in seconds for which responses are cached
+pub const DEFAULT_RESPONSE_CACHE_TTL_SECS: u64 = 300;
+
+/// default maximum number of cached responses. If the cache is full, the oldest entry is dropped
+pub const DEFAULT_RESPONSE_CACHE_MAX_ENTRIES: usize = 10_000;
+
+/// paths of the routes whose responses are cached
+pub const CACHED_PATHS: [&str; 3] = ["/v1/mint/bolt11", "/v1/melt/bolt11", "/v1/swap"];
+
+#[derive(Debug)]
+pub struct ResponseCache {
+    ttl: Duration,
+    max_entries: usize,
+    /// increasing number of the inserts, used to find the oldest entry
+    inserts: AtomicU64,
+    /// cached response bodies by the hash of path and request body
+    entries: Mutex<HashMap<sha256::Hash, (Instant, u64, Vec<u8>)>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            max_entries: DEFAULT_RESPONSE_CACHE_MAX_ENTRIES,
+            inserts: AtomicU64::new(0),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_max_entries(self, max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            ..self
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the key of a request, which is the hash of its path and body
+    pub fn key(path: &str, body: &[u8]) -> sha256::Hash {
+        sha256::Hash::hash(&[path.as_bytes(), b"\n", body].concat())
+    }
+
+    /// Returns the cached response body, if it hasn't expired yet
+    pub fn get(&self, key: &sha256::Hash) -> Option<Vec<u8>> {
+        let entries = self
+            .entries
+            .lock()
+            .expect("response cache lock is poisoned");
+        entries
+            .get(key)
+            .filter(|(created_at, _, _)| created_at.elapsed() < self.ttl)
+            .map(|(_, _, body)| body.clone())
+    }
+
+    /// Stores the response body and drops all expired entries. If the cache is still full, the
+    /// oldest entries are dropped as well, so it can't grow without bounds within the ttl.
+    pub fn insert(&self, key: sha256::Hash, body: Vec<u8>) {
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("response cache lock is poisoned");
+        entries.retain(|_, (created_at, _, _)| created_at.elapsed() < self.ttl);
+        while !entries.contains_key(&key) && entries.len() >= self.max_entries.max(1) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (_, insert, _))| *insert)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(oldest) => entries.remove(&oldest),
+                None => break,
+            };
+        }
+        let insert = self.inserts.fetch_add(1, Ordering::Relaxed);
+        entries.insert(key, (Instant::now(), insert, body));
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_RESPONSE_CACHE_TTL_SECS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ResponseCache;
+
+    #[test]
+    fn test_response_cache() -> anyhow::Result<()> {
+        let cache = ResponseCache::default();
+        let key = ResponseCache::key("/v1/swap", b"{}");
+        assert_eq!(None, cache.get(&key));
+
+        cache.insert(key, b"response".to_vec());
+        assert_eq!(Some(b"response".to_vec()), cache.get(&key));
+        assert_eq!(
+            None,
+            cache.get(&ResponseCache::key("/v1/melt/bolt11", b"{}"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_cache_drops_oldest_entry_if_full() -> anyhow::Result<()> {
+        let cache = ResponseCache::default().with_max_entries(2);
+        let keys = ["/v1/swap", "/v1/mint/bolt11", "/v1/melt/bolt11"]
+            .map(|path| ResponseCache::key(path, b"{}"));
+
+        for key in keys {
+            cache.insert(key, b"response".to_vec());
+        }
+        assert_eq!(None, cache.get(&keys[0]));
+        assert_eq!(Some(b"response".to_vec()), cache.get(&keys[1]));
+        assert_eq!(Some(b"response".to_vec()), cache.get(&keys[2]));
+
+        // replacing a cached response doesn't drop another entry
+        cache.insert(keys[2], b"updated".to_vec());
+        assert_eq!(Some(b"response".to_vec()), cache.get(&keys[1]));
+        assert_eq!(Some(b"updated".to_vec()), cache.get(&keys[2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_cache_expired() -> anyhow::Result<()> {
+        let cache = ResponseCache::new(Duration::ZERO);
+        let key = ResponseCache::key("/v1/swap", b"{}");
+
+        cache.insert(key, b"response".to_vec());
+        assert_eq!(None, cache.get(&key));
+        Ok(())
+    }
+}
@@ -0,0 +1,508 @@
+//! Configuration of the mint binary from environment variables. All settings are validated at
+//! once, so operators see every invalid or missing setting in a single report.
+
+use std::{
+    collections::HashMap, env, fmt, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration,
+};
+
+use moksha_core::{amount::Denominations, keyset::MAX_ORDER, primitives::CurrencyUnit};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::{
+    cache::DEFAULT_RESPONSE_CACHE_TTL_SECS,
+    database::{memory::MEMORY_DB_URL, postgres::DEFAULT_DB_MAX_CONNECTIONS},
+    error::MokshaMintError,
+    info::MintInfoSettings,
+    lightning::{
+        AlbyLightningSettings, LightningType, LnbitsLightningSettings, LndLightningSettings,
+        StrikeLightningSettings, DEFAULT_LIGHTNING_RPC_TIMEOUT_SECS,
+    },
+    mint::{
+        LightningFeeTier, MintBuilder, DEFAULT_MAX_BODY_BYTES, DEFAULT_MAX_OUTPUTS,
+        DEFAULT_QUOTE_EXPIRY_SECS,
+    },
+    mnemonic::{secret_from_mnemonic, DEFAULT_MNEMONIC_DERIVATION_PATH},
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("{0} not found")]
+    Missing(String),
+
+    #[error("Invalid value for {0} - {1}")]
+    Invalid(String, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppEnv {
+    Dev,
+    Prod,
+}
+
+impl AppEnv {
+    /// Reads the environment from `MINT_APP_ENV`. Every value except `dev` is prod.
+    pub fn from_env() -> Self {
+        Self::from_value(env::var("MINT_APP_ENV").ok().as_deref())
+    }
+
+    fn from_value(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            Some("dev") => Self::Dev,
+            _ => Self::Prod,
+        }
+    }
+}
+
+impl fmt::Display for AppEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppEnv::Dev => write!(f, "dev"),
+            AppEnv::Prod => write!(f, "prod"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MintConfig {
+    pub app_env: AppEnv,
+    pub host_port: SocketAddr,
+    pub api_prefix: Option<String>,
+    /// mounts all routes, including the api under `api_prefix`, e.g. `/mint-a/api/v1/keys`
+    pub base_path: Option<String>,
+    pub serve_wallet_path: Option<PathBuf>,
+    pub private_key: String,
+    pub db_url: String,
+    pub db_max_connections: u32,
+    pub lightning_type: LightningType,
+    pub lightning_timeout: Duration,
+    pub fee_percent: f32,
+    /// minimum fee reserve in msat
+    pub fee_reserve_min: u64,
+    pub fee_tiers: Vec<LightningFeeTier>,
+    pub mint_info: MintInfoSettings,
+    pub admin_token: Option<String>,
+    pub max_order: u8,
+    pub denominations: Denominations,
+    pub max_outputs: usize,
+    pub max_dust_ratio: Option<usize>,
+    pub max_body_bytes: usize,
+    pub quote_expiry: Duration,
+    pub response_cache_ttl: Duration,
+    pub unit: CurrencyUnit,
+    pub legacy_api: bool,
+    pub require_auth: bool,
+    pub minting_disabled: bool,
+}
+
+impl MintConfig {
+    /// Reads the configuration from the environment. Returns all invalid or missing settings if
+    /// the configuration is invalid.
+    pub fn from_env() -> Result<Self, Vec<ConfigError>> {
+        Self::from_vars(env::vars())
+    }
+
+    pub fn from_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, Vec<ConfigError>> {
+        let mut vars = Vars::new(vars);
+
+        let app_env = AppEnv::from_value(vars.optional("MINT_APP_ENV").as_deref());
+        let host_port = vars.parse_or("MINT_HOST_PORT", SocketAddr::from(([0; 16], 3338)));
+        let api_prefix = vars.optional("MINT_API_PREFIX");
+        let base_path = vars.optional("MINT_BASE_PATH");
+        let serve_wallet_path = vars.optional("MINT_SERVE_WALLET_PATH").map(PathBuf::from);
+        // a mnemonic replaces the raw private key
+        let private_key = match vars.optional("MINT_MNEMONIC") {
+            Some(mnemonic) => {
+                if vars.optional("MINT_PRIVATE_KEY").is_some() {
+                    vars.invalid("MINT_MNEMONIC", "can't be combined with MINT_PRIVATE_KEY");
+                }
+                let derivation_path = vars
+                    .optional("MINT_MNEMONIC_DERIVATION_PATH")
+                    .unwrap_or_else(|| DEFAULT_MNEMONIC_DERIVATION_PATH.to_owned());
+                secret_from_mnemonic(&mnemonic, &derivation_path).unwrap_or_else(|err| {
+                    match err {
+                        MokshaMintError::InvalidDerivationPath(_) => {
+                            vars.invalid("MINT_MNEMONIC_DERIVATION_PATH", err)
+                        }
+                        _ => vars.invalid("MINT_MNEMONIC", err),
+                    }
+                    String::new()
+                })
+            }
+            None => vars.required("MINT_PRIVATE_KEY").unwrap_or_default(),
+        };
+
+        // in dev mode the mint falls back to the in-memory database if no db url is set
+        let db_url = match (vars.optional("MINT_DB_URL"), app_env) {
+            (Some(db_url), _) => {
+                if !db_url.starts_with(MEMORY_DB_URL) && !db_url.starts_with("postgres") {
+                    vars.invalid("MINT_DB_URL", "must be a postgres or memory url");
+                }
+                db_url
+            }
+            (None, AppEnv::Dev) => MEMORY_DB_URL.to_owned(),
+            (None, AppEnv::Prod) => {
+                vars.missing("MINT_DB_URL");
+                String::new()
+            }
+        };
+        let db_max_connections =
+            vars.parse_or("MINT_DB_MAX_CONNECTIONS", DEFAULT_DB_MAX_CONNECTIONS);
+
+        let lightning_type = vars.lightning_type();
+        let lightning_timeout = Duration::from_secs(vars.parse_or(
+            "LIGHTNING_RPC_TIMEOUT_SECS",
+            DEFAULT_LIGHTNING_RPC_TIMEOUT_SECS,
+        ));
+
+        let fee_percent = vars.parse_required::<f32>("LIGHTNING_FEE_PERCENT");
+        if fee_percent.is_some_and(|fee_percent| !(0.0..=100.0).contains(&fee_percent)) {
+            vars.invalid("LIGHTNING_FEE_PERCENT", "must be between 0 and 100");
+        }
+        let fee_reserve_min = vars.parse_required("LIGHTNING_RESERVE_FEE_MIN");
+        let fee_tiers = match vars.optional("LIGHTNING_FEE_TIERS") {
+            Some(value) => LightningFeeTier::parse_list(&value).unwrap_or_else(|err| {
+                vars.invalid("LIGHTNING_FEE_TIERS", err);
+                vec![]
+            }),
+            None => vec![],
+        };
+
+        let mint_info = MintInfoSettings::from_vars(vars.vars.clone()).unwrap_or_else(|err| {
+            vars.invalid("MINT_INFO_*", err);
+            MintInfoSettings::default()
+        });
+
+        let max_order = vars.parse_or("MINT_MAX_ORDER", MAX_ORDER);
+        if max_order == 0 || max_order > MAX_ORDER {
+            vars.invalid(
+                "MINT_MAX_ORDER",
+                format!("must be between 1 and {MAX_ORDER}"),
+            );
+        }
+
+        let denominations = vars.parse_or("MINT_DENOMINATIONS", Denominations::PowersOfTwo);
+        let max_outputs = vars.parse_or("MINT_MAX_OUTPUTS", DEFAULT_MAX_OUTPUTS);
+        let max_dust_ratio = vars
+            .optional("MINT_MAX_DUST_RATIO")
+            .and_then(|value| vars.parse::<usize>("MINT_MAX_DUST_RATIO", &value));
+        if max_dust_ratio == Some(0) {
+            vars.invalid("MINT_MAX_DUST_RATIO", "must be at least 1");
+        }
+        let max_body_bytes = vars.parse_or("MINT_MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES);
+        let quote_expiry = vars.parse_or("MINT_QUOTE_EXPIRY_SECS", DEFAULT_QUOTE_EXPIRY_SECS);
+        let response_cache_ttl = vars.parse_or(
+            "MINT_RESPONSE_CACHE_TTL_SECS",
+            DEFAULT_RESPONSE_CACHE_TTL_SECS,
+        );
+        let unit = vars.parse_or("MINT_UNIT", CurrencyUnit::Sat);
+        let legacy_api = vars.parse_or("MINT_ENABLE_LEGACY_API", true);
+        let require_auth = vars.parse_or("MINT_REQUIRE_AUTH", false);
+        let minting_disabled = vars.parse_or("MINT_DISABLE_MINTING", false);
+
+        // the lightning type is only missing if an error has been reported
+        let (Some(lightning_type), true) = (lightning_type, vars.errors.is_empty()) else {
+            return Err(vars.errors);
+        };
+
+        Ok(Self {
+            app_env,
+            host_port,
+            api_prefix,
+            base_path,
+            serve_wallet_path,
+            private_key,
+            db_url,
+            db_max_connections,
+            lightning_type,
+            lightning_timeout,
+            fee_percent: fee_percent.unwrap_or_default(),
+            fee_reserve_min: fee_reserve_min.unwrap_or_default(),
+            fee_tiers,
+            mint_info,
+            admin_token: vars.optional("MINT_ADMIN_TOKEN"),
+            max_order,
+            denominations,
+            max_outputs,
+            max_dust_ratio,
+            max_body_bytes,
+            quote_expiry: Duration::from_secs(quote_expiry),
+            response_cache_ttl: Duration::from_secs(response_cache_ttl),
+            unit,
+            legacy_api,
+            require_auth,
+            minting_disabled,
+        })
+    }
+
+    /// Returns a builder for the mint with all settings applied
+    pub fn builder(&self) -> MintBuilder {
+        MintBuilder::new()
+            .with_mint_info(self.mint_info.clone())
+            .with_private_key(self.private_key.clone())
+            .with_db(self.db_url.clone())
+            .with_db_pool_size(self.db_max_connections)
+            .with_lightning(self.lightning_type.clone())
+            .with_lightning_timeout(self.lightning_timeout)
+            .with_admin_token(self.admin_token.clone())
+            .with_max_order(self.max_order)
+            .with_denominations(self.denominations)
+            .with_max_outputs(self.max_outputs)
+            .with_max_dust_ratio(self.max_dust_ratio)
+            .with_max_body_bytes(self.max_body_bytes)
+            .with_quote_expiry(self.quote_expiry)
+            .with_response_cache_ttl(self.response_cache_ttl)
+            .with_unit(self.unit.clone())
+            .with_legacy_api(self.legacy_api)
+            .with_require_auth(self.require_auth)
+            .with_minting_disabled(self.minting_disabled)
+            .with_fee(self.fee_percent, self.fee_reserve_min)
+            .with_fee_tiers(self.fee_tiers.clone())
+    }
+}
+
+/// Environment variables and the errors found while reading them
+struct Vars {
+    vars: HashMap<String, String>,
+    errors: Vec<ConfigError>,
+}
+
+impl Vars {
+    fn new(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            vars: vars.into_iter().collect(),
+            errors: vec![],
+        }
+    }
+
+    fn missing(&mut self, key: &str) {
+        self.errors.push(ConfigError::Missing(key.to_owned()));
+    }
+
+    fn invalid(&mut self, key: &str, reason: impl ToString) {
+        self.errors
+            .push(ConfigError::Invalid(key.to_owned(), reason.to_string()));
+    }
+
+    fn optional(&self, key: &str) -> Option<String> {
+        self.vars.get(key).cloned()
+    }
+
+    fn required(&mut self, key: &str) -> Option<String> {
+        match self.vars.get(key).filter(|value| !value.trim().is_empty()) {
+            Some(value) => Some(value.to_owned()),
+            None => {
+                self.missing(key);
+                None
+            }
+        }
+    }
+
+    fn parse<T>(&mut self, key: &str, value: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        value
+            .trim()
+            .parse()
+            .map_err(|err| self.invalid(key, err))
+            .ok()
+    }
+
+    fn parse_required<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self.required(key)?;
+        self.parse(key, &value)
+    }
+
+    fn parse_or<T>(&mut self, key: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.optional(key) {
+            Some(value) => self.parse(key, &value).unwrap_or(default),
+            None => default,
+        }
+    }
+
+    fn settings<T: DeserializeOwned>(&mut self, prefix: &str) -> Option<T> {
+        envy::prefixed(prefix)
+            .from_iter(self.vars.clone())
+            .map_err(|err| self.invalid(&format!("{prefix}*"), err))
+            .ok()
+    }
+
+    /// Reads the settings of the backend in `MINT_LIGHTNING_BACKEND` and checks that all
+    /// required settings of the backend are set
+    fn lightning_type(&mut self) -> Option<LightningType> {
+        let backend = self.required("MINT_LIGHTNING_BACKEND")?;
+        let lightning_type = match backend.as_str() {
+            "Lnbits" => LightningType::Lnbits(self.settings("LNBITS_")?),
+            "Lnd" => LightningType::Lnd(self.settings("LND_")?),
+            "Alby" => LightningType::Alby(self.settings("ALBY_")?),
+            "Strike" => LightningType::Strike(self.settings("STRIKE_")?),
+            _ => {
+                self.invalid(
+                    "MINT_LIGHTNING_BACKEND",
+                    "valid values are Lnbits, Lnd, Alby, and Strike",
+                );
+                return None;
+            }
+        };
+
+        match &lightning_type {
+            LightningType::Lnbits(LnbitsLightningSettings { admin_key, url }) => {
+                self.check_set("LNBITS_ADMIN_KEY", admin_key.is_some());
+                self.check_set("LNBITS_URL", url.is_some());
+            }
+            LightningType::Lnd(LndLightningSettings {
+                grpc_host,
+                tls_cert_path,
+                macaroon_path,
+                tls_cert_pem,
+                macaroon_hex,
+            }) => {
+                self.check_set("LND_GRPC_HOST", grpc_host.is_some());
+                self.check_set(
+                    "LND_TLS_CERT_PATH or LND_TLS_CERT_PEM",
+                    tls_cert_path.is_some() || tls_cert_pem.is_some(),
+                );
+                self.check_set(
+                    "LND_MACAROON_PATH or LND_MACAROON_HEX",
+                    macaroon_path.is_some() || macaroon_hex.is_some(),
+                );
+            }
+            LightningType::Alby(AlbyLightningSettings { api_key, .. }) => {
+                self.check_set("ALBY_API_KEY", api_key.is_some());
+            }
+            LightningType::Strike(StrikeLightningSettings { api_key, .. }) => {
+                self.check_set("STRIKE_API_KEY", api_key.is_some());
+            }
+            #[cfg(feature = "custom-lightning")]
+            LightningType::Custom(_) => {}
+        }
+        Some(lightning_type)
+    }
+
+    fn check_set(&mut self, key: &str, is_set: bool) {
+        if !is_set {
+            self.missing(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::amount::Denominations;
+
+    use crate::{
+        config::{ConfigError, MintConfig},
+        mnemonic::{secret_from_mnemonic, DEFAULT_MNEMONIC_DERIVATION_PATH},
+    };
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_config_from_vars() -> anyhow::Result<()> {
+        let config = MintConfig::from_vars(vars(&[
+            ("MINT_PRIVATE_KEY", "my_private_key"),
+            ("MINT_DB_URL", "memory://"),
+            ("MINT_LIGHTNING_BACKEND", "Lnbits"),
+            ("LNBITS_ADMIN_KEY", "admin_key"),
+            ("LNBITS_URL", "http://localhost:5000"),
+            ("LIGHTNING_FEE_PERCENT", "1.0"),
+            ("LIGHTNING_RESERVE_FEE_MIN", "4000"),
+            ("MINT_MAX_OUTPUTS", "100"),
+            ("MINT_DENOMINATIONS", "1-2-5"),
+        ]))
+        .map_err(|errors| anyhow::anyhow!("{errors:?}"))?;
+
+        assert_eq!("my_private_key", config.private_key);
+        assert_eq!(100, config.max_outputs);
+        assert_eq!(Denominations::OneTwoFive, config.denominations);
+        assert_eq!("Lnbits", config.lightning_type.name());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_private_key_from_mnemonic() -> anyhow::Result<()> {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let config = MintConfig::from_vars(vars(&[
+            ("MINT_MNEMONIC", mnemonic),
+            ("MINT_DB_URL", "memory://"),
+            ("MINT_LIGHTNING_BACKEND", "Lnbits"),
+            ("LNBITS_ADMIN_KEY", "admin_key"),
+            ("LNBITS_URL", "http://localhost:5000"),
+            ("LIGHTNING_FEE_PERCENT", "1.0"),
+            ("LIGHTNING_RESERVE_FEE_MIN", "4000"),
+        ]))
+        .map_err(|errors| anyhow::anyhow!("{errors:?}"))?;
+
+        assert_eq!(
+            secret_from_mnemonic(mnemonic, DEFAULT_MNEMONIC_DERIVATION_PATH)?,
+            config.private_key
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_rejects_invalid_derivation_path() -> anyhow::Result<()> {
+        let errors = MintConfig::from_vars(vars(&[
+            ("MINT_MNEMONIC", "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
+            ("MINT_MNEMONIC_DERIVATION_PATH", "m/129372'/O'"),
+            ("MINT_DB_URL", "memory://"),
+            ("MINT_LIGHTNING_BACKEND", "Lnbits"),
+            ("LNBITS_ADMIN_KEY", "admin_key"),
+            ("LNBITS_URL", "http://localhost:5000"),
+            ("LIGHTNING_FEE_PERCENT", "1.0"),
+            ("LIGHTNING_RESERVE_FEE_MIN", "4000"),
+        ]))
+        .expect_err("config must be invalid");
+
+        assert_eq!(1, errors.len(), "{errors:?}");
+        assert!(matches!(
+            &errors[0],
+            ConfigError::Invalid(key, _) if key == "MINT_MNEMONIC_DERIVATION_PATH"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_reports_all_errors() -> anyhow::Result<()> {
+        let errors = MintConfig::from_vars(vars(&[
+            ("MINT_LIGHTNING_BACKEND", "Lnbits"),
+            ("LNBITS_URL", "http://localhost:5000"),
+            ("LIGHTNING_FEE_PERCENT", "150"),
+            ("MINT_MAX_OUTPUTS", "many"),
+        ]))
+        .expect_err("config must be invalid");
+
+        assert_eq!(6, errors.len(), "{errors:?}");
+        for error in [
+            ConfigError::Missing("MINT_PRIVATE_KEY".to_owned()),
+            ConfigError::Missing("MINT_DB_URL".to_owned()),
+            ConfigError::Missing("LNBITS_ADMIN_KEY".to_owned()),
+            ConfigError::Missing("LIGHTNING_RESERVE_FEE_MIN".to_owned()),
+        ] {
+            assert!(errors.contains(&error), "{error} is not reported");
+        }
+        assert!(errors.iter().any(
+            |error| matches!(error, ConfigError::Invalid(key, _) if key == "LIGHTNING_FEE_PERCENT")
+        ));
+        assert!(errors.iter().any(
+            |error| matches!(error, ConfigError::Invalid(key, _) if key == "MINT_MAX_OUTPUTS")
+        ));
+        Ok(())
+    }
+}
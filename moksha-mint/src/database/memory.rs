@@ -0,0 +1,304 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use moksha_core::{
+    primitives::{Bolt11MeltQuote, Bolt11MintQuote},
+    proof::{Proof, Proofs},
+};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    error::MokshaMintError,
+    model::{Invoice, KeysetInfo},
+};
+
+use super::Database;
+
+/// db url that selects the in-memory database
+pub const MEMORY_DB_URL: &str = "memory://";
+
+/// Database that keeps everything in memory. All data is lost when the mint is stopped, so it
+/// should only be used for local development, examples and tests.
+#[derive(Default)]
+pub struct InMemoryDatabase {
+    /// used proofs by secret
+    used_proofs: RwLock<HashMap<String, Proof>>,
+    pending_invoices: RwLock<HashMap<String, Invoice>>,
+    paid_invoices: RwLock<HashSet<String>>,
+    blacklisted_secrets: RwLock<HashSet<String>>,
+    mint_quotes: RwLock<HashMap<Uuid, Bolt11MintQuote>>,
+    melt_quotes: RwLock<HashMap<Uuid, Bolt11MeltQuote>>,
+    /// keysets in the order they were activated
+    keysets: RwLock<Vec<KeysetInfo>>,
+}
+
+impl InMemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Database for InMemoryDatabase {
+    async fn get_used_proofs(&self) -> Result<Proofs, MokshaMintError> {
+        Ok(Proofs::new(
+            self.used_proofs.read().await.values().cloned().collect(),
+        ))
+    }
+
+    async fn add_used_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
+        let mut used_proofs = self.used_proofs.write().await;
+        // like the unique constraint of postgres, a secret that is repeated within the batch is
+        // rejected as well
+        let mut batch_secrets = HashSet::new();
+        let used_indices = proofs
+            .proofs()
+            .iter()
+            .enumerate()
+            .filter(|(_, proof)| {
+                used_proofs.contains_key(&proof.secret)
+                    || !batch_secrets.insert(proof.secret.clone())
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        if !used_indices.is_empty() {
+            return Err(MokshaMintError::ProofsAlreadyUsed(used_indices));
+        }
+
+        for proof in proofs.proofs() {
+            used_proofs.insert(proof.secret.clone(), proof);
+        }
+        Ok(())
+    }
+
+    async fn delete_used_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
+        let mut used_proofs = self.used_proofs.write().await;
+        for proof in proofs.proofs() {
+            used_proofs.remove(&proof.secret);
+        }
+        Ok(())
+    }
+
+    async fn get_pending_invoice(&self, key: String) -> Result<Invoice, MokshaMintError> {
+        self.pending_invoices
+            .read()
+            .await
+            .get(&key)
+            .cloned()
+            .ok_or(MokshaMintError::InvoiceNotFound(key))
+    }
+
+    async fn add_pending_invoice(
+        &self,
+        key: String,
+        invoice: &Invoice,
+    ) -> Result<(), MokshaMintError> {
+        self.pending_invoices
+            .write()
+            .await
+            .insert(key, invoice.clone());
+        Ok(())
+    }
+
+    async fn add_bolt11_mint_quotes(
+        &self,
+        quotes: &[(Bolt11MintQuote, Invoice)],
+    ) -> Result<(), MokshaMintError> {
+        let mut pending_invoices = self.pending_invoices.write().await;
+        let mut mint_quotes = self.mint_quotes.write().await;
+        for (quote, invoice) in quotes {
+            pending_invoices.insert(quote.quote_id.to_string(), invoice.clone());
+            mint_quotes.insert(quote.quote_id, quote.clone());
+        }
+        Ok(())
+    }
+
+    async fn delete_pending_invoice(&self, key: String) -> Result<(), MokshaMintError> {
+        self.pending_invoices.write().await.remove(&key);
+        Ok(())
+    }
+
+    async fn mark_pending_invoice_paid(&self, key: &str) -> Result<(), MokshaMintError> {
+        self.pending_invoices
+            .write()
+            .await
+            .get_mut(key)
+            .map(|invoice| invoice.paid = true)
+            .ok_or(MokshaMintError::InvoiceNotFound(key.to_owned()))
+    }
+
+    async fn add_paid_invoice(&self, invoice_id: &str) -> Result<(), MokshaMintError> {
+        self.paid_invoices
+            .write()
+            .await
+            .insert(invoice_id.to_owned());
+        Ok(())
+    }
+
+    async fn is_invoice_marked_paid(&self, invoice_id: &str) -> Result<bool, MokshaMintError> {
+        Ok(self.paid_invoices.read().await.contains(invoice_id))
+    }
+
+    async fn add_blacklisted_secret(&self, secret: &str) -> Result<(), MokshaMintError> {
+        self.blacklisted_secrets
+            .write()
+            .await
+            .insert(secret.to_owned());
+        Ok(())
+    }
+
+    async fn is_blacklisted(&self, secret: &str) -> Result<bool, MokshaMintError> {
+        Ok(self.blacklisted_secrets.read().await.contains(secret))
+    }
+
+    async fn get_bolt11_mint_quote(&self, key: &Uuid) -> Result<Bolt11MintQuote, MokshaMintError> {
+        self.mint_quotes
+            .read()
+            .await
+            .get(key)
+            .cloned()
+            .ok_or(MokshaMintError::InvalidQuote(key.to_string()))
+    }
+
+    async fn add_bolt11_mint_quote(&self, quote: &Bolt11MintQuote) -> Result<(), MokshaMintError> {
+        self.mint_quotes
+            .write()
+            .await
+            .insert(quote.quote_id, quote.clone());
+        Ok(())
+    }
+
+    async fn update_bolt11_mint_quote(
+        &self,
+        quote: &Bolt11MintQuote,
+    ) -> Result<(), MokshaMintError> {
+        self.add_bolt11_mint_quote(quote).await
+    }
+
+    async fn delete_bolt11_mint_quote(
+        &self,
+        quote: &Bolt11MintQuote,
+    ) -> Result<(), MokshaMintError> {
+        self.mint_quotes.write().await.remove(&quote.quote_id);
+        Ok(())
+    }
+
+    async fn get_bolt11_melt_quote(&self, key: &Uuid) -> Result<Bolt11MeltQuote, MokshaMintError> {
+        self.melt_quotes
+            .read()
+            .await
+            .get(key)
+            .cloned()
+            .ok_or(MokshaMintError::InvalidQuote(key.to_string()))
+    }
+
+    async fn add_bolt11_melt_quote(&self, quote: &Bolt11MeltQuote) -> Result<(), MokshaMintError> {
+        self.melt_quotes
+            .write()
+            .await
+            .insert(quote.quote_id, quote.clone());
+        Ok(())
+    }
+
+    async fn update_bolt11_melt_quote(
+        &self,
+        quote: &Bolt11MeltQuote,
+    ) -> Result<(), MokshaMintError> {
+        self.add_bolt11_melt_quote(quote).await
+    }
+
+    async fn delete_bolt11_melt_quote(
+        &self,
+        quote: &Bolt11MeltQuote,
+    ) -> Result<(), MokshaMintError> {
+        self.melt_quotes.write().await.remove(&quote.quote_id);
+        Ok(())
+    }
+
+    async fn get_keysets(&self) -> Result<Vec<KeysetInfo>, MokshaMintError> {
+        Ok(self.keysets.read().await.clone())
+    }
+
+    async fn add_keyset(&self, keyset: &KeysetInfo) -> Result<(), MokshaMintError> {
+        let mut keysets = self.keysets.write().await;
+        if !keysets.iter().any(|k| k.keyset_id == keyset.keyset_id) {
+            keysets.push(keyset.clone());
+        }
+        Ok(())
+    }
+
+    async fn close(&self) {}
+
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::{fixture::read_fixture, proof::Proofs, token::TokenV3};
+
+    use super::InMemoryDatabase;
+    use crate::{database::Database, error::MokshaMintError, model::Invoice};
+
+    #[tokio::test]
+    async fn test_used_proofs() -> anyhow::Result<()> {
+        let db = InMemoryDatabase::new();
+        assert!(db.get_used_proofs().await?.is_empty());
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        db.add_used_proofs(&tokens.proofs()).await?;
+        // like the unique constraint of postgres, spent secrets can't be stored again
+        assert!(matches!(
+            db.add_used_proofs(&tokens.proofs()).await,
+            Err(MokshaMintError::ProofsAlreadyUsed(indices)) if indices.len() == tokens.proofs().len()
+        ));
+
+        let mut expected = tokens.proofs().proofs();
+        expected.sort_by_key(|proof| proof.amount);
+        let mut used_proofs = db.get_used_proofs().await?.proofs();
+        used_proofs.sort_by_key(|proof| proof.amount);
+        assert_eq!(expected, used_proofs);
+
+        db.delete_used_proofs(&tokens.proofs()).await?;
+        assert!(db.get_used_proofs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_used_proofs_rejects_duplicate_secret_in_batch() -> anyhow::Result<()> {
+        let db = InMemoryDatabase::new();
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let proofs = tokens.proofs().proofs();
+        let batch = Proofs::new(vec![
+            proofs[0].clone(),
+            proofs[1].clone(),
+            proofs[0].clone(),
+        ]);
+
+        assert!(matches!(
+            db.add_used_proofs(&batch).await,
+            Err(MokshaMintError::ProofsAlreadyUsed(indices)) if indices == vec![2]
+        ));
+        // none of the proofs of the batch are stored
+        assert!(db.get_used_proofs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pending_invoice() -> anyhow::Result<()> {
+        let db = InMemoryDatabase::new();
+        let invoice = Invoice::new(100, "lnbcrt1u1...".to_string());
+
+        db.add_pending_invoice("key".to_string(), &invoice).await?;
+        assert_eq!(invoice, db.get_pending_invoice("key".to_string()).await?);
+
+        db.delete_pending_invoice("key".to_string()).await?;
+        assert!(matches!(
+            db.get_pending_invoice("key".to_string()).await,
+            Err(MokshaMintError::InvoiceNotFound(_))
+        ));
+        Ok(())
+    }
+}
@@ -5,8 +5,12 @@ use moksha_core::{
 };
 use uuid::Uuid;
 
-use crate::{error::MokshaMintError, model::Invoice};
+use crate::{
+    error::MokshaMintError,
+    model::{Invoice, KeysetInfo},
+};
 
+pub mod memory;
 pub mod postgres;
 
 #[cfg(test)]
@@ -16,7 +20,11 @@ use mockall::automock;
 #[async_trait]
 pub trait Database {
     async fn get_used_proofs(&self) -> Result<Proofs, MokshaMintError>;
+    /// Stores all proofs or none of them. Fails with `ProofsAlreadyUsed` if one of the secrets
+    /// has been stored before or is repeated in the batch.
     async fn add_used_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError>;
+    /// Releases proofs that have been stored before a payment that failed
+    async fn delete_used_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError>;
 
     async fn get_pending_invoice(&self, key: String) -> Result<Invoice, MokshaMintError>;
     async fn add_pending_invoice(
@@ -24,7 +32,22 @@ pub trait Database {
         key: String,
         invoice: &Invoice,
     ) -> Result<(), MokshaMintError>;
+    /// Stores the mint quotes together with their pending invoices, which use the quote id as
+    /// key. Either all of them are stored or none.
+    async fn add_bolt11_mint_quotes(
+        &self,
+        quotes: &[(Bolt11MintQuote, Invoice)],
+    ) -> Result<(), MokshaMintError>;
     async fn delete_pending_invoice(&self, key: String) -> Result<(), MokshaMintError>;
+    /// Stores that the pending invoice has been paid
+    async fn mark_pending_invoice_paid(&self, key: &str) -> Result<(), MokshaMintError>;
+
+    async fn add_paid_invoice(&self, invoice_id: &str) -> Result<(), MokshaMintError>;
+    async fn is_invoice_marked_paid(&self, invoice_id: &str) -> Result<bool, MokshaMintError>;
+
+    /// Proofs with a blacklisted secret are refused even if they haven't been spent
+    async fn add_blacklisted_secret(&self, secret: &str) -> Result<(), MokshaMintError>;
+    async fn is_blacklisted(&self, secret: &str) -> Result<bool, MokshaMintError>;
 
     async fn get_bolt11_mint_quote(&self, key: &Uuid) -> Result<Bolt11MintQuote, MokshaMintError>;
     async fn add_bolt11_mint_quote(&self, quote: &Bolt11MintQuote) -> Result<(), MokshaMintError>;
@@ -47,4 +70,14 @@ pub trait Database {
         &self,
         quote: &Bolt11MeltQuote,
     ) -> Result<(), MokshaMintError>;
+
+    /// Returns the activated keysets in the order they were activated
+    async fn get_keysets(&self) -> Result<Vec<KeysetInfo>, MokshaMintError>;
+    async fn add_keyset(&self, keyset: &KeysetInfo) -> Result<(), MokshaMintError>;
+
+    /// Closes all connections. Called once the server has shut down.
+    async fn close(&self);
+
+    /// Name of the database backend, e.g. for logging the configuration
+    fn name(&self) -> &'static str;
 }
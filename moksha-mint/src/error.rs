@@ -19,14 +19,23 @@ pub enum MokshaMintError {
     #[error("LndConnectError - {0}")]
     ConnectError(ConnectError),
 
+    #[error("Invalid lnd credential - {0}")]
+    InvalidLndCredential(String),
+
     #[error("Failed to decode payment request {0} - Error {1}")]
     DecodeInvoice(String, ParseOrSemanticError),
 
     #[error("Failed to pay invoice {0} - Error {1}")]
     PayInvoice(String, LightningError),
 
-    #[error("DB Error {0}")]
-    Db(#[from] sqlx::Error),
+    #[error("Database is unreachable - {0}")]
+    DbConnection(String),
+
+    #[error("DB query failed - {0}")]
+    DbQuery(String),
+
+    #[error("Failed to migrate the database - {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
 
     #[error("Utf8 Error {0}")]
     Utf8(#[from] FromUtf8Error),
@@ -34,6 +43,9 @@ pub enum MokshaMintError {
     #[error("Serde Error {0}")]
     Serialization(#[from] serde_json::Error),
 
+    #[error("MokshaCoreError - {0}")]
+    Core(#[from] moksha_core::error::MokshaCoreError),
+
     #[error("Invoice amount is too low {0}")]
     InvoiceAmountTooLow(String),
 
@@ -43,26 +55,120 @@ pub enum MokshaMintError {
     #[error("Lightning invoice not paid yet.")]
     InvoiceNotPaidYet,
 
-    #[error("Proof already used {0}")]
-    ProofAlreadyUsed(String),
+    /// indices of the spent proofs in the submitted proofs
+    #[error("Proofs already used at indices {0:?}")]
+    ProofsAlreadyUsed(Vec<usize>),
+
+    #[error("Proof {0} is blacklisted")]
+    ProofBlacklisted(String),
 
     #[error("{0}")]
     SwapAmountMismatch(String),
 
+    #[error("Amount overflow")]
+    AmountOverflow,
+
     #[error("duplicate promises.")]
     SwapHasDuplicatePromises,
 
+    #[error("Too many outputs {0}. The mint signs at most {1} outputs per request")]
+    TooManyOutputs(usize, usize),
+
+    #[error("Too many invoices {0}. The mint creates at most {1} invoices per request")]
+    TooManyInvoices(usize, usize),
+
+    #[error("Too many proofs {0} for the amount. The mint accepts at most {1} proofs for it")]
+    TooManyDustProofs(usize, usize),
+
     #[error("Invalid amount")]
     InvalidAmount,
 
+    #[error("Minting is disabled")]
+    MintingDisabled,
+
+    #[error("The legacy api is disabled")]
+    LegacyApiDisabled,
+
+    #[error("Invalid request field {0}: {1}")]
+    InvalidRequest(String, String),
+
+    #[error("Invalid max order {0}. Must be between 1 and 64")]
+    InvalidMaxOrder(u8),
+
+    #[error("Invalid mnemonic - {0}")]
+    InvalidMnemonic(String),
+
+    #[error("Invalid derivation path {0}. Expected a BIP32 path like m/129372'/0'")]
+    InvalidDerivationPath(String),
+
+    #[error("Invalid fee tier {0}. Expected <min_amount>:<fee_percent>")]
+    InvalidFeeTier(String),
+
     #[error("Lightning Error {0}")]
     Lightning(#[from] LightningError),
 
+    #[error("Lightning backend did not respond in time")]
+    LightningTimeout,
+
     #[error("Invalid quote {0}")]
     InvalidQuote(String),
 
-    #[error("Keyset not found {0}")]
-    KeysetNotFound(String),
+    #[error("Melt quote {0} doesn't match the request - {1}")]
+    MeltQuoteMismatch(String, String),
+
+    #[error("Unknown keyset {0}")]
+    UnknownKeyset(String),
+
+    #[error("Keyset {0} is inactive")]
+    InactiveKeyset(String),
+
+    #[error("Invalid proof {0}")]
+    InvalidProof(String),
+
+    #[error("Proof {0} uses {1}, which is not supported by the mint")]
+    UnsupportedProofFeature(String, String),
+
+    #[error("Proof {0} claims amount {1}, but is signed with the key for amount {2}")]
+    ProofAmountKeyMismatch(String, u64, u64),
+
+    #[error("A valid blind auth token is required")]
+    AuthRequired,
+
+    #[error("Blind authentication is not enabled")]
+    AuthNotEnabled,
+
+    #[error("Webhooks are not supported by the lightning backend")]
+    WebhookNotSupported,
+
+    #[error("Onchain payments are not supported by the lightning backend")]
+    OnchainNotSupported,
+
+    #[error("Failed to pay onchain to {0} - Error {1}")]
+    PayOnchain(String, String),
+
+    #[error("Fee reserve {0} exceeds the maximum fee {1}")]
+    FeeExceedsMaximum(u64, u64),
+
+    #[error("Fee estimation is not supported by the lightning backend")]
+    FeeEstimateNotSupported,
+
+    #[error("Failed to estimate the fee - Error {0}")]
+    EstimateFee(String),
+}
+
+/// Separates an unreachable database from failing queries, so operators can tell them apart
+impl From<sqlx::Error> for MokshaMintError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::Configuration(_)
+            | sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => Self::DbConnection(err.to_string()),
+            _ => Self::DbQuery(err.to_string()),
+        }
+    }
 }
 
 impl IntoResponse for MokshaMintError {
@@ -70,7 +176,9 @@ impl IntoResponse for MokshaMintError {
         event!(Level::ERROR, "error in mint: {:?}", self);
 
         let status = match self {
-            Self::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::DbConnection(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::DbQuery(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::AuthRequired => StatusCode::UNAUTHORIZED,
             _ => StatusCode::BAD_REQUEST,
         };
 
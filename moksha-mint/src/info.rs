@@ -1,12 +1,175 @@
 use serde_derive::{Deserialize, Serialize};
+use tracing::{event, Level};
 
-#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+const ENV_PREFIX: &str = "MINT_INFO_";
+const CONTACT_ENV_PREFIX: &str = "MINT_INFO_CONTACT_";
+/// contact list in the json format used before `MINT_INFO_CONTACT_<METHOD>`, e.g.
+/// `[["email","contact@me.com"]]`
+const DEPRECATED_CONTACT_ENV: &str = "MINT_INFO_CONTACT";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
 pub struct MintInfoSettings {
     pub name: Option<String>,
     #[serde(default)]
     pub version: bool,
     pub description: Option<String>,
     pub description_long: Option<String>,
-    pub contact: Option<Vec<Vec<String>>>,
+    /// contact methods and their values e.g. ("email", "contact@me.com")
+    #[serde(default)]
+    pub contact: Vec<(String, String)>,
     pub motd: Option<String>,
+    /// numbers of the NUTs the mint advertises as supported
+    #[serde(default)]
+    pub nuts: Vec<u8>,
+    pub tos_url: Option<String>,
+}
+
+impl MintInfoSettings {
+    /// Reads the settings from the `MINT_INFO_` environment variables.
+    /// Every `MINT_INFO_CONTACT_<METHOD>` variable is added as a contact entry.
+    pub fn from_env() -> Result<Self, envy::Error> {
+        Self::from_vars(std::env::vars())
+    }
+
+    pub fn from_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, envy::Error> {
+        let vars = vars.into_iter().collect::<Vec<_>>();
+
+        let mut contact = vars
+            .iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix(CONTACT_ENV_PREFIX)
+                    .map(|method| (method.to_lowercase(), value.to_owned()))
+            })
+            .collect::<Vec<_>>();
+        if let Some((_, value)) = vars.iter().find(|(key, _)| key == DEPRECATED_CONTACT_ENV) {
+            event!(
+                Level::WARN,
+                "{DEPRECATED_CONTACT_ENV} is deprecated, use {CONTACT_ENV_PREFIX}<METHOD> instead"
+            );
+            contact.extend(parse_deprecated_contact(value)?);
+        }
+        contact.sort();
+
+        let settings = envy::prefixed(ENV_PREFIX).from_iter::<_, Self>(vars.into_iter().filter(
+            |(key, _)| key != DEPRECATED_CONTACT_ENV && !key.starts_with(CONTACT_ENV_PREFIX),
+        ))?;
+
+        Ok(Self {
+            contact,
+            ..settings
+        })
+    }
+
+    /// Returns whether the NUT is advertised as supported. The mint's default is used if no NUTs
+    /// are configured.
+    pub fn advertises_nut(&self, nut: u8, default: bool) -> bool {
+        match self.nuts.is_empty() {
+            true => default,
+            false => self.nuts.contains(&nut),
+        }
+    }
+
+    /// contact entries in the format used by the info responses
+    pub fn contact_info(&self) -> Option<Vec<Vec<String>>> {
+        if self.contact.is_empty() {
+            return None;
+        }
+        Some(
+            self.contact
+                .iter()
+                .map(|(method, value)| vec![method.to_owned(), value.to_owned()])
+                .collect(),
+        )
+    }
+}
+
+fn parse_deprecated_contact(value: &str) -> Result<Vec<(String, String)>, envy::Error> {
+    let invalid = || envy::Error::Custom(format!("invalid {DEPRECATED_CONTACT_ENV}: {value}"));
+    serde_json::from_str::<Vec<Vec<String>>>(value)
+        .map_err(|_| invalid())?
+        .into_iter()
+        .map(|entry| match entry.as_slice() {
+            [method, value] => Ok((method.to_lowercase(), value.to_owned())),
+            _ => Err(invalid()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::info::MintInfoSettings;
+
+    #[test]
+    fn test_mint_info_settings_from_env_and_json() -> anyhow::Result<()> {
+        let vars = vec![
+            ("MINT_INFO_NAME", "moksha-mint"),
+            ("MINT_INFO_VERSION", "true"),
+            ("MINT_INFO_MOTD", "some message of the day"),
+            ("MINT_INFO_CONTACT_EMAIL", "contact@me.com"),
+            ("MINT_INFO_CONTACT_NOSTR", "npub..."),
+            ("MINT_INFO_NUTS", "4,5,6,7"),
+            ("MINT_INFO_TOS_URL", "https://mint.example.com/tos"),
+            ("LNBITS_URL", "http://localhost:5000"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()));
+
+        let settings = MintInfoSettings::from_vars(vars)?;
+        assert_eq!(
+            MintInfoSettings {
+                name: Some("moksha-mint".to_owned()),
+                version: true,
+                description: None,
+                description_long: None,
+                contact: vec![
+                    ("email".to_owned(), "contact@me.com".to_owned()),
+                    ("nostr".to_owned(), "npub...".to_owned()),
+                ],
+                motd: Some("some message of the day".to_owned()),
+                nuts: vec![4, 5, 6, 7],
+                tos_url: Some("https://mint.example.com/tos".to_owned()),
+            },
+            settings
+        );
+
+        let json = serde_json::to_string(&settings)?;
+        assert_eq!(settings, serde_json::from_str::<MintInfoSettings>(&json)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mint_info_settings_deprecated_contact() -> anyhow::Result<()> {
+        let vars = vec![
+            ("MINT_INFO_CONTACT", r#"[["email","contact@me.com"]]"#),
+            ("MINT_INFO_CONTACT_NOSTR", "npub..."),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()));
+
+        let settings = MintInfoSettings::from_vars(vars)?;
+        assert_eq!(
+            vec![
+                ("email".to_owned(), "contact@me.com".to_owned()),
+                ("nostr".to_owned(), "npub...".to_owned()),
+            ],
+            settings.contact
+        );
+
+        let result = MintInfoSettings::from_vars(vec![(
+            "MINT_INFO_CONTACT".to_owned(),
+            r#"[["email"]]"#.to_owned(),
+        )]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mint_info_settings_empty_contact() -> anyhow::Result<()> {
+        let settings = MintInfoSettings::from_vars(vec![])?;
+        assert_eq!(MintInfoSettings::default(), settings);
+        assert_eq!(None, settings.contact_info());
+        Ok(())
+    }
 }
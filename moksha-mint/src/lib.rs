@@ -1,7 +1,12 @@
+pub mod bloom;
+pub mod cache;
+pub mod config;
 pub mod database;
 pub mod error;
 pub mod info;
 pub mod lightning;
 pub mod mint;
+pub mod mnemonic;
 pub mod model;
 pub mod server;
+pub mod validation;
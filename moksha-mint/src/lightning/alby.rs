@@ -1,10 +1,16 @@
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
 use hyper::{header::CONTENT_TYPE, http::HeaderValue};
 use tracing::info;
 use url::Url;
 
 use crate::model::{CreateInvoiceParams, CreateInvoiceResult, PayInvoiceResult};
 
-use super::error::LightningError;
+use super::{constant_time_eq, error::LightningError, hmac_sha256};
+
+/// maximum age of a webhook in seconds to prevent replay attacks
+const WEBHOOK_TOLERANCE_SECS: i64 = 5 * 60;
 
 #[derive(Clone)]
 pub struct AlbyClient {
@@ -116,6 +122,10 @@ impl AlbyClient {
                 .as_str()
                 .expect("payment_hash is empty")
                 .to_owned(),
+            payment_preimage: response["payment_preimage"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
             total_fees: 0, // FIXME return fees for alby
         })
     }
@@ -129,3 +139,48 @@ impl AlbyClient {
             .unwrap_or(false))
     }
 }
+
+/// Verifies the signature of a webhook sent by alby.
+///
+/// Alby sends webhooks via svix, so the signature is a base64 encoded HMAC-SHA256 over `{id}.{timestamp}.{body}`.
+/// See <https://docs.svix.com/receiving/verifying-payloads/how-manual>
+pub fn verify_webhook_signature(
+    webhook_secret: &str,
+    headers: &HeaderMap,
+    body: &str,
+) -> Result<(), LightningError> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(LightningError::InvalidWebhookSignature)
+    };
+    let msg_id = header("svix-id")?;
+    let timestamp = header("svix-timestamp")?;
+    let signatures = header("svix-signature")?;
+
+    let sent_at = timestamp
+        .parse::<i64>()
+        .map_err(|_| LightningError::InvalidWebhookSignature)?;
+    if (Utc::now().timestamp() - sent_at).abs() > WEBHOOK_TOLERANCE_SECS {
+        return Err(LightningError::InvalidWebhookSignature);
+    }
+
+    let key = general_purpose::STANDARD
+        .decode(webhook_secret.trim_start_matches("whsec_"))
+        .map_err(|_| LightningError::InvalidWebhookSignature)?;
+    let expected = general_purpose::STANDARD.encode(hmac_sha256(
+        &key,
+        format!("{msg_id}.{timestamp}.{body}").as_bytes(),
+    ));
+
+    if signatures
+        .split(' ')
+        .filter_map(|signature| signature.strip_prefix("v1,"))
+        .any(|signature| constant_time_eq(signature.as_bytes(), expected.as_bytes()))
+    {
+        Ok(())
+    } else {
+        Err(LightningError::InvalidWebhookSignature)
+    }
+}
@@ -17,4 +17,13 @@ pub enum LightningError {
 
     #[error("Payment failed")]
     PaymentFailed,
+
+    #[error("lnd error: {0}")]
+    Lnd(String),
+
+    #[error("Invalid webhook signature")]
+    InvalidWebhookSignature,
+
+    #[error("Invalid webhook payload")]
+    InvalidWebhookPayload,
 }
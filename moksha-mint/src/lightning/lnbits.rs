@@ -1,4 +1,5 @@
 use hyper::{header::CONTENT_TYPE, http::HeaderValue};
+use tracing::{event, Level};
 use url::Url;
 
 use crate::model::{CreateInvoiceParams, CreateInvoiceResult, PayInvoiceResult};
@@ -127,12 +128,38 @@ impl LNBitsClient {
             .as_str()
             .expect("payment_hash is empty")
             .to_owned();
+
+        // lnbits doesn't return the preimage when paying, so it has to be fetched separately. The
+        // invoice has been paid at this point, so a failed lookup must not fail the payment.
+        let payment_preimage = self
+            .get_payment_preimage(&payment_hash)
+            .await
+            .unwrap_or_else(|err| {
+                event!(
+                    Level::WARN,
+                    "failed to get the preimage for payment {payment_hash}: {err}"
+                );
+                String::new()
+            });
         Ok(PayInvoiceResult {
             payment_hash,
+            payment_preimage,
             total_fees: 0,
         })
     }
 
+    pub async fn get_payment_preimage(&self, payment_hash: &str) -> Result<String, LightningError> {
+        let body = self
+            .make_get(&format!("api/v1/payments/{payment_hash}"))
+            .await?;
+        Ok(
+            serde_json::from_str::<serde_json::Value>(&body)?["preimage"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+        )
+    }
+
     pub async fn is_invoice_paid(&self, payment_hash: &str) -> Result<bool, LightningError> {
         let body = self
             .make_get(&format!("api/v1/payments/{payment_hash}"))
@@ -1,7 +1,16 @@
 use async_trait::async_trait;
+use axum::http::HeaderMap;
+use bitcoin_hashes::{hmac, sha256, Hash, HashEngine};
+use fedimint_tonic_lnd::tonic::{Code, Status};
 use fedimint_tonic_lnd::Client;
 use std::fmt::{self, Formatter};
-use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempPath};
+use tokio::sync::Mutex;
+use tracing::{event, Level};
 
 use url::Url;
 
@@ -9,7 +18,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
     error::MokshaMintError,
-    model::{CreateInvoiceParams, CreateInvoiceResult, PayInvoiceResult},
+    model::{CreateInvoiceParams, CreateInvoiceResult, MSat, PayInvoiceResult, Sat, WebhookEvent},
 };
 
 use lightning_invoice::{Bolt11Invoice as LNInvoice, SignedRawBolt11Invoice};
@@ -25,12 +34,28 @@ use std::{path::PathBuf, str::FromStr, sync::Arc};
 
 use self::{alby::AlbyClient, error::LightningError, lnbits::LNBitsClient, strike::StrikeClient};
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum LightningType {
     Lnbits(LnbitsLightningSettings),
     Alby(AlbyLightningSettings),
     Strike(StrikeLightningSettings),
     Lnd(LndLightningSettings),
+    /// Backend supplied by a library embedder, e.g. for a node that isn't supported by moksha
+    #[cfg(feature = "custom-lightning")]
+    Custom(Arc<dyn Lightning + Send + Sync>),
+}
+
+impl fmt::Debug for LightningType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LightningType::Lnbits(settings) => f.debug_tuple("Lnbits").field(settings).finish(),
+            LightningType::Alby(settings) => f.debug_tuple("Alby").field(settings).finish(),
+            LightningType::Strike(settings) => f.debug_tuple("Strike").field(settings).finish(),
+            LightningType::Lnd(settings) => f.debug_tuple("Lnd").field(settings).finish(),
+            #[cfg(feature = "custom-lightning")]
+            LightningType::Custom(_) => f.write_str("Custom"),
+        }
+    }
 }
 
 impl fmt::Display for LightningType {
@@ -40,6 +65,22 @@ impl fmt::Display for LightningType {
             LightningType::Alby(settings) => write!(f, "Alby: {}", settings),
             LightningType::Strike(settings) => write!(f, "Strike: {}", settings),
             LightningType::Lnd(settings) => write!(f, "Lnd: {}", settings),
+            #[cfg(feature = "custom-lightning")]
+            LightningType::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl LightningType {
+    /// Name of the backend without its settings, so it can be logged without leaking api keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightningType::Lnbits(_) => "Lnbits",
+            LightningType::Alby(_) => "Alby",
+            LightningType::Strike(_) => "Strike",
+            LightningType::Lnd(_) => "Lnd",
+            #[cfg(feature = "custom-lightning")]
+            LightningType::Custom(_) => "Custom",
         }
     }
 }
@@ -58,6 +99,126 @@ pub trait Lightning: Send + Sync {
         LNInvoice::from_str(&payment_request)
             .map_err(|err| MokshaMintError::DecodeInvoice(payment_request, err))
     }
+
+    /// Returns the id the lightning backend uses to identify the invoice. By default this is the payment hash
+    async fn invoice_id(&self, payment_request: String) -> Result<String, MokshaMintError> {
+        Ok(self
+            .decode_invoice(payment_request)
+            .await?
+            .payment_hash()
+            .to_string())
+    }
+
+    /// Verifies the signature of a webhook callback from the lightning backend and returns the contained event
+    async fn verify_webhook(
+        &self,
+        _headers: &HeaderMap,
+        _body: &str,
+    ) -> Result<WebhookEvent, MokshaMintError> {
+        Err(MokshaMintError::WebhookNotSupported)
+    }
+
+    /// Sends `amount` sats to a bitcoin address and returns the id of the transaction
+    async fn pay_onchain(&self, _address: String, _amount: u64) -> Result<String, MokshaMintError> {
+        Err(MokshaMintError::OnchainNotSupported)
+    }
+
+    /// Estimates the routing fee for paying the invoice. Backends that can't estimate fees return
+    /// `FeeEstimateNotSupported` and the mint falls back to the configured fee reserve.
+    async fn estimate_fee(&self, _invoice: &LNInvoice) -> Result<Sat, MokshaMintError> {
+        Err(MokshaMintError::FeeEstimateNotSupported)
+    }
+
+    /// Minimum fee reserve the backend needs for paying an invoice. The mint uses the larger of
+    /// this and the configured `fee_reserve_min`, so backends with high base fees can raise it.
+    fn min_fee_reserve(&self) -> MSat {
+        MSat(0)
+    }
+}
+
+/// default timeout in seconds for calls to the lightning backend
+pub const DEFAULT_LIGHTNING_RPC_TIMEOUT_SECS: u64 = 30;
+
+/// Wraps a lightning backend and aborts calls that don't finish within the configured timeout,
+/// so a hung lightning node can't block a mint request forever. Payments are not aborted: they
+/// might still succeed after the timeout, and the mint would release the proofs of a paid melt.
+pub struct TimeoutLightning {
+    inner: Arc<dyn Lightning + Send + Sync>,
+    timeout: Duration,
+}
+
+impl TimeoutLightning {
+    pub fn new(inner: Arc<dyn Lightning + Send + Sync>, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+
+    async fn with_timeout<T>(
+        &self,
+        call: impl Future<Output = Result<T, MokshaMintError>>,
+    ) -> Result<T, MokshaMintError> {
+        tokio::time::timeout(self.timeout, call)
+            .await
+            .map_err(|_| MokshaMintError::LightningTimeout)?
+    }
+}
+
+#[async_trait]
+impl Lightning for TimeoutLightning {
+    async fn is_invoice_paid(&self, invoice: String) -> Result<bool, MokshaMintError> {
+        self.with_timeout(self.inner.is_invoice_paid(invoice)).await
+    }
+
+    async fn create_invoice(&self, amount: u64) -> Result<CreateInvoiceResult, MokshaMintError> {
+        self.with_timeout(self.inner.create_invoice(amount)).await
+    }
+
+    async fn pay_invoice(
+        &self,
+        payment_request: String,
+    ) -> Result<PayInvoiceResult, MokshaMintError> {
+        self.inner.pay_invoice(payment_request).await
+    }
+
+    async fn decode_invoice(&self, payment_request: String) -> Result<LNInvoice, MokshaMintError> {
+        self.inner.decode_invoice(payment_request).await
+    }
+
+    async fn invoice_id(&self, payment_request: String) -> Result<String, MokshaMintError> {
+        self.with_timeout(self.inner.invoice_id(payment_request))
+            .await
+    }
+
+    async fn verify_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Result<WebhookEvent, MokshaMintError> {
+        self.with_timeout(self.inner.verify_webhook(headers, body))
+            .await
+    }
+
+    async fn pay_onchain(&self, address: String, amount: u64) -> Result<String, MokshaMintError> {
+        self.inner.pay_onchain(address, amount).await
+    }
+
+    async fn estimate_fee(&self, invoice: &LNInvoice) -> Result<Sat, MokshaMintError> {
+        self.with_timeout(self.inner.estimate_fee(invoice)).await
+    }
+
+    fn min_fee_reserve(&self) -> MSat {
+        self.inner.min_fee_reserve()
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+    engine.input(message);
+    hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+/// compares two signatures or tokens in constant time
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -138,6 +299,7 @@ impl Lightning for LnbitsLightning {
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct AlbyLightningSettings {
     pub api_key: Option<String>,
+    pub webhook_secret: Option<String>,
 }
 
 impl fmt::Display for AlbyLightningSettings {
@@ -150,6 +312,7 @@ impl AlbyLightningSettings {
     pub fn new(api_key: &str) -> Self {
         Self {
             api_key: Some(api_key.to_owned()),
+            webhook_secret: None,
         }
     }
 }
@@ -157,12 +320,14 @@ impl AlbyLightningSettings {
 #[derive(Clone)]
 pub struct AlbyLightning {
     pub client: AlbyClient,
+    webhook_secret: Option<String>,
 }
 
 impl AlbyLightning {
-    pub fn new(api_key: String) -> Self {
+    pub fn new(api_key: String, webhook_secret: Option<String>) -> Self {
         Self {
             client: AlbyClient::new(&api_key).expect("Can not create Alby client"),
+            webhook_secret,
         }
     }
 }
@@ -199,11 +364,33 @@ impl Lightning for AlbyLightning {
             .await
             .map_err(|err| MokshaMintError::PayInvoice(payment_request, err))
     }
+
+    async fn verify_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Result<WebhookEvent, MokshaMintError> {
+        let webhook_secret = self
+            .webhook_secret
+            .as_ref()
+            .ok_or(MokshaMintError::WebhookNotSupported)?;
+        alby::verify_webhook_signature(webhook_secret, headers, body)?;
+
+        let event = serde_json::from_str::<serde_json::Value>(body)?;
+        Ok(WebhookEvent {
+            invoice_id: event["payment_hash"]
+                .as_str()
+                .ok_or(LightningError::InvalidWebhookPayload)?
+                .to_owned(),
+            paid: event["settled"].as_bool().unwrap_or(false),
+        })
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct StrikeLightningSettings {
     pub api_key: Option<String>,
+    pub webhook_secret: Option<String>,
 }
 
 impl fmt::Display for StrikeLightningSettings {
@@ -216,6 +403,7 @@ impl StrikeLightningSettings {
     pub fn new(api_key: &str) -> Self {
         Self {
             api_key: Some(api_key.to_owned()),
+            webhook_secret: None,
         }
     }
 }
@@ -223,12 +411,14 @@ impl StrikeLightningSettings {
 #[derive(Clone)]
 pub struct StrikeLightning {
     pub client: StrikeClient,
+    webhook_secret: Option<String>,
 }
 
 impl StrikeLightning {
-    pub fn new(api_key: String) -> Self {
+    pub fn new(api_key: String, webhook_secret: Option<String>) -> Self {
         Self {
             client: StrikeClient::new(&api_key).expect("Can not create Strike client"),
+            webhook_secret,
         }
     }
 }
@@ -236,7 +426,12 @@ impl StrikeLightning {
 #[async_trait]
 impl Lightning for StrikeLightning {
     async fn is_invoice_paid(&self, invoice: String) -> Result<bool, MokshaMintError> {
-        let decoded_invoice = self.decode_invoice(invoice).await?;
+        let invoice_id = self.invoice_id(invoice).await?;
+        Ok(self.client.is_invoice_paid(&invoice_id).await?)
+    }
+
+    async fn invoice_id(&self, payment_request: String) -> Result<String, MokshaMintError> {
+        let decoded_invoice = self.decode_invoice(payment_request).await?;
         let description_hash = decoded_invoice
             .into_signed_raw()
             .description_hash()
@@ -244,9 +439,28 @@ impl Lightning for StrikeLightning {
             .0;
 
         // invoiceId is the last 16 bytes of the description hash
-        let invoice_id = format_as_uuid_string(&description_hash[16..]);
+        Ok(format_as_uuid_string(&description_hash[16..]))
+    }
 
-        Ok(self.client.is_invoice_paid(&invoice_id).await?)
+    async fn verify_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Result<WebhookEvent, MokshaMintError> {
+        let webhook_secret = self
+            .webhook_secret
+            .as_ref()
+            .ok_or(MokshaMintError::WebhookNotSupported)?;
+        strike::verify_webhook_signature(webhook_secret, headers, body)?;
+
+        // the webhook only tells that the invoice has changed, so the state has to be fetched
+        let event = serde_json::from_str::<serde_json::Value>(body)?;
+        let invoice_id = event["data"]["entityId"]
+            .as_str()
+            .ok_or(LightningError::InvalidWebhookPayload)?
+            .to_owned();
+        let paid = self.client.is_invoice_paid(&invoice_id).await?;
+        Ok(WebhookEvent { invoice_id, paid })
     }
 
     async fn create_invoice(&self, amount: u64) -> Result<CreateInvoiceResult, MokshaMintError> {
@@ -302,7 +516,8 @@ impl Lightning for StrikeLightning {
 
         Ok(PayInvoiceResult {
             payment_hash: hex::encode(payment_hash),
-            total_fees: 0, // FIXME return fees for strike
+            payment_preimage: "".to_string(), // FIXME strike doesn't expose the preimage of a payment
+            total_fees: 0,                    // FIXME return fees for strike
         })
     }
 }
@@ -346,48 +561,221 @@ pub struct LndLightningSettings {
     pub grpc_host: Option<Url>,
     pub tls_cert_path: Option<PathBuf>,
     pub macaroon_path: Option<PathBuf>,
+    /// pem encoded tls certificate, takes precedence over `tls_cert_path`
+    pub tls_cert_pem: Option<String>,
+    /// hex encoded macaroon, takes precedence over `macaroon_path`
+    pub macaroon_hex: Option<String>,
 }
+
+impl LndLightningSettings {
+    /// Returns the tls certificate from `LND_TLS_CERT_PEM` or `LND_TLS_CERT_PATH`
+    pub fn tls_cert(&self) -> Result<LndCredential, MokshaMintError> {
+        match (&self.tls_cert_pem, &self.tls_cert_path) {
+            (Some(pem), _) => Ok(LndCredential::Inline(pem.as_bytes().to_vec())),
+            (None, Some(path)) => Ok(LndCredential::File(path.to_owned())),
+            (None, None) => Err(MokshaMintError::InvalidLndCredential(
+                "LND_TLS_CERT_PEM or LND_TLS_CERT_PATH not set".to_owned(),
+            )),
+        }
+    }
+
+    /// Returns the macaroon from `LND_MACAROON_HEX` or `LND_MACAROON_PATH`
+    pub fn macaroon(&self) -> Result<LndCredential, MokshaMintError> {
+        match (&self.macaroon_hex, &self.macaroon_path) {
+            (Some(macaroon_hex), _) => hex::decode(macaroon_hex.trim())
+                .map(LndCredential::Inline)
+                .map_err(|err| {
+                    MokshaMintError::InvalidLndCredential(format!("LND_MACAROON_HEX: {err}"))
+                }),
+            (None, Some(path)) => Ok(LndCredential::File(path.to_owned())),
+            (None, None) => Err(MokshaMintError::InvalidLndCredential(
+                "LND_MACAROON_HEX or LND_MACAROON_PATH not set".to_owned(),
+            )),
+        }
+    }
+}
+
 impl fmt::Display for LndLightningSettings {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let source = |inline: &Option<String>, path: &Option<PathBuf>| match (inline, path) {
+            (Some(_), _) => "inline".to_owned(),
+            (None, Some(path)) => path.to_str().unwrap_or_default().to_owned(),
+            (None, None) => "not set".to_owned(),
+        };
         write!(
             f,
-            "grpc_host: {}, tls_cert_path: {}, macaroon_path: {}",
-            self.grpc_host.as_ref().unwrap(),
-            self.tls_cert_path
-                .as_ref()
-                .unwrap() // FIXME unwrap
-                .to_str()
-                .unwrap_or_default(),
-            self.macaroon_path
+            "grpc_host: {}, tls_cert: {}, macaroon: {}",
+            self.grpc_host
                 .as_ref()
-                .unwrap()
-                .to_str()
-                .unwrap_or_default()
+                .map(Url::to_string)
+                .unwrap_or_else(|| "not set".to_owned()),
+            source(&self.tls_cert_pem, &self.tls_cert_path),
+            source(&self.macaroon_hex, &self.macaroon_path),
         )
     }
 }
 
-pub struct LndLightning(Arc<Mutex<Client>>);
+/// A file lnd needs to connect, either as path or as its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LndCredential {
+    File(PathBuf),
+    Inline(Vec<u8>),
+}
+
+impl LndCredential {
+    /// Returns the path of the credential. Inline credentials are written to a temporary file,
+    /// because the lnd client only reads them from disk. The file is deleted when the returned
+    /// `TempPath` is dropped.
+    fn into_path(self) -> Result<(PathBuf, Option<TempPath>), MokshaMintError> {
+        match self {
+            Self::File(path) => Ok((path, None)),
+            Self::Inline(content) => {
+                let write_file = || -> std::io::Result<TempPath> {
+                    let mut file = NamedTempFile::new()?;
+                    file.write_all(&content)?;
+                    Ok(file.into_temp_path())
+                };
+                let temp_path = write_file()
+                    .map_err(|err| MokshaMintError::InvalidLndCredential(err.to_string()))?;
+                Ok((temp_path.to_path_buf(), Some(temp_path)))
+            }
+        }
+    }
+}
+
+/// maximum number of reconnects to lnd for a single call
+pub const LND_MAX_RECONNECT_ATTEMPTS: usize = 3;
 
-impl LndLightning {
-    pub async fn new(
+type LndCall<'a, T> = Pin<Box<dyn Future<Output = Result<T, Status>> + Send + 'a>>;
+
+/// Creates new connections to lnd
+#[async_trait]
+pub trait LndConnector<C>: Send + Sync {
+    async fn connect(&self) -> Result<C, MokshaMintError>;
+}
+
+/// Connects to lnd with the stored host, tls certificate and macaroon
+struct LndClientConnector {
+    address: Url,
+    cert_file: PathBuf,
+    macaroon_file: PathBuf,
+    /// keeps the temporary files of inline credentials alive
+    _temp_files: Vec<TempPath>,
+}
+
+impl LndClientConnector {
+    fn new(
         address: Url,
-        cert_file: &PathBuf,
-        macaroon_file: &PathBuf,
+        cert: LndCredential,
+        macaroon: LndCredential,
     ) -> Result<Self, MokshaMintError> {
-        let client =
-            fedimint_tonic_lnd::connect(address.to_string(), cert_file, &macaroon_file).await;
+        let (cert_file, cert_temp) = cert.into_path()?;
+        let (macaroon_file, macaroon_temp) = macaroon.into_path()?;
+        Ok(Self {
+            address,
+            cert_file,
+            macaroon_file,
+            _temp_files: cert_temp.into_iter().chain(macaroon_temp).collect(),
+        })
+    }
+}
 
-        Ok(Self(Arc::new(Mutex::new(
-            client.map_err(MokshaMintError::ConnectError)?,
-        ))))
+#[async_trait]
+impl LndConnector<Client> for LndClientConnector {
+    async fn connect(&self) -> Result<Client, MokshaMintError> {
+        fedimint_tonic_lnd::connect(
+            self.address.to_string(),
+            &self.cert_file,
+            &self.macaroon_file,
+        )
+        .await
+        .map_err(MokshaMintError::ConnectError)
     }
+}
 
-    pub async fn client_lock(
-        &self,
-    ) -> anyhow::Result<MappedMutexGuard<'_, fedimint_tonic_lnd::LightningClient>> {
-        let guard = self.0.lock().await;
-        Ok(MutexGuard::map(guard, |client| client.lightning()))
+/// Holds the connection to lnd and rebuilds it if a call fails with a transport error, e.g.
+/// because lnd has been restarted.
+pub struct ReconnectingClient<C> {
+    connector: Box<dyn LndConnector<C>>,
+    client: Mutex<Option<C>>,
+}
+
+impl<C: Send> ReconnectingClient<C> {
+    pub fn new(connector: Box<dyn LndConnector<C>>, client: Option<C>) -> Self {
+        Self {
+            connector,
+            client: Mutex::new(client),
+        }
+    }
+
+    /// Runs the call with the current client. If the call fails with a transport error the
+    /// client is reconnected and the call is retried up to `LND_MAX_RECONNECT_ATTEMPTS` times.
+    /// Only use this for calls that can safely be sent twice, like lookups.
+    pub async fn call<T, F>(&self, call: F) -> Result<T, Status>
+    where
+        F: for<'a> Fn(&'a mut C) -> LndCall<'a, T> + Send + Sync,
+    {
+        self.call_with_retries(call, LND_MAX_RECONNECT_ATTEMPTS)
+            .await
+    }
+
+    /// Runs a call that must not be sent twice, e.g. a payment. lnd might have executed the
+    /// call before the connection was lost, so a transport error is returned to the caller and
+    /// the client is only reconnected for the next call.
+    pub async fn call_once<T, F>(&self, call: F) -> Result<T, Status>
+    where
+        F: for<'a> Fn(&'a mut C) -> LndCall<'a, T> + Send + Sync,
+    {
+        self.call_with_retries(call, 0).await
+    }
+
+    async fn call_with_retries<T, F>(&self, call: F, max_retries: usize) -> Result<T, Status>
+    where
+        F: for<'a> Fn(&'a mut C) -> LndCall<'a, T> + Send + Sync,
+    {
+        let mut attempts = 0;
+        let mut guard = self.client.lock().await;
+        loop {
+            if guard.is_none() {
+                let client = self
+                    .connector
+                    .connect()
+                    .await
+                    .map_err(|err| Status::unavailable(err.to_string()))?;
+                *guard = Some(client);
+            }
+
+            let client = guard.as_mut().expect("client is connected");
+            match call(client).await {
+                Err(status) if status.code() == Code::Unavailable => {
+                    event!(Level::WARN, "lnd connection lost: {status}");
+                    *guard = None;
+                    if attempts == max_retries {
+                        return Err(status);
+                    }
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+pub struct LndLightning(ReconnectingClient<Client>);
+
+impl LndLightning {
+    pub async fn new(
+        address: Url,
+        cert: LndCredential,
+        macaroon: LndCredential,
+    ) -> Result<Self, MokshaMintError> {
+        let connector = LndClientConnector::new(address, cert, macaroon)?;
+        let client = connector.connect().await?;
+
+        Ok(Self(ReconnectingClient::new(
+            Box::new(connector),
+            Some(client),
+        )))
     }
 }
 
@@ -403,12 +791,14 @@ impl Lightning for LndLightning {
         };
 
         let invoice = self
-            .client_lock()
-            .await
-            .expect("failed to lock client")
-            .lookup_invoice(fedimint_tonic_lnd::tonic::Request::new(invoice_request))
+            .0
+            .call(|client| {
+                Box::pin(client.lightning().lookup_invoice(
+                    fedimint_tonic_lnd::tonic::Request::new(invoice_request.clone()),
+                ))
+            })
             .await
-            .expect("failed to lookup invoice")
+            .map_err(|status| LightningError::Lnd(status.message().to_owned()))?
             .into_inner();
 
         Ok(invoice.state == fedimint_tonic_lnd::lnrpc::invoice::InvoiceState::Settled as i32)
@@ -420,14 +810,16 @@ impl Lightning for LndLightning {
             ..Default::default()
         };
 
-        let invoice = self
-            .client_lock()
-            .await
-            .expect("failed to lock client")
-            .add_invoice(fedimint_tonic_lnd::tonic::Request::new(invoice_request))
-            .await
-            .expect("failed to create invoice")
-            .into_inner();
+        let invoice =
+            self.0
+                .call_once(|client| {
+                    Box::pin(client.lightning().add_invoice(
+                        fedimint_tonic_lnd::tonic::Request::new(invoice_request.clone()),
+                    ))
+                })
+                .await
+                .map_err(|status| LightningError::Lnd(status.message().to_owned()))?
+                .into_inner();
 
         Ok(CreateInvoiceResult {
             payment_hash: invoice.r_hash,
@@ -440,17 +832,24 @@ impl Lightning for LndLightning {
         payment_request: String,
     ) -> Result<PayInvoiceResult, MokshaMintError> {
         let pay_req = fedimint_tonic_lnd::lnrpc::SendRequest {
-            payment_request,
+            payment_request: payment_request.clone(),
             ..Default::default()
         };
-        let payment_response = self
-            .client_lock()
-            .await
-            .expect("failed to lock client") //FIXME map error
-            .send_payment_sync(fedimint_tonic_lnd::tonic::Request::new(pay_req))
-            .await
-            .expect("failed to pay invoice")
-            .into_inner();
+        let payment_response =
+            self.0
+                .call_once(|client| {
+                    Box::pin(client.lightning().send_payment_sync(
+                        fedimint_tonic_lnd::tonic::Request::new(pay_req.clone()),
+                    ))
+                })
+                .await
+                .map_err(|status| {
+                    MokshaMintError::PayInvoice(
+                        payment_request,
+                        LightningError::Lnd(status.message().to_owned()),
+                    )
+                })?
+                .into_inner();
 
         let total_fees = payment_response
             .payment_route
@@ -458,15 +857,235 @@ impl Lightning for LndLightning {
 
         Ok(PayInvoiceResult {
             payment_hash: hex::encode(payment_response.payment_hash),
+            payment_preimage: hex::encode(payment_response.payment_preimage),
             total_fees,
         })
     }
+
+    async fn pay_onchain(&self, address: String, amount: u64) -> Result<String, MokshaMintError> {
+        let send_request = fedimint_tonic_lnd::lnrpc::SendCoinsRequest {
+            addr: address.clone(),
+            amount: amount as i64,
+            ..Default::default()
+        };
+
+        let response =
+            self.0
+                .call_once(|client| {
+                    Box::pin(client.lightning().send_coins(
+                        fedimint_tonic_lnd::tonic::Request::new(send_request.clone()),
+                    ))
+                })
+                .await
+                .map_err(|status| {
+                    MokshaMintError::PayOnchain(address, status.message().to_owned())
+                })?
+                .into_inner();
+
+        Ok(response.txid)
+    }
+
+    async fn estimate_fee(&self, invoice: &LNInvoice) -> Result<Sat, MokshaMintError> {
+        let query = fedimint_tonic_lnd::lnrpc::QueryRoutesRequest {
+            pub_key: invoice.recover_payee_pub_key().to_string(),
+            amt_msat: invoice.amount_milli_satoshis().unwrap_or_default() as i64,
+            final_cltv_delta: invoice.min_final_cltv_expiry_delta() as i32,
+            ..Default::default()
+        };
+
+        let response = self
+            .0
+            .call(|client| {
+                Box::pin(
+                    client
+                        .lightning()
+                        .query_routes(fedimint_tonic_lnd::tonic::Request::new(query.clone())),
+                )
+            })
+            .await
+            .map_err(|status| MokshaMintError::EstimateFee(status.message().to_owned()))?
+            .into_inner();
+
+        response
+            .routes
+            .iter()
+            .map(|route| route.total_fees_msat as u64)
+            .min()
+            .map(|fee| MSat(fee).to_sat_ceil())
+            .ok_or_else(|| MokshaMintError::EstimateFee("no route found".to_owned()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+
+    use crate::error::MokshaMintError;
     use crate::lightning::Lightning;
     use crate::lightning::LnbitsLightning;
+    use crate::lightning::{LndClientConnector, LndConnector, ReconnectingClient};
+    use crate::lightning::{LndCredential, LndLightningSettings};
+    use crate::lightning::{MockLightning, TimeoutLightning};
+    use crate::model::{CreateInvoiceResult, PayInvoiceResult};
+    use fedimint_tonic_lnd::tonic::Status;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// client whose calls fail with a transport error until it has been reconnected
+    struct FakeLndClient {
+        connected: bool,
+    }
+
+    /// connector that counts the connections it creates
+    #[derive(Clone, Default)]
+    struct FakeLndConnector {
+        connections: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LndConnector<FakeLndClient> for FakeLndConnector {
+        async fn connect(&self) -> Result<FakeLndClient, MokshaMintError> {
+            self.connections.fetch_add(1, Ordering::SeqCst);
+            Ok(FakeLndClient { connected: true })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lnd_reconnects_after_transport_error() -> anyhow::Result<()> {
+        let connector = FakeLndConnector::default();
+        let client = ReconnectingClient::new(
+            Box::new(connector.clone()),
+            Some(FakeLndClient { connected: false }),
+        );
+
+        let result = client
+            .call(|client| {
+                Box::pin(async move {
+                    if client.connected {
+                        Ok(42)
+                    } else {
+                        Err(Status::unavailable("transport error"))
+                    }
+                })
+            })
+            .await?;
+
+        assert_eq!(42, result);
+        assert_eq!(1, connector.connections.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lnd_reconnect_is_bounded() -> anyhow::Result<()> {
+        let connector = FakeLndConnector::default();
+        let client = ReconnectingClient::new(Box::new(connector.clone()), None);
+
+        let result = client
+            .call(|_| Box::pin(async { Err::<(), _>(Status::unavailable("lnd is down")) }))
+            .await;
+
+        assert!(result.is_err());
+        // the initial connection plus one for every retry
+        assert_eq!(
+            1 + super::LND_MAX_RECONNECT_ATTEMPTS,
+            connector.connections.load(Ordering::SeqCst)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lnd_call_once_is_not_retried() -> anyhow::Result<()> {
+        let connector = FakeLndConnector::default();
+        let client = ReconnectingClient::new(
+            Box::new(connector.clone()),
+            Some(FakeLndClient { connected: false }),
+        );
+        let calls = AtomicUsize::new(0);
+
+        let result = client
+            .call_once(|client| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async move {
+                    if client.connected {
+                        Ok(42)
+                    } else {
+                        Err(Status::unavailable("transport error"))
+                    }
+                })
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        assert_eq!(0, connector.connections.load(Ordering::SeqCst));
+
+        // the next call uses a new connection
+        let result = client.call_once(|_| Box::pin(async { Ok(42) })).await?;
+        assert_eq!(42, result);
+        assert_eq!(1, connector.connections.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    /// lightning backend that takes one second to answer every call
+    struct SlowLightning;
+
+    #[async_trait]
+    impl Lightning for SlowLightning {
+        async fn is_invoice_paid(&self, _invoice: String) -> Result<bool, MokshaMintError> {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(true)
+        }
+
+        async fn create_invoice(
+            &self,
+            _amount: u64,
+        ) -> Result<CreateInvoiceResult, MokshaMintError> {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(CreateInvoiceResult {
+                payment_hash: vec![],
+                payment_request: "".to_string(),
+            })
+        }
+
+        async fn pay_invoice(
+            &self,
+            _payment_request: String,
+        ) -> Result<PayInvoiceResult, MokshaMintError> {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(PayInvoiceResult {
+                payment_hash: "".to_string(),
+                payment_preimage: "".to_string(),
+                total_fees: 0,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_lightning_slow_backend() -> anyhow::Result<()> {
+        let lightning = TimeoutLightning::new(Arc::new(SlowLightning), Duration::from_millis(10));
+
+        let result = lightning.is_invoice_paid("invoice".to_string()).await;
+        assert!(matches!(result, Err(MokshaMintError::LightningTimeout)));
+
+        let result = lightning.create_invoice(100).await;
+        assert!(matches!(result, Err(MokshaMintError::LightningTimeout)));
+
+        // payments are awaited until the backend answers
+        let result = lightning.pay_invoice("invoice".to_string()).await;
+        assert!(result.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout_lightning_fast_backend() -> anyhow::Result<()> {
+        let mut mock = MockLightning::new();
+        mock.expect_is_invoice_paid().returning(|_| Ok(true));
+        let lightning = TimeoutLightning::new(Arc::new(mock), Duration::from_secs(5));
+
+        assert!(lightning.is_invoice_paid("invoice".to_string()).await?);
+        Ok(())
+    }
 
     #[tokio::test]
     async fn test_decode_invoice() -> anyhow::Result<()> {
@@ -496,4 +1115,75 @@ mod tests {
         assert!(decoded_invoice.is_err());
         Ok(())
     }
+
+    #[test]
+    fn test_lnd_connector_from_inline_credentials() -> anyhow::Result<()> {
+        let settings = LndLightningSettings {
+            grpc_host: Some("https://localhost:10009".parse()?),
+            tls_cert_pem: Some("-----BEGIN CERTIFICATE-----".to_owned()),
+            macaroon_hex: Some("0201036c6e64".to_owned()),
+            ..Default::default()
+        };
+
+        let connector = LndClientConnector::new(
+            settings.grpc_host.clone().expect("grpc host not set"),
+            settings.tls_cert()?,
+            settings.macaroon()?,
+        )?;
+        assert_eq!(
+            "-----BEGIN CERTIFICATE-----",
+            std::fs::read_to_string(&connector.cert_file)?
+        );
+        assert_eq!(
+            vec![0x02, 0x01, 0x03, 0x6c, 0x6e, 0x64],
+            std::fs::read(&connector.macaroon_file)?
+        );
+
+        let cert_file = connector.cert_file.clone();
+        drop(connector);
+        assert!(!cert_file.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lnd_connector_from_credential_files() -> anyhow::Result<()> {
+        let settings = LndLightningSettings {
+            grpc_host: Some("https://localhost:10009".parse()?),
+            tls_cert_path: Some("/lnd/tls.cert".into()),
+            macaroon_path: Some("/lnd/admin.macaroon".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            LndCredential::File("/lnd/tls.cert".into()),
+            settings.tls_cert()?
+        );
+
+        let connector = LndClientConnector::new(
+            settings.grpc_host.clone().expect("grpc host not set"),
+            settings.tls_cert()?,
+            settings.macaroon()?,
+        )?;
+        assert_eq!(
+            std::path::PathBuf::from("/lnd/tls.cert"),
+            connector.cert_file
+        );
+        assert_eq!(
+            std::path::PathBuf::from("/lnd/admin.macaroon"),
+            connector.macaroon_file
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lnd_invalid_macaroon_hex() -> anyhow::Result<()> {
+        let settings = LndLightningSettings {
+            macaroon_hex: Some("not hex".to_owned()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            settings.macaroon(),
+            Err(MokshaMintError::InvalidLndCredential(_))
+        ));
+        Ok(())
+    }
 }
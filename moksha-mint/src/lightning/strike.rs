@@ -1,3 +1,4 @@
+use axum::http::HeaderMap;
 use hyper::{header::CONTENT_TYPE, http::HeaderValue};
 use serde::{Deserialize, Serialize};
 
@@ -5,7 +6,7 @@ use url::Url;
 
 use crate::model::CreateInvoiceParams;
 
-use super::error::LightningError;
+use super::{constant_time_eq, error::LightningError, hmac_sha256};
 
 #[derive(Clone)]
 pub struct StrikeClient {
@@ -189,3 +190,23 @@ impl StrikeClient {
         Ok(response["state"].as_str().unwrap_or("") == "PAID")
     }
 }
+
+/// Verifies the signature of a webhook sent by strike. The signature is a hex encoded HMAC-SHA256 of the body.
+pub fn verify_webhook_signature(
+    webhook_secret: &str,
+    headers: &HeaderMap,
+    body: &str,
+) -> Result<(), LightningError> {
+    let signature = headers
+        .get("X-Webhook-Signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(LightningError::InvalidWebhookSignature)?
+        .to_lowercase();
+    let expected = hex::encode(hmac_sha256(webhook_secret.as_bytes(), body.as_bytes()));
+
+    if constant_time_eq(signature.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(LightningError::InvalidWebhookSignature)
+    }
+}
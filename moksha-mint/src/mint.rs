@@ -1,18 +1,42 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use lightning_invoice::Bolt11Invoice as LNInvoice;
 
 use moksha_core::{
+    amount::{Amount, Denominations},
     blind::{BlindedMessage, BlindedSignature, TotalAmount},
     dhke::Dhke,
-    keyset::MintKeyset,
-    proof::Proofs,
+    keyset::{MintKeyset, MAX_ORDER},
+    primitives::{Bolt11MeltQuote, Bolt11MintQuote, CurrencyUnit},
+    proof::{AuthProof, Proof, Proofs},
 };
 
+use rayon::prelude::*;
+use tracing::{event, Level};
+use uuid::Uuid;
+
 use crate::{
-    database::Database,
+    bloom::{BloomFilter, MIN_FILTER_ITEMS},
+    cache::{ResponseCache, DEFAULT_RESPONSE_CACHE_TTL_SECS},
+    database::{
+        memory::{InMemoryDatabase, MEMORY_DB_URL},
+        postgres::DEFAULT_DB_MAX_CONNECTIONS,
+        Database,
+    },
     error::MokshaMintError,
     info::MintInfoSettings,
-    lightning::{AlbyLightning, Lightning, LightningType, LnbitsLightning, StrikeLightning},
-    model::Invoice,
+    lightning::{
+        AlbyLightning, Lightning, LightningType, LnbitsLightning, StrikeLightning,
+        TimeoutLightning, DEFAULT_LIGHTNING_RPC_TIMEOUT_SECS,
+    },
+    mnemonic::secret_from_mnemonic,
+    model::{Invoice, KeysetInfo, MSat, MeltQuoteInfo, Sat},
 };
 
 #[derive(Clone)]
@@ -20,18 +44,73 @@ pub struct Mint {
     pub lightning: Arc<dyn Lightning + Send + Sync>,
     pub lightning_type: LightningType,
     // FIXME remove after v1 api release
-    pub keyset_legacy: MintKeyset,
+    pub keyset_legacy: Option<MintKeyset>,
     pub keyset: MintKeyset,
+    /// keysets that were rotated out. Proofs from these keysets can still be reissued.
+    pub inactive_keysets: Vec<MintKeyset>,
+    /// the active keyset with the id moksha derived before it followed NUT-02. It is only used to
+    /// verify proofs wallets still hold and isn't advertised.
+    pub pre_nut02_keysets: Vec<MintKeyset>,
     pub db: Arc<dyn Database + Send + Sync>,
     pub dhke: Dhke,
     pub lightning_fee_config: LightningFeeConfig,
     pub mint_info: MintInfoSettings,
+    /// bearer token that grants access to the admin routes. Admin routes are disabled if unset.
+    pub admin_token: Option<String>,
+    /// keyset that signs the blind auth proofs (NUT-22). Auth is not required if unset.
+    pub auth_keyset: Option<MintKeyset>,
+    /// maximum number of blinded messages the mint signs in a single request
+    pub max_outputs: usize,
+    /// maximum number of proofs per proof the amount needs at least. Swaps and melts with more
+    /// proofs are rejected as dust. There is no limit if unset.
+    pub max_dust_ratio: Option<usize>,
+    /// maximum size of a request body in bytes. Larger requests are rejected before parsing.
+    pub max_body_bytes: usize,
+    /// time after which mint and melt quotes expire, unless their invoice expires earlier
+    pub quote_expiry: Duration,
+    /// rejects new invoices and minting, while swaps and melts keep working, so a mint that is
+    /// wound down lets users redeem their ecash
+    pub minting_disabled: bool,
+    /// secrets of the spent proofs, so unspent proofs can be checked without a db query. Every
+    /// check queries the db if unset.
+    pub spent_proofs_filter: Option<Arc<RwLock<BloomFilter>>>,
+    /// responses of mint, melt and swap requests, so retried requests aren't processed twice
+    pub response_cache: Arc<ResponseCache>,
+    /// seed the keysets are derived from
+    pub(crate) seed: String,
+    /// time at which the mint was started, used to report its uptime
+    pub started_at: Instant,
 }
 
+/// derivation path of the keyset for blind auth proofs
+pub const AUTH_DERIVATION_PATH: &str = "auth";
+
+/// default maximum number of blinded messages per request
+pub const DEFAULT_MAX_OUTPUTS: usize = 1000;
+
+/// default maximum size of a request body in bytes (1 MiB)
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// maximum number of invoices that are created in a single batch
+pub const MAX_BATCH_INVOICES: usize = 20;
+
+/// default time in seconds after which quotes expire
+pub const DEFAULT_QUOTE_EXPIRY_SECS: u64 = 600;
+
+/// number of times an unpaid invoice is checked again before minting fails. Lightning backends
+/// can report an invoice as unpaid for a moment after it has been paid.
+pub const INVOICE_PAID_RETRIES: usize = 3;
+
+const INVOICE_PAID_RETRY_DELAY: Duration = Duration::from_millis(250);
+
 #[derive(Clone, Debug)]
 pub struct LightningFeeConfig {
     pub fee_percent: f32,
+    /// minimum fee reserve in msat
     pub fee_reserve_min: u64,
+    /// fee percentages for larger payments. The tier with the highest threshold the amount
+    /// reaches replaces `fee_percent`.
+    pub fee_tiers: Vec<LightningFeeTier>,
     // TODO check if fee_percent is in range
 }
 
@@ -40,8 +119,23 @@ impl LightningFeeConfig {
         Self {
             fee_percent,
             fee_reserve_min,
+            fee_tiers: vec![],
         }
     }
+
+    pub fn with_fee_tiers(mut self, fee_tiers: Vec<LightningFeeTier>) -> Self {
+        self.fee_tiers = fee_tiers;
+        self
+    }
+
+    /// Returns the fee percentage for paying the given amount
+    pub fn fee_percent_for(&self, amount: MSat) -> f32 {
+        self.fee_tiers
+            .iter()
+            .filter(|tier| amount.0 >= tier.min_amount.saturating_mul(1000))
+            .max_by_key(|tier| tier.min_amount)
+            .map_or(self.fee_percent, |tier| tier.fee_percent)
+    }
 }
 
 impl Default for LightningFeeConfig {
@@ -49,10 +143,52 @@ impl Default for LightningFeeConfig {
         Self {
             fee_percent: 1.0,
             fee_reserve_min: 4000,
+            fee_tiers: vec![],
         }
     }
 }
 
+/// Fee percentage that applies to payments of at least `min_amount` sat
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightningFeeTier {
+    /// threshold in sat
+    pub min_amount: u64,
+    pub fee_percent: f32,
+}
+
+impl LightningFeeTier {
+    pub fn new(min_amount: u64, fee_percent: f32) -> Self {
+        Self {
+            min_amount,
+            fee_percent,
+        }
+    }
+
+    /// Parses a comma separated list of tiers in the form `<min_amount>:<fee_percent>`, e.g.
+    /// `100000:0.5,1000000:0.25`
+    pub fn parse_list(tiers: &str) -> Result<Vec<Self>, MokshaMintError> {
+        tiers
+            .split(',')
+            .map(str::trim)
+            .filter(|tier| !tier.is_empty())
+            .map(Self::from_str)
+            .collect()
+    }
+}
+
+impl FromStr for LightningFeeTier {
+    type Err = MokshaMintError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MokshaMintError::InvalidFeeTier(s.to_owned());
+        let (min_amount, fee_percent) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            min_amount: min_amount.trim().parse().map_err(|_| invalid())?,
+            fee_percent: fee_percent.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
 impl Mint {
     pub fn new(
         secret: String,
@@ -63,15 +199,30 @@ impl Mint {
         lightning_fee_config: LightningFeeConfig,
         mint_info: MintInfoSettings,
     ) -> Self {
+        let keyset = MintKeyset::new(&secret, &derivation_path);
         Self {
             lightning,
             lightning_type,
             lightning_fee_config,
-            keyset_legacy: MintKeyset::legacy_new(&secret, &derivation_path),
-            keyset: MintKeyset::new(&secret, &derivation_path),
+            keyset_legacy: Some(MintKeyset::legacy_new(&secret, &derivation_path)),
+            inactive_keysets: vec![],
+            // proofs issued before the keyset ids followed NUT-02 can still be redeemed
+            pre_nut02_keysets: vec![keyset.with_pre_nut02_id()],
+            keyset,
             db,
             dhke: Dhke::new(),
             mint_info,
+            admin_token: None,
+            auth_keyset: None,
+            max_outputs: DEFAULT_MAX_OUTPUTS,
+            max_dust_ratio: None,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            quote_expiry: Duration::from_secs(DEFAULT_QUOTE_EXPIRY_SECS),
+            minting_disabled: false,
+            spent_proofs_filter: None,
+            response_cache: Arc::new(ResponseCache::default()),
+            seed: secret,
+            started_at: Instant::now(),
         }
     }
 
@@ -79,30 +230,418 @@ impl Mint {
         MintBuilder::new()
     }
 
-    pub fn fee_reserve(&self, amount_msat: u64) -> u64 {
-        let fee_percent = self.lightning_fee_config.fee_percent as f64 / 100.0;
-        let fee_reserve = (amount_msat as f64 * fee_percent) as u64;
-        std::cmp::max(fee_reserve, self.lightning_fee_config.fee_reserve_min)
+    /// Returns a one line summary of the configuration, so operators can check it at startup
+    pub fn startup_summary(&self) -> String {
+        format!(
+            "active keyset: {}, unit: {}, denominations: {} ({}), lightning backend: {}, database: {}",
+            self.keyset.keyset_id,
+            self.keyset.unit(),
+            self.keyset.public_keys.len(),
+            self.keyset.denominations(),
+            self.lightning_type.name(),
+            self.db.name(),
+        )
+    }
+
+    /// Returns the unix timestamp at which a new quote for the invoice expires. The configured
+    /// quote expiry is capped by the expiry of the invoice.
+    pub fn quote_expiry(&self, invoice: &LNInvoice) -> u64 {
+        let expiry = Utc::now().timestamp() as u64 + self.quote_expiry.as_secs();
+        let invoice_expiry = (invoice.duration_since_epoch() + invoice.expiry_time()).as_secs();
+        expiry.min(invoice_expiry)
+    }
+
+    /// Returns the fee reserve for paying an invoice of the given amount. The reserve is at least
+    /// the configured minimum or the minimum of the lightning backend, whichever is larger.
+    /// The reserve is rounded up to whole sats so it always covers the routing fee.
+    pub fn fee_reserve(&self, amount: MSat) -> Sat {
+        let fee_percent = self.lightning_fee_config.fee_percent_for(amount) as f64 / 100.0;
+        let fee_reserve = (amount.0 as f64 * fee_percent) as u64;
+        let fee_reserve_min = std::cmp::max(
+            self.lightning_fee_config.fee_reserve_min,
+            self.lightning.min_fee_reserve().0,
+        );
+        MSat(std::cmp::max(fee_reserve, fee_reserve_min)).to_sat_ceil()
+    }
+
+    /// Returns the fee reserve for paying the invoice. The estimate of the lightning backend is
+    /// preferred, if the backend can't estimate the fee the configured reserve is used.
+    pub async fn estimate_fee_reserve(&self, invoice: &LNInvoice) -> Result<Sat, MokshaMintError> {
+        match self.lightning.estimate_fee(invoice).await {
+            Ok(fee) => Ok(fee),
+            Err(err) => {
+                if !matches!(err, MokshaMintError::FeeEstimateNotSupported) {
+                    event!(Level::WARN, "fee estimation failed: {err}");
+                }
+                let amount = invoice
+                    .amount_milli_satoshis()
+                    .ok_or(MokshaMintError::InvalidAmount)?;
+                Ok(self.fee_reserve(MSat(amount)))
+            }
+        }
+    }
+
+    /// Decodes the invoice and returns its amount together with the fee reserve and expiry a
+    /// melt quote for it would have, so the wallet doesn't need to decode the invoice itself
+    pub async fn quote_melt(
+        &self,
+        payment_request: &str,
+    ) -> Result<MeltQuoteInfo, MokshaMintError> {
+        let invoice = self
+            .lightning
+            .decode_invoice(payment_request.to_owned())
+            .await?;
+        let amount = MSat(
+            invoice
+                .amount_milli_satoshis()
+                .ok_or(MokshaMintError::InvalidAmount)?,
+        );
+        let fee_reserve = self.estimate_fee_reserve(&invoice).await?;
+        Ok(MeltQuoteInfo {
+            amount_sat: amount.to_sat_ceil().0,
+            fee_reserve: fee_reserve.0,
+            expiry: self.quote_expiry(&invoice),
+        })
+    }
+
+    /// Returns the keyset of the legacy api or an error if the legacy api is disabled
+    pub fn legacy_keyset(&self) -> Result<&MintKeyset, MokshaMintError> {
+        self.keyset_legacy
+            .as_ref()
+            .ok_or(MokshaMintError::LegacyApiDisabled)
+    }
+
+    /// Assigns the legacy keyset to outputs that don't reference a keyset. Legacy wallets don't
+    /// send keyset ids, but expect their outputs to be signed with the legacy keyset.
+    pub fn legacy_outputs(
+        &self,
+        outputs: &[BlindedMessage],
+    ) -> Result<Vec<BlindedMessage>, MokshaMintError> {
+        let legacy_keyset_id = &self.legacy_keyset()?.keyset_id;
+        Ok(outputs
+            .iter()
+            .map(|output| BlindedMessage {
+                id: output.id.clone().or_else(|| Some(legacy_keyset_id.clone())),
+                ..output.clone()
+            })
+            .collect())
+    }
+
+    /// Maps the id of the legacy keyset to the id of the v1 keyset with the same keys, so wallets
+    /// can map proofs of the legacy keyset to their v1 keyset id. Both keysets are derived from
+    /// the mint seed and only differ in how the id is computed. The map is empty if the mint has
+    /// no legacy keyset or no v1 keyset with the same keys.
+    pub fn keyset_migration_map(&self) -> HashMap<String, String> {
+        self.keyset_legacy
+            .iter()
+            .filter_map(|legacy| {
+                std::iter::once(&self.keyset)
+                    .chain(self.inactive_keysets.iter())
+                    .find(|keyset| keyset.public_keys == legacy.public_keys)
+                    .map(|keyset| (legacy.keyset_id.clone(), keyset.keyset_id.clone()))
+            })
+            .collect()
+    }
+
+    /// Replaces the active keyset. The previous keyset stays known to the mint as inactive.
+    pub fn rotate_keyset(&mut self, keyset: MintKeyset) {
+        let old_keyset = std::mem::replace(&mut self.keyset, keyset);
+        self.inactive_keysets.push(old_keyset);
+    }
+
+    /// Derives a new keyset from the mint seed, persists it and makes it the active keyset
+    pub async fn activate_keyset(&mut self, derivation_path: &str) -> Result<(), MokshaMintError> {
+        let keyset = self.derive_keyset(derivation_path);
+        self.db
+            .add_keyset(&KeysetInfo {
+                keyset_id: keyset.keyset_id.clone(),
+                derivation_path: derivation_path.to_owned(),
+                activated_at: Utc::now().timestamp() as u64,
+            })
+            .await?;
+        self.rotate_keyset(keyset);
+        Ok(())
+    }
+
+    /// Builds the filter of spent proofs from the db. The filter is sized for twice the number of
+    /// spent proofs, so its false positive rate stays low while more proofs are spent.
+    pub async fn load_spent_proofs_filter(&mut self) -> Result<(), MokshaMintError> {
+        let used_proofs = self.db.get_used_proofs().await?;
+        let mut filter = BloomFilter::new(MIN_FILTER_ITEMS.max(used_proofs.len() * 2));
+        for proof in used_proofs.proofs() {
+            filter.insert(&proof.secret);
+        }
+        self.spent_proofs_filter = Some(Arc::new(RwLock::new(filter)));
+        Ok(())
+    }
+
+    /// Loads the keysets that have been activated before a restart. The most recently activated
+    /// keyset becomes the active keyset, all others are kept as inactive keysets.
+    pub async fn load_keysets(&mut self) -> Result<(), MokshaMintError> {
+        for info in self.db.get_keysets().await? {
+            if info.keyset_id == self.keyset.keyset_id {
+                continue;
+            }
+
+            let keyset = match self
+                .inactive_keysets
+                .iter()
+                .position(|keyset| keyset.keyset_id == info.keyset_id)
+            {
+                Some(index) => self.inactive_keysets.remove(index),
+                None => {
+                    let keyset = self.derive_keyset(&info.derivation_path);
+                    if keyset.keyset_id != info.keyset_id {
+                        event!(
+                            Level::WARN,
+                            "keyset {} can't be derived from the mint seed",
+                            info.keyset_id
+                        );
+                        continue;
+                    }
+                    keyset
+                }
+            };
+            self.rotate_keyset(keyset);
+        }
+        Ok(())
+    }
+
+    /// Derives a keyset with the same max order and denominations as the active keyset
+    fn derive_keyset(&self, derivation_path: &str) -> MintKeyset {
+        let max_order = self.keyset.public_keys.len() as u8;
+        MintKeyset::new_with_denominations(
+            &self.seed,
+            derivation_path,
+            max_order,
+            self.keyset.denominations(),
+        )
+        .with_unit(self.keyset.unit())
+    }
+
+    fn find_keyset(&self, keyset_id: &str) -> Option<&MintKeyset> {
+        std::iter::once(&self.keyset)
+            .chain(self.keyset_legacy.iter())
+            .chain(self.inactive_keysets.iter())
+            .chain(self.pre_nut02_keysets.iter())
+            .find(|keyset| keyset.keyset_id == keyset_id)
+    }
+
+    /// Checks that every proof was signed by the keyset it references. Hashing the secrets to
+    /// the curve dominates the cost for large batches, so the `Y` points of all proofs are
+    /// computed in parallel before the signatures are checked.
+    pub fn verify_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
+        let proofs = proofs.proofs();
+        proofs.par_iter().for_each(|proof| {
+            proof.y();
+        });
+
+        for proof in proofs.iter() {
+            let keyset = self
+                .find_keyset(&proof.keyset_id)
+                .ok_or_else(|| MokshaMintError::UnknownKeyset(proof.keyset_id.clone()))?;
+            self.verify_proof(keyset, proof)?;
+        }
+        Ok(())
+    }
+
+    fn verify_proof(&self, keyset: &MintKeyset, proof: &Proof) -> Result<(), MokshaMintError> {
+        let private_key = keyset
+            .private_keys
+            .get(&proof.amount)
+            .ok_or(MokshaMintError::InvalidAmount)?;
+        let is_valid = self
+            .dhke
+            .verify_with_y(*private_key, proof.c, proof.y())
+            .map_err(|_| MokshaMintError::InvalidProof(proof.secret.clone()))?;
+        if !is_valid {
+            return Err(self.invalid_proof_error(keyset, proof));
+        }
+        Ok(())
+    }
+
+    /// Checks whether an invalid proof is signed with the key of a different amount of the
+    /// keyset. This is only done after the verification failed, so valid proofs don't pay for
+    /// the additional checks.
+    fn invalid_proof_error(&self, keyset: &MintKeyset, proof: &Proof) -> MokshaMintError {
+        keyset
+            .private_keys
+            .iter()
+            .filter(|(amount, _)| **amount != proof.amount)
+            .find(|(_, private_key)| {
+                self.dhke
+                    .verify_with_y(**private_key, proof.c, proof.y())
+                    .unwrap_or(false)
+            })
+            .map_or_else(
+                || MokshaMintError::InvalidProof(proof.secret.clone()),
+                |(amount, _)| {
+                    MokshaMintError::ProofAmountKeyMismatch(
+                        proof.secret.clone(),
+                        proof.amount,
+                        *amount,
+                    )
+                },
+            )
+    }
+
+    /// Rejects proofs with a legacy P2SH script. Spending them would ignore the condition the
+    /// user intended, so they are rejected until spending conditions are supported.
+    fn check_unsupported_features(proofs: &Proofs) -> Result<(), MokshaMintError> {
+        match proofs.proofs().iter().find(|proof| proof.script.is_some()) {
+            Some(proof) => Err(MokshaMintError::UnsupportedProofFeature(
+                proof.secret.clone(),
+                "a P2SH script".to_owned(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Rejects requests with more blinded messages than the mint is willing to sign, so a single
+    /// request can't keep the mint busy with elliptic curve operations.
+    pub fn check_max_outputs(&self, outputs: &[BlindedMessage]) -> Result<(), MokshaMintError> {
+        if outputs.len() > self.max_outputs {
+            return Err(MokshaMintError::TooManyOutputs(
+                outputs.len(),
+                self.max_outputs,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Rejects proofs that are mostly dust, e.g. a thousand proofs of 1 sat, because every proof
+    /// costs an elliptic curve operation to verify. The amount needs at least one proof per bit
+    /// set, so up to `max_dust_ratio` times that many proofs are accepted.
+    pub fn check_dust_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
+        let Some(max_dust_ratio) = self.max_dust_ratio else {
+            return Ok(());
+        };
+        let total_amount = proofs
+            .checked_total_amount()
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        let max_proofs = (total_amount.count_ones() as usize)
+            .max(1)
+            .saturating_mul(max_dust_ratio);
+        if proofs.len() > max_proofs {
+            return Err(MokshaMintError::TooManyDustProofs(proofs.len(), max_proofs));
+        }
+        Ok(())
+    }
+
+    /// Signs blinded messages with the auth keyset, the unblinded signatures are the auth proofs
+    /// wallets have to present on protected routes.
+    pub fn create_auth_signatures(
+        &self,
+        blinded_messages: &[BlindedMessage],
+    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        let auth_keyset = self
+            .auth_keyset
+            .as_ref()
+            .ok_or(MokshaMintError::AuthNotEnabled)?;
+        self.sign_blinded_messages(blinded_messages, &[auth_keyset])
+    }
+
+    /// Checks the auth proof if blind auth is enabled and marks it as used, so it can't be
+    /// presented again.
+    pub async fn verify_auth_proof(
+        &self,
+        auth_proof: Option<AuthProof>,
+    ) -> Result<(), MokshaMintError> {
+        let Some(auth_keyset) = self.auth_keyset.as_ref() else {
+            return Ok(());
+        };
+        let auth_proof = auth_proof.ok_or(MokshaMintError::AuthRequired)?;
+        if auth_proof.keyset_id != auth_keyset.keyset_id {
+            return Err(MokshaMintError::AuthRequired);
+        }
+
+        let proof: Proof = auth_proof.into();
+        self.verify_proof(auth_keyset, &proof)
+            .map_err(|_| MokshaMintError::AuthRequired)?;
+
+        let proofs = Proofs::with_proof(proof);
+        self.check_used_proofs(&proofs).await?;
+        self.add_used_proofs(&proofs).await?;
+        Ok(())
     }
 
+    /// Signs the blinded messages. The signatures are returned in the same order as the
+    /// messages, wallets rely on this to match them to their secrets. Every message is signed
+    /// with the active keyset it references, so one request can contain outputs of the v1 and
+    /// the legacy keyset. Messages without a keyset id are signed with the active keyset.
     pub fn create_blinded_signatures(
         &self,
         blinded_messages: &[BlindedMessage],
-        keyset: &MintKeyset, // FIXME refactor keyset management
     ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
-        let promises = blinded_messages
+        self.sign_blinded_messages(blinded_messages, &self.active_keysets())
+    }
+
+    /// The keysets the mint signs new outputs with, the active keyset first
+    fn active_keysets(&self) -> Vec<&MintKeyset> {
+        std::iter::once(&self.keyset)
+            .chain(self.keyset_legacy.iter())
+            .collect()
+    }
+
+    /// Returns the keyset a blinded message is signed with, which has to be one of the given
+    /// keysets. Messages without a keyset id are signed with the first keyset.
+    fn signing_keyset<'a>(
+        &self,
+        keyset_id: Option<&str>,
+        keysets: &[&'a MintKeyset],
+    ) -> Result<&'a MintKeyset, MokshaMintError> {
+        match keyset_id {
+            None => Ok(keysets[0]),
+            Some(id) => keysets
+                .iter()
+                .copied()
+                .find(|keyset| keyset.keyset_id == id)
+                .ok_or_else(|| self.unsignable_keyset_error(id)),
+        }
+    }
+
+    /// Signs the blinded messages with the keysets they reference, which have to be one of the
+    /// given keysets. Messages without a keyset id are signed with the first keyset.
+    fn sign_blinded_messages(
+        &self,
+        blinded_messages: &[BlindedMessage],
+        keysets: &[&MintKeyset],
+    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        blinded_messages
             .iter()
             .map(|blinded_msg| {
-                let private_key = keyset.private_keys.get(&blinded_msg.amount).unwrap(); // FIXME unwrap
+                let keyset = self.signing_keyset(blinded_msg.id.as_deref(), keysets)?;
+                // the keyset only contains keys for the supported denominations
+                let private_key = keyset
+                    .private_keys
+                    .get(&blinded_msg.amount)
+                    .ok_or(MokshaMintError::InvalidAmount)?;
                 let blinded_sig = self.dhke.step2_bob(blinded_msg.b_, private_key).unwrap(); // FIXME unwrap
-                BlindedSignature {
+                let dleq = self
+                    .dhke
+                    .step2_bob_dleq(blinded_msg.b_, blinded_sig, private_key)?;
+                Ok(BlindedSignature {
                     id: Some(keyset.keyset_id.clone()),
                     amount: blinded_msg.amount,
                     c_: blinded_sig,
-                }
+                    dleq: Some(dleq),
+                })
             })
-            .collect::<Vec<BlindedSignature>>();
-        Ok(promises)
+            .collect::<Result<Vec<BlindedSignature>, MokshaMintError>>()
+    }
+
+    /// Retired keysets are only used to redeem proofs, the mint doesn't issue new signatures
+    /// with them
+    fn unsignable_keyset_error(&self, keyset_id: &str) -> MokshaMintError {
+        if self
+            .inactive_keysets
+            .iter()
+            .any(|keyset| keyset.keyset_id == keyset_id)
+        {
+            MokshaMintError::InactiveKeyset(keyset_id.to_owned())
+        } else {
+            MokshaMintError::UnknownKeyset(keyset_id.to_owned())
+        }
     }
 
     pub async fn create_invoice(
@@ -110,6 +649,13 @@ impl Mint {
         key: String,
         amount: u64,
     ) -> Result<(String, String), MokshaMintError> {
+        if self.minting_disabled {
+            return Err(MokshaMintError::MintingDisabled);
+        }
+        if amount == 0 {
+            return Err(MokshaMintError::InvalidAmount);
+        }
+
         let pr = self.lightning.create_invoice(amount).await?.payment_request;
         self.db
             .add_pending_invoice(key.clone(), &Invoice::new(amount, pr.clone()))
@@ -117,25 +663,135 @@ impl Mint {
         Ok((pr, key))
     }
 
+    /// Creates an invoice and a mint quote for every amount, e.g. for funding a wallet from
+    /// several sources. Each quote has its own id, the quotes are returned in the order of the
+    /// amounts. The quotes and their pending invoices are stored in one transaction after all
+    /// invoices have been created.
+    pub async fn create_invoices(
+        &self,
+        amounts: &[u64],
+        unit: &CurrencyUnit,
+    ) -> Result<Vec<Bolt11MintQuote>, MokshaMintError> {
+        if self.minting_disabled {
+            return Err(MokshaMintError::MintingDisabled);
+        }
+        if amounts.is_empty() || amounts.contains(&0) {
+            return Err(MokshaMintError::InvalidAmount);
+        }
+        if amounts.len() > MAX_BATCH_INVOICES {
+            return Err(MokshaMintError::TooManyInvoices(
+                amounts.len(),
+                MAX_BATCH_INVOICES,
+            ));
+        }
+
+        let mut quotes = Vec::with_capacity(amounts.len());
+        for amount in amounts {
+            let pr = self
+                .lightning
+                .create_invoice(*amount)
+                .await?
+                .payment_request;
+            let invoice = self.lightning.decode_invoice(pr.clone()).await?;
+            let quote = Bolt11MintQuote {
+                quote_id: Uuid::new_v4(),
+                payment_request: pr.clone(),
+                expiry: self.quote_expiry(&invoice),
+                paid: false,
+                amount: *amount,
+                unit: unit.clone(),
+            };
+            quotes.push((quote, Invoice::new(*amount, pr)));
+        }
+        self.db.add_bolt11_mint_quotes(&quotes).await?;
+
+        Ok(quotes.into_iter().map(|(quote, _)| quote).collect())
+    }
+
     pub async fn mint_tokens(
         &self,
         key: String,
         outputs: &[BlindedMessage],
-        keyset: &MintKeyset,
     ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        // invoices paid before minting was disabled can still be redeemed
         let invoice = self.db.get_pending_invoice(key.clone()).await?;
 
-        let is_paid = self
-            .lightning
-            .is_invoice_paid(invoice.payment_request.clone())
-            .await?;
+        let is_paid = invoice.paid || {
+            // invoices that have already been reported as paid by a webhook don't need to be polled
+            let invoice_id = self
+                .lightning
+                .invoice_id(invoice.payment_request.clone())
+                .await?;
+            self.db.is_invoice_marked_paid(&invoice_id).await?
+                || self.poll_invoice_paid(&invoice.payment_request).await?
+        };
 
         if !is_paid {
             return Err(MokshaMintError::InvoiceNotPaidYet);
         }
 
+        // the invoice is only redeemed once the outputs are signed, so a wallet can retry with
+        // valid outputs
+        let signatures = self.create_blinded_signatures(outputs)?;
         self.db.delete_pending_invoice(key).await?;
-        self.create_blinded_signatures(outputs, keyset)
+        Ok(signatures)
+    }
+
+    /// Returns whether the pending invoice for the key is paid. Once the invoice is confirmed paid
+    /// the flag is stored in the pending invoice, so later checks don't call the lightning backend.
+    pub async fn is_invoice_paid(&self, key: &str) -> Result<bool, MokshaMintError> {
+        let invoice = self.db.get_pending_invoice(key.to_owned()).await?;
+        if invoice.paid {
+            return Ok(true);
+        }
+
+        let invoice_id = self
+            .lightning
+            .invoice_id(invoice.payment_request.clone())
+            .await?;
+        let is_paid = self.db.is_invoice_marked_paid(&invoice_id).await?
+            || self
+                .lightning
+                .is_invoice_paid(invoice.payment_request)
+                .await?;
+        if is_paid {
+            self.db.mark_pending_invoice_paid(key).await?;
+        }
+        Ok(is_paid)
+    }
+
+    /// Checks whether the invoice is paid. An unpaid invoice is checked again up to
+    /// `INVOICE_PAID_RETRIES` times, unless it has expired.
+    async fn poll_invoice_paid(&self, payment_request: &str) -> Result<bool, MokshaMintError> {
+        let mut retries = 0;
+        loop {
+            if self
+                .lightning
+                .is_invoice_paid(payment_request.to_owned())
+                .await?
+            {
+                return Ok(true);
+            }
+
+            if retries == INVOICE_PAID_RETRIES
+                || self
+                    .lightning
+                    .decode_invoice(payment_request.to_owned())
+                    .await?
+                    .is_expired()
+            {
+                return Ok(false);
+            }
+            retries += 1;
+            tokio::time::sleep(INVOICE_PAID_RETRY_DELAY).await;
+        }
+    }
+
+    /// Deletes a pending invoice that is stuck, so it can't be used for minting anymore. Fails
+    /// with `InvoiceNotFound` if there is no pending invoice for the key.
+    pub async fn cancel_pending_invoice(&self, key: String) -> Result<(), MokshaMintError> {
+        self.db.get_pending_invoice(key.clone()).await?;
+        self.db.delete_pending_invoice(key).await
     }
 
     fn has_duplicate_pubkeys(outputs: &[BlindedMessage]) -> bool {
@@ -147,122 +803,515 @@ impl Mint {
         &self,
         proofs: &Proofs,
         blinded_messages: &[BlindedMessage],
-        keyset: &MintKeyset,
     ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        Self::check_unsupported_features(proofs)?;
+        self.check_dust_proofs(proofs)?;
+        self.verify_proofs(proofs)?;
         self.check_used_proofs(proofs).await?;
+        self.check_blacklisted_proofs(proofs).await?;
+
+        Self::validate_balanced(proofs, blinded_messages)?;
 
-        if Self::has_duplicate_pubkeys(blinded_messages) {
+        let promises = self.create_blinded_signatures(blinded_messages)?;
+        self.add_used_proofs(proofs).await?;
+        Ok(promises)
+    }
+
+    /// Checks that the outputs of a swap are unique and add up to the amount of the proofs. This
+    /// runs before anything is signed or written to the db.
+    pub fn validate_balanced(
+        proofs: &Proofs,
+        outputs: &[BlindedMessage],
+    ) -> Result<(), MokshaMintError> {
+        if Self::has_duplicate_pubkeys(outputs) {
             return Err(MokshaMintError::SwapHasDuplicatePromises);
         }
 
-        let sum_proofs = proofs.total_amount();
-
-        let promises = self.create_blinded_signatures(blinded_messages, keyset)?;
-        let amount_promises = promises.total_amount();
-        if sum_proofs != amount_promises {
+        let sum_proofs = proofs
+            .checked_total_amount()
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        let sum_outputs = outputs
+            .iter()
+            .try_fold(0u64, |acc, output| acc.checked_add(output.amount))
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        if sum_proofs != sum_outputs {
             return Err(MokshaMintError::SwapAmountMismatch(format!(
-                "Split amount mismatch: {sum_proofs} != {amount_promises}"
+                "Split amount mismatch: {sum_proofs} != {sum_outputs}"
             )));
         }
+        Ok(())
+    }
 
-        self.db.add_used_proofs(proofs).await?;
-        Ok(promises)
+    /// Exchanges proofs of any known keyset for signatures of the active keyset
+    pub async fn reissue(
+        &self,
+        proofs: &Proofs,
+        outputs: &[BlindedMessage],
+    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        self.swap(proofs, outputs).await
     }
 
+    /// Pays the invoice with the proofs. The blank outputs for the change are optional: a wallet
+    /// that sends no outputs forgoes the refund of the unused fee reserve, which is then kept by
+    /// the mint.
     pub async fn melt(
         &self,
         payment_request: String,
         proofs: &Proofs,
         blinded_messages: &[BlindedMessage],
-        keyset: &MintKeyset,
     ) -> Result<(bool, String, Vec<BlindedSignature>), MokshaMintError> {
         let invoice = self
             .lightning
             .decode_invoice(payment_request.clone())
             .await?;
 
-        let proofs_amount = proofs.total_amount();
-
-        // TODO verify proofs
+        let proofs_amount = Sat(proofs
+            .checked_total_amount()
+            .ok_or(MokshaMintError::AmountOverflow)?);
 
+        Self::check_unsupported_features(proofs)?;
+        self.check_dust_proofs(proofs)?;
+        self.verify_proofs(proofs)?;
         self.check_used_proofs(proofs).await?;
+        self.check_blacklisted_proofs(proofs).await?;
 
         // TODO check for fees
-        let amount_msat = invoice
-            .amount_milli_satoshis()
-            .expect("Invoice amount is missing");
+        // invoices without an amount can't be paid with a fixed amount of proofs
+        let amount = MSat(
+            invoice
+                .amount_milli_satoshis()
+                .ok_or(MokshaMintError::InvalidAmount)?,
+        )
+        .to_sat_ceil();
 
-        if amount_msat < (proofs_amount / 1_000) {
+        if proofs_amount < amount {
             return Err(MokshaMintError::InvoiceAmountTooLow(format!(
-                "Invoice amount is too low: {amount_msat}",
+                "Proofs amount {proofs_amount} is too low for invoice amount {amount}",
             )));
         }
 
         // TODO check invoice
 
-        let result = self.lightning.pay_invoice(payment_request).await?;
-        self.db.add_used_proofs(proofs).await?;
-
-        let _remaining_amount = (amount_msat - (proofs_amount / 1000)) * 1000;
+        // the change outputs are checked before paying, so the payment can't succeed without
+        // the change being signed
+        let change_keyset = self.change_keyset(blinded_messages)?;
+
+        // the proofs are spent before paying, so concurrent melts of the same proofs are
+        // rejected by the db. They are released again if the payment fails.
+        self.add_used_proofs(proofs).await?;
+        let result = match self.lightning.pay_invoice(payment_request).await {
+            Ok(result) => result,
+            Err(err) => {
+                self.db.delete_used_proofs(proofs).await?;
+                return Err(err);
+            }
+        };
 
-        // FIXME check if output amount matches remaining_amount
-        let change = self.create_blinded_signatures(blinded_messages, keyset)?;
+        let fee_paid = MSat(result.total_fees).to_sat_ceil();
+        let change_amount = proofs_amount.0.saturating_sub(amount.0 + fee_paid.0);
+        let change =
+            self.create_change_signatures(change_keyset, change_amount, blinded_messages)?;
 
-        Ok((true, result.payment_hash, change))
+        Ok((true, result.payment_preimage, change))
     }
 
-    pub async fn check_used_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
-        let used_proofs = self.db.get_used_proofs().await?.proofs();
-        for used_proof in used_proofs {
-            if proofs.proofs().contains(&used_proof) {
-                return Err(MokshaMintError::ProofAlreadyUsed(format!("{used_proof:?}")));
+    /// Returns the keyset the change outputs of a melt reference. All outputs have to reference
+    /// the same active keyset, outputs without a keyset id reference the active keyset.
+    fn change_keyset(&self, outputs: &[BlindedMessage]) -> Result<&MintKeyset, MokshaMintError> {
+        let active_keysets = self.active_keysets();
+        let keysets = outputs
+            .iter()
+            .map(|output| self.signing_keyset(output.id.as_deref(), &active_keysets))
+            .collect::<Result<Vec<_>, _>>()?;
+        match keysets.split_first() {
+            None => Ok(&self.keyset),
+            Some((keyset, others))
+                if others
+                    .iter()
+                    .all(|other| other.keyset_id == keyset.keyset_id) =>
+            {
+                Ok(keyset)
             }
+            Some(_) => Err(MokshaMintError::InvalidRequest(
+                "outputs".to_owned(),
+                "the change outputs reference different keysets".to_owned(),
+            )),
         }
-        Ok(())
     }
-}
 
-#[derive(Debug, Default)]
-pub struct MintBuilder {
-    private_key: Option<String>,
-    lightning_type: Option<LightningType>,
-    db_url: Option<String>,
-    fee_percent: Option<f32>,
-    fee_reserve_min: Option<u64>,
-    mint_info_settings: Option<MintInfoSettings>,
-}
+    /// Signs the change of a melt (NUT-08). The outputs are blank outputs whose amounts are set to
+    /// the change split into the denominations of the keyset they reference, largest first. If no
+    /// change is due, e.g. because the fee equals the reserve, no signatures are created, so there
+    /// is never a signature for an amount of 0. Change that doesn't fit into the outputs is kept
+    /// by the mint.
+    fn create_change_signatures(
+        &self,
+        keyset: &MintKeyset,
+        change_amount: u64,
+        outputs: &[BlindedMessage],
+    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        if change_amount == 0 {
+            return Ok(Vec::new());
+        }
 
-impl MintBuilder {
-    pub fn new() -> Self {
-        Self::default()
+        let denominations = keyset.public_keys.keys().copied().collect::<Vec<_>>();
+        let change = Amount(change_amount)
+            .split_with(&denominations)
+            .ok_or(MokshaMintError::InvalidAmount)?;
+        let outputs = outputs
+            .iter()
+            .zip(change.into_iter().rev())
+            .map(|(output, amount)| BlindedMessage {
+                amount,
+                ..output.clone()
+            })
+            .collect::<Vec<_>>();
+        self.sign_blinded_messages(&outputs, &[keyset])
     }
 
-    pub fn with_mint_info(mut self, mint_info: MintInfoSettings) -> MintBuilder {
-        self.mint_info_settings = Some(mint_info);
-        self
-    }
+    /// Pays the invoice of a stored melt quote. The melt is rejected if the quote has already
+    /// been paid, doesn't match its invoice anymore or if the proofs don't cover the amount and
+    /// the fee reserve of the quote, so the wallet pays exactly the fee it was quoted. If the
+    /// wallet sets a `max_fee`, the melt is rejected before paying if the fee reserve is higher.
+    /// The proofs are marked as spent before the invoice is paid and released if the payment
+    /// fails, the quote is only marked as paid after a successful payment.
+    pub async fn execute_melt(
+        &self,
+        quote_id: &str,
+        proofs: &Proofs,
+        blinded_messages: &[BlindedMessage],
+        max_fee: Option<u64>,
+    ) -> Result<(bool, String, Vec<BlindedSignature>), MokshaMintError> {
+        let uuid = Uuid::from_str(quote_id)
+            .map_err(|_| MokshaMintError::InvalidQuote(quote_id.to_owned()))?;
+        let quote = self.db.get_bolt11_melt_quote(&uuid).await?;
+        let mismatch =
+            |reason: String| MokshaMintError::MeltQuoteMismatch(quote_id.to_owned(), reason);
+
+        if quote.paid {
+            return Err(mismatch("the quote has already been paid".to_owned()));
+        }
+        if let Some(max_fee) = max_fee.filter(|max_fee| quote.fee_reserve > *max_fee) {
+            return Err(MokshaMintError::FeeExceedsMaximum(
+                quote.fee_reserve,
+                max_fee,
+            ));
+        }
 
-    pub fn with_private_key(mut self, private_key: String) -> MintBuilder {
-        self.private_key = Some(private_key);
-        self
-    }
+        let invoice = self
+            .lightning
+            .decode_invoice(quote.payment_request.clone())
+            .await?;
+        let invoice_amount = MSat(
+            invoice
+                .amount_milli_satoshis()
+                .ok_or(MokshaMintError::InvalidAmount)?,
+        )
+        .to_sat_ceil();
+        if invoice_amount.0 != quote.amount {
+            return Err(mismatch(format!(
+                "quote amount {} differs from invoice amount {}",
+                quote.amount, invoice_amount
+            )));
+        }
 
-    pub fn with_db(mut self, db_url: String) -> MintBuilder {
-        self.db_url = Some(db_url);
-        self
-    }
+        let proofs_amount = proofs
+            .checked_total_amount()
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        let required_amount = quote
+            .amount
+            .checked_add(quote.fee_reserve)
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        if proofs_amount < required_amount {
+            return Err(mismatch(format!(
+                "proofs amount {proofs_amount} doesn't cover amount {} and fee reserve {}",
+                quote.amount, quote.fee_reserve
+            )));
+        }
 
-    pub fn with_lightning(mut self, lightning: LightningType) -> MintBuilder {
-        self.lightning_type = Some(lightning);
-        self
+        let (paid, payment_preimage, change) = self
+            .melt(quote.payment_request.to_owned(), proofs, blinded_messages)
+            .await?;
+        self.db
+            .update_bolt11_melt_quote(&Bolt11MeltQuote { paid, ..quote })
+            .await?;
+        Ok((paid, payment_preimage, change))
     }
 
-    pub fn with_fee(mut self, fee_percent: f32, fee_reserve_min: u64) -> MintBuilder {
-        self.fee_percent = Some(fee_percent);
+    /// Pays `amount` sats to a bitcoin address using the onchain wallet of the lightning backend.
+    /// Returns the txid and the signatures for the change outputs.
+    pub async fn melt_onchain(
+        &self,
+        address: String,
+        amount: u64,
+        proofs: &Proofs,
+        outputs: &[BlindedMessage],
+    ) -> Result<(String, Vec<BlindedSignature>), MokshaMintError> {
+        self.verify_proofs(proofs)?;
+        self.check_used_proofs(proofs).await?;
+        self.check_blacklisted_proofs(proofs).await?;
+
+        let proofs_amount = proofs
+            .checked_total_amount()
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        let change_amount = outputs
+            .to_vec()
+            .checked_total_amount()
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        let required_amount = amount
+            .checked_add(change_amount)
+            .ok_or(MokshaMintError::AmountOverflow)?;
+        if amount == 0 || proofs_amount < required_amount {
+            return Err(MokshaMintError::SwapAmountMismatch(format!(
+                "Onchain melt amount mismatch: {proofs_amount} < {amount} + {change_amount}"
+            )));
+        }
+
+        self.add_used_proofs(proofs).await?;
+        let txid = match self.lightning.pay_onchain(address, amount).await {
+            Ok(txid) => txid,
+            Err(err) => {
+                self.db.delete_used_proofs(proofs).await?;
+                return Err(err);
+            }
+        };
+
+        let change = self.create_blinded_signatures(outputs)?;
+        Ok((txid, change))
+    }
+
+    /// Returns for every proof whether it hasn't been spent yet
+    pub async fn check_spendable(&self, proofs: &Proofs) -> Result<Vec<bool>, MokshaMintError> {
+        let used_proofs = self.db.get_used_proofs().await?;
+        let used_secrets = used_proofs.secrets_set();
+
+        Ok(proofs
+            .proofs()
+            .iter()
+            .map(|proof| !used_secrets.contains(proof.secret.as_str()))
+            .collect())
+    }
+
+    /// Marks the proofs as spent in the db and in the filter of spent proofs
+    async fn add_used_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
+        self.db.add_used_proofs(proofs).await?;
+        if let Some(filter) = &self.spent_proofs_filter {
+            let mut filter = filter.write().expect("spent proofs filter is poisoned");
+            for proof in proofs.proofs() {
+                filter.insert(&proof.secret);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if any of the proofs has been spent. Proofs are compared by their secret only, so a
+    /// spent proof can't be reused by altering its other fields. This only rejects spent proofs
+    /// early, concurrent spends of the same proofs are prevented by the db when the proofs are
+    /// stored.
+    pub async fn check_used_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
+        // the db only needs to be queried if the filter might contain one of the secrets
+        if let Some(filter) = &self.spent_proofs_filter {
+            let filter = filter.read().expect("spent proofs filter is poisoned");
+            if !proofs
+                .proofs()
+                .iter()
+                .any(|proof| filter.might_contain(&proof.secret))
+            {
+                return Ok(());
+            }
+        }
+
+        let used_proofs = self.db.get_used_proofs().await?;
+        let used_secrets = used_proofs.secrets_set();
+
+        // all spent proofs are reported, so wallets can prune them from a partially spent batch
+        let used_indices = proofs
+            .proofs()
+            .iter()
+            .enumerate()
+            .filter(|(_, proof)| used_secrets.contains(proof.secret.as_str()))
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        match used_indices.is_empty() {
+            true => Ok(()),
+            false => Err(MokshaMintError::ProofsAlreadyUsed(used_indices)),
+        }
+    }
+
+    /// Fails if the secret of any of the proofs has been blacklisted by the operator
+    pub async fn check_blacklisted_proofs(&self, proofs: &Proofs) -> Result<(), MokshaMintError> {
+        for proof in proofs.proofs() {
+            if self.db.is_blacklisted(&proof.secret).await? {
+                return Err(MokshaMintError::ProofBlacklisted(proof.secret));
+            }
+        }
+        Ok(())
+    }
+
+    /// Refuses all future proofs with the given secret, e.g. because they are known to be stolen
+    pub async fn blacklist_secret(&self, secret: &str) -> Result<(), MokshaMintError> {
+        event!(Level::INFO, "blacklisting secret {secret}");
+        self.db.add_blacklisted_secret(secret).await
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MintBuilder {
+    private_key: Option<String>,
+    /// mnemonic and derivation path the private key is derived from
+    mnemonic: Option<(String, String)>,
+    lightning_type: Option<LightningType>,
+    lightning_timeout: Option<Duration>,
+    db_url: Option<String>,
+    db_max_connections: Option<u32>,
+    fee_percent: Option<f32>,
+    fee_reserve_min: Option<u64>,
+    fee_tiers: Option<Vec<LightningFeeTier>>,
+    mint_info_settings: Option<MintInfoSettings>,
+    admin_token: Option<String>,
+    max_order: Option<u8>,
+    denominations: Option<Denominations>,
+    max_outputs: Option<usize>,
+    max_dust_ratio: Option<usize>,
+    max_body_bytes: Option<usize>,
+    quote_expiry: Option<Duration>,
+    response_cache_ttl: Option<Duration>,
+    legacy_api: Option<bool>,
+    require_auth: Option<bool>,
+    minting_disabled: Option<bool>,
+    unit: Option<CurrencyUnit>,
+}
+
+impl MintBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mint_info(mut self, mint_info: MintInfoSettings) -> MintBuilder {
+        self.mint_info_settings = Some(mint_info);
+        self
+    }
+
+    pub fn with_private_key(mut self, private_key: String) -> MintBuilder {
+        self.private_key = Some(private_key);
+        self
+    }
+
+    /// Derives the private key from a BIP39 mnemonic instead of using a raw secret
+    pub fn with_mnemonic(mut self, mnemonic: String, derivation_path: String) -> MintBuilder {
+        self.mnemonic = Some((mnemonic, derivation_path));
+        self
+    }
+
+    pub fn with_db(mut self, db_url: String) -> MintBuilder {
+        self.db_url = Some(db_url);
+        self
+    }
+
+    pub fn with_db_pool_size(mut self, max_connections: u32) -> MintBuilder {
+        self.db_max_connections = Some(max_connections);
+        self
+    }
+
+    pub fn with_lightning(mut self, lightning: LightningType) -> MintBuilder {
+        self.lightning_type = Some(lightning);
+        self
+    }
+
+    pub fn with_lightning_timeout(mut self, timeout: Duration) -> MintBuilder {
+        self.lightning_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_admin_token(mut self, admin_token: Option<String>) -> MintBuilder {
+        self.admin_token = admin_token;
+        self
+    }
+
+    /// Enables the legacy (pre-v1) api. If disabled the legacy routes are not served and no
+    /// legacy keyset is generated. Enabled by default
+    pub fn with_legacy_api(mut self, legacy_api: bool) -> MintBuilder {
+        self.legacy_api = Some(legacy_api);
+        self
+    }
+
+    /// Rejects minting while swaps and melts keep working, e.g. to wind down the mint. Minting is
+    /// enabled by default
+    pub fn with_minting_disabled(mut self, minting_disabled: bool) -> MintBuilder {
+        self.minting_disabled = Some(minting_disabled);
+        self
+    }
+
+    /// Requires a blind auth proof (NUT-22) for minting, melting and swapping. Disabled by default
+    pub fn with_require_auth(mut self, require_auth: bool) -> MintBuilder {
+        self.require_auth = Some(require_auth);
+        self
+    }
+
+    /// Sets the number of keys per keyset. With powers of two the largest supported amount is
+    /// 2^(max_order - 1)
+    pub fn with_max_order(mut self, max_order: u8) -> MintBuilder {
+        self.max_order = Some(max_order);
+        self
+    }
+
+    /// Sets the denominations of the keyset, e.g. 1-2-5 amounts. The legacy keyset always uses
+    /// powers of two. Defaults to powers of two
+    pub fn with_denominations(mut self, denominations: Denominations) -> MintBuilder {
+        self.denominations = Some(denominations);
+        self
+    }
+
+    /// Sets the maximum number of blinded messages per request. Defaults to 1000
+    pub fn with_max_outputs(mut self, max_outputs: usize) -> MintBuilder {
+        self.max_outputs = Some(max_outputs);
+        self
+    }
+
+    /// Rejects swaps and melts with more than `max_dust_ratio` times the number of proofs the
+    /// amount needs at least. Disabled by default
+    pub fn with_max_dust_ratio(mut self, max_dust_ratio: Option<usize>) -> MintBuilder {
+        self.max_dust_ratio = max_dust_ratio;
+        self
+    }
+
+    /// Sets the maximum size of a request body in bytes. Defaults to 1 MiB
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> MintBuilder {
+        self.max_body_bytes = Some(max_body_bytes);
+        self
+    }
+
+    /// Sets the unit of the keysets that are derived from the private key. Defaults to sat
+    pub fn with_unit(mut self, unit: CurrencyUnit) -> MintBuilder {
+        self.unit = Some(unit);
+        self
+    }
+
+    /// Sets the time after which quotes expire. Defaults to 600 seconds
+    pub fn with_quote_expiry(mut self, quote_expiry: Duration) -> MintBuilder {
+        self.quote_expiry = Some(quote_expiry);
+        self
+    }
+
+    /// Sets the time for which responses of mint, melt and swap requests are cached. Defaults to
+    /// 300 seconds
+    pub fn with_response_cache_ttl(mut self, response_cache_ttl: Duration) -> MintBuilder {
+        self.response_cache_ttl = Some(response_cache_ttl);
+        self
+    }
+
+    pub fn with_fee(mut self, fee_percent: f32, fee_reserve_min: u64) -> MintBuilder {
+        self.fee_percent = Some(fee_percent);
         self.fee_reserve_min = Some(fee_reserve_min);
         self
     }
 
+    /// Sets lower fee percentages for larger payments
+    pub fn with_fee_tiers(mut self, fee_tiers: Vec<LightningFeeTier>) -> MintBuilder {
+        self.fee_tiers = Some(fee_tiers);
+        self
+    }
+
     pub async fn build(self) -> Result<Mint, MokshaMintError> {
         let ln: Arc<dyn Lightning + Send + Sync> = match self.lightning_type.clone() {
             Some(LightningType::Lnbits(lnbits_settings)) => Arc::new(LnbitsLightning::new(
@@ -271,207 +1320,1827 @@ impl MintBuilder {
             )),
             Some(LightningType::Alby(alby_settings)) => Arc::new(AlbyLightning::new(
                 alby_settings.api_key.expect("ALBY_API_KEY not set"),
+                alby_settings.webhook_secret,
             )),
             Some(LightningType::Strike(strike_settings)) => Arc::new(StrikeLightning::new(
                 strike_settings.api_key.expect("STRIKE_API_KEY not set"),
+                strike_settings.webhook_secret,
             )),
             Some(LightningType::Lnd(lnd_settings)) => Arc::new(
                 crate::lightning::LndLightning::new(
-                    lnd_settings.grpc_host.expect("LND_GRPC_HOST not set"),
-                    &lnd_settings
-                        .tls_cert_path
-                        .expect("LND_TLS_CERT_PATH not set"),
-                    &lnd_settings
-                        .macaroon_path
-                        .expect("LND_MACAROON_PATH not set"),
+                    lnd_settings
+                        .grpc_host
+                        .clone()
+                        .expect("LND_GRPC_HOST not set"),
+                    lnd_settings.tls_cert()?,
+                    lnd_settings.macaroon()?,
                 )
                 .await?,
             ),
+            #[cfg(feature = "custom-lightning")]
+            Some(LightningType::Custom(ln)) => ln,
             None => panic!("Lightning backend not set"),
         };
-
-        let db = Arc::new(crate::database::postgres::PostgresDB::new().await?);
-        db.migrate().await;
+        let ln = Arc::new(TimeoutLightning::new(
+            ln,
+            self.lightning_timeout
+                .unwrap_or(Duration::from_secs(DEFAULT_LIGHTNING_RPC_TIMEOUT_SECS)),
+        ));
+
+        let db_url = self.db_url.expect("MINT_DB_URL not set");
+        let db: Arc<dyn Database + Send + Sync> = if db_url.starts_with(MEMORY_DB_URL) {
+            Arc::new(InMemoryDatabase::new())
+        } else {
+            let db = crate::database::postgres::PostgresDB::new(
+                &db_url,
+                self.db_max_connections
+                    .unwrap_or(DEFAULT_DB_MAX_CONNECTIONS),
+            )
+            .await?;
+            db.migrate().await?;
+            event!(
+                Level::INFO,
+                "database schema version: {:?}",
+                db.schema_version().await?
+            );
+            Arc::new(db)
+        };
 
         let fee_config = LightningFeeConfig::new(
             self.fee_percent.expect("LIGHTNING_FEE_PERCENT not set"),
             self.fee_reserve_min
                 .expect("LIGHTNING_RESERVE_FEE_MIN not set"),
+        )
+        .with_fee_tiers(self.fee_tiers.unwrap_or_default());
+
+        let max_order = self.max_order.unwrap_or(MAX_ORDER);
+        if max_order == 0 || max_order > MAX_ORDER {
+            return Err(MokshaMintError::InvalidMaxOrder(max_order));
+        }
+
+        let private_key = match self.mnemonic {
+            Some((mnemonic, derivation_path)) => secret_from_mnemonic(&mnemonic, &derivation_path)?,
+            None => self.private_key.expect("MINT_PRIVATE_KEY not set"),
+        };
+        let unit = self.unit.unwrap_or(CurrencyUnit::Sat);
+        let keyset = MintKeyset::new_with_denominations(
+            &private_key,
+            "",
+            max_order,
+            self.denominations.unwrap_or_default(),
+        )
+        .with_unit(unit.clone());
+        let mut mint = Mint {
+            admin_token: self.admin_token,
+            keyset_legacy: self.legacy_api.unwrap_or(true).then(|| {
+                MintKeyset::legacy_new_with_max_order(&private_key, "", max_order).with_unit(unit)
+            }),
+            inactive_keysets: vec![],
+            pre_nut02_keysets: vec![keyset.with_pre_nut02_id()],
+            keyset,
+            // auth proofs always have an amount of 1, so the auth keyset only needs a single key
+            auth_keyset: self.require_auth.unwrap_or(false).then(|| {
+                MintKeyset::new_with_max_order(&private_key, AUTH_DERIVATION_PATH, 1)
+                    .with_unit(CurrencyUnit::Auth)
+            }),
+            max_outputs: self.max_outputs.unwrap_or(DEFAULT_MAX_OUTPUTS),
+            max_dust_ratio: self.max_dust_ratio,
+            max_body_bytes: self.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES),
+            quote_expiry: self
+                .quote_expiry
+                .unwrap_or(Duration::from_secs(DEFAULT_QUOTE_EXPIRY_SECS)),
+            minting_disabled: self.minting_disabled.unwrap_or(false),
+            response_cache: Arc::new(ResponseCache::new(
+                self.response_cache_ttl
+                    .unwrap_or(Duration::from_secs(DEFAULT_RESPONSE_CACHE_TTL_SECS)),
+            )),
+            ..Mint::new(
+                private_key,
+                "".to_string(),
+                ln,
+                self.lightning_type.expect("Lightning backend not set"),
+                db,
+                fee_config,
+                self.mint_info_settings.unwrap_or_default(),
+            )
+        };
+        mint.load_keysets().await?;
+        mint.load_spent_proofs_filter().await?;
+        Ok(mint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::memory::InMemoryDatabase;
+    use crate::lightning::error::LightningError;
+    use crate::lightning::{LightningType, MockLightning};
+    use crate::mint::{LightningFeeConfig, LightningFeeTier, Mint, DEFAULT_QUOTE_EXPIRY_SECS};
+    use crate::model::{CreateInvoiceResult, Invoice, MSat, PayInvoiceResult, Sat};
+    use crate::{database::MockDatabase, error::MokshaMintError};
+    use chrono::Utc;
+    use lightning_invoice::Bolt11Invoice as LNInvoice;
+    use moksha_core::amount::{Amount, Denominations};
+    use moksha_core::blind::{BlindedMessage, TotalAmount};
+    use moksha_core::dhke;
+    use moksha_core::keyset::MintKeyset;
+    use moksha_core::primitives::{Bolt11MeltQuote, CurrencyUnit, PostSplitRequest};
+    use moksha_core::proof::{P2SHScript, Proof, Proofs};
+    use moksha_core::token::TokenV3;
+    use std::collections::{HashMap, HashSet};
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_fee_reserve() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(None, None);
+        let fee = mint.fee_reserve(MSat(10_000));
+        assert_eq!(Sat(4), fee);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee_reserve_prefers_backend_estimate() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning.expect_estimate_fee().returning(|_| Ok(Sat(7)));
+        let mint = create_mint_from_mocks(None, Some(lightning));
+
+        // 100 sat, the configured reserve would be 4 sat
+        let invoice = lightning_invoice::Bolt11Invoice::from_str("lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv")?;
+        assert_eq!(Sat(7), mint.estimate_fee_reserve(&invoice).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee_reserve_falls_back_to_config() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_estimate_fee()
+            .returning(|_| Err(MokshaMintError::FeeEstimateNotSupported));
+        lightning.expect_min_fee_reserve().returning(|| MSat(0));
+        let mint = create_mint_from_mocks(None, Some(lightning));
+
+        let invoice = lightning_invoice::Bolt11Invoice::from_str("lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv")?;
+        assert_eq!(Sat(4), mint.estimate_fee_reserve(&invoice).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quote_melt() -> anyhow::Result<()> {
+        // 100 sat
+        let pr = "lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv";
+        let mut lightning = MockLightning::new();
+        lightning.expect_decode_invoice().returning(|pr| {
+            lightning_invoice::Bolt11Invoice::from_str(&pr)
+                .map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+        lightning
+            .expect_estimate_fee()
+            .returning(|_| Err(MokshaMintError::FeeEstimateNotSupported));
+        lightning.expect_min_fee_reserve().returning(|| MSat(0));
+        let mint = create_mint_from_mocks(None, Some(lightning));
+
+        let info = mint.quote_melt(pr).await?;
+        let invoice = lightning_invoice::Bolt11Invoice::from_str(pr)?;
+        assert_eq!(100, info.amount_sat);
+        assert_eq!(4, info.fee_reserve);
+        // the invoice has already expired, so the quote expires with the invoice
+        assert_eq!(
+            (invoice.duration_since_epoch() + invoice.expiry_time()).as_secs(),
+            info.expiry
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fee_reserve_uses_backend_minimum() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_min_fee_reserve()
+            .returning(|| MSat(10_000));
+        let mint = create_mint_from_mocks(None, Some(lightning));
+
+        // the configured minimum is 4 sat
+        assert_eq!(Sat(10), mint.fee_reserve(MSat(10_000)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fee_reserve_sub_sat_amounts() -> anyhow::Result<()> {
+        let mut mint = create_mint_from_mocks(None, None);
+        mint.lightning_fee_config = LightningFeeConfig::new(1.0, 0);
+
+        // 1% of 1_000_500 msat is 10_005 msat, which needs 11 sat to be covered
+        assert_eq!(Sat(11), mint.fee_reserve(MSat(1_000_500)));
+        assert_eq!(Sat(10), mint.fee_reserve(MSat(1_000_000)));
+        assert_eq!(Sat(2), mint.fee_reserve(MSat(150_000)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fee_reserve_tiers() -> anyhow::Result<()> {
+        let mut mint = create_mint_from_mocks(None, None);
+        mint.lightning_fee_config = LightningFeeConfig::new(1.0, 0)
+            .with_fee_tiers(LightningFeeTier::parse_list("1000000:0.25, 100000:0.5")?);
+
+        // below the first tier the base percent applies
+        assert_eq!(Sat(500), mint.fee_reserve(MSat(50_000_000)));
+        assert_eq!(Sat(500), mint.fee_reserve(MSat(100_000_000)));
+        assert_eq!(Sat(2_500), mint.fee_reserve(MSat(1_000_000_000)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_fee_tiers() -> anyhow::Result<()> {
+        assert_eq!(
+            vec![LightningFeeTier::new(100_000, 0.5)],
+            LightningFeeTier::parse_list("100000:0.5")?
+        );
+        assert!(LightningFeeTier::parse_list("")?.is_empty());
+        assert!(matches!(
+            LightningFeeTier::parse_list("100000"),
+            Err(MokshaMintError::InvalidFeeTier(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_msat_to_sat_rounds_up() -> anyhow::Result<()> {
+        assert_eq!(Sat(0), MSat(0).to_sat_ceil());
+        assert_eq!(Sat(1), MSat(1).to_sat_ceil());
+        assert_eq!(Sat(1), MSat(1_000).to_sat_ceil());
+        assert_eq!(Sat(2), MSat(1_001).to_sat_ceil());
+        assert_eq!(Sat(21), MSat(20_500).to_sat_ceil());
+        assert_eq!(MSat(21_000), Sat(21).to_msat());
+        Ok(())
+    }
+
+    #[test]
+    fn test_msat_to_sat_floor() -> anyhow::Result<()> {
+        assert_eq!(Sat(1), MSat(1_500).to_sat_floor());
+        assert_eq!(Sat(2), MSat(1_500).to_sat_ceil());
+        assert_eq!(Sat(0), MSat(999).to_sat_floor());
+        assert_eq!(Sat(1), MSat(1_000).to_sat_floor());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_blindsignatures() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(None, None);
+
+        let blinded_messages = vec![BlindedMessage {
+            amount: 8,
+            b_: dhke::public_key_from_hex(
+                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+            ),
+            id: None,
+        }];
+
+        let result = mint.create_blinded_signatures(&mint.legacy_outputs(&blinded_messages)?)?;
+
+        assert_eq!(1, result.len());
+        assert_eq!(8, result[0].amount);
+        assert_eq!(
+            dhke::public_key_from_hex(
+                "037074c4f53e326ee14ed67125f387d160e0e729351471b69ad41f7d5d21071e15"
+            ),
+            result[0].c_
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_invoice_zero_amount() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(None, None);
+
+        let result = mint.create_invoice("somehash".to_string(), 0).await;
+        assert!(matches!(result, Err(MokshaMintError::InvalidAmount)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_empty() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_invoice_id()
+            .returning(|_| Ok("invoice_id".to_string()));
+        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let mint = create_mint_from_mocks(Some(create_mock_mint()), Some(lightning));
+
+        let outputs = vec![];
+        let result = mint.mint_tokens("somehash".to_string(), &outputs).await?;
+        assert!(result.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_valid() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_invoice_id()
+            .returning(|_| Ok("invoice_id".to_string()));
+        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let mint = create_mint_from_mocks(Some(create_mock_mint()), Some(lightning));
+
+        let outputs = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
+        let result = mint.mint_tokens("somehash".to_string(), &outputs).await?;
+        assert_eq!(40, result.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_invoice_reported_paid_after_retry() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_invoice_id()
+            .returning(|_| Ok("invoice_id".to_string()));
+        // the backend lags behind and reports the paid invoice as unpaid on the first check
+        let checks = Arc::new(AtomicUsize::new(0));
+        lightning
+            .expect_is_invoice_paid()
+            .times(2)
+            .returning(move |_| Ok(checks.fetch_add(1, Ordering::SeqCst) > 0));
+        lightning.expect_decode_invoice().returning(|_| {
+            // expires in 2123
+            Ok(LNInvoice::from_str("lnbcrt5u1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxq8zals8sqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssq2psnxdza6tl2yhk4rj9z5pj6vv9l9e53ve4645hj0zcn8e5t64q566xj0928r6ln6nw4n5ey9f43r2jarxmzst85qpf42k4s22n55cgqgjrc6w").expect("invalid invoice"))
+        });
+        let mint = create_mint_from_mocks(Some(create_mock_mint()), Some(lightning));
+
+        let outputs = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
+        let result = mint.mint_tokens("somehash".to_string(), &outputs).await?;
+        assert_eq!(40, result.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_expired_invoice_not_polled_again() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_invoice_id()
+            .returning(|_| Ok("invoice_id".to_string()));
+        lightning
+            .expect_is_invoice_paid()
+            .times(1)
+            .returning(|_| Ok(false));
+        lightning.expect_decode_invoice().returning(|pr| {
+            LNInvoice::from_str(&pr).map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+        let mint = create_mint_from_mocks(Some(create_mock_mint()), Some(lightning));
+
+        let outputs = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
+        let result = mint.mint_tokens("somehash".to_string(), &outputs).await;
+        assert!(matches!(result, Err(MokshaMintError::InvoiceNotPaidYet)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_invoice_marked_paid_by_webhook() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_invoice_id()
+            .returning(|_| Ok("invoice_id".to_string()));
+        lightning.expect_is_invoice_paid().never();
+
+        let mut mock_db = MockDatabase::new();
+        mock_db
+            .expect_get_pending_invoice()
+            .returning(|_| Ok(Invoice::new(100, "payment_request".to_string())));
+        mock_db
+            .expect_is_invoice_marked_paid()
+            .returning(|_| Ok(true));
+        mock_db
+            .expect_delete_pending_invoice()
+            .returning(|_| Ok(()));
+        let mint = create_mint_from_mocks(Some(mock_db), Some(lightning));
+
+        let outputs = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
+        let result = mint.mint_tokens("somehash".to_string(), &outputs).await?;
+        assert_eq!(40, result.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_unknown_keyset_keeps_invoice_pending() -> anyhow::Result<()> {
+        let mint = Mint {
+            db: Arc::new(InMemoryDatabase::new()),
+            ..create_melt_mint(0)
+        };
+        let invoice = Invoice {
+            paid: true,
+            ..Invoice::new(8, "payment_request".to_string())
+        };
+        mint.db
+            .add_pending_invoice("paid_key".to_string(), &invoice)
+            .await?;
+        let (b_, _) = mint.dhke.step1_alice("minted_secret", None)?;
+        let outputs = vec![BlindedMessage {
+            amount: 8,
+            b_,
+            id: Some("unknown".to_string()),
+        }];
+
+        let result = mint.mint_tokens("paid_key".to_string(), &outputs).await;
+
+        assert!(matches!(result, Err(MokshaMintError::UnknownKeyset(_))));
+        assert!(
+            mint.db
+                .get_pending_invoice("paid_key".to_string())
+                .await?
+                .paid
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_zero() -> anyhow::Result<()> {
+        let blinded_messages = vec![];
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+
+        let proofs = Proofs::empty();
+        let result = mint.swap(&proofs, &blinded_messages).await?;
+
+        assert!(result.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_64_in_20() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        let request = create_request_from_fixture("post_split_request_64_20.json".to_string())?;
+        let proofs = create_proofs(&mint, &mint.keyset, &[64])?;
+
+        let result = mint.swap(&proofs, &request.outputs).await?;
+        assert_eq!(result.total_amount(), 64);
+
+        let prv_lst = result.get(result.len() - 2).unwrap();
+        let lst = result.last().unwrap();
+
+        assert_eq!(prv_lst.amount, 4);
+        assert_eq!(lst.amount, 16);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_preserves_output_order() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        // outputs are deliberately not sorted by amount: 4, 8, 32, 4, 16
+        let request = create_request_from_fixture("post_split_request_64_20.json".to_string())?;
+        let proofs = create_proofs(&mint, mint.legacy_keyset()?, &[64])?;
+
+        let outputs = mint.legacy_outputs(&request.outputs)?;
+        let result = mint.swap(&proofs, &outputs).await?;
+
+        assert_eq!(request.outputs.len(), result.len());
+        for (output, signature) in request.outputs.iter().zip(result.iter()) {
+            let private_key = mint.legacy_keyset()?.private_keys[&output.amount];
+            assert_eq!(output.amount, signature.amount);
+            assert_eq!(mint.dhke.step2_bob(output.b_, &private_key)?, signature.c_);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_amount_overflow() -> anyhow::Result<()> {
+        let c = dhke::public_key_from_hex(
+            "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
+        );
+        let proofs = Proofs::new(vec![
+            Proof::new(u64::MAX, "secret_1".to_string(), c, "someid".to_string()),
+            Proof::new(1, "secret_2".to_string(), c, "someid".to_string()),
+        ]);
+
+        let result = Mint::validate_balanced(&proofs, &[]);
+        assert!(matches!(result, Err(MokshaMintError::AmountOverflow)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_amount_beyond_max_order() -> anyhow::Result<()> {
+        let mut mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        mint.keyset_legacy = Some(MintKeyset::legacy_new_with_max_order(
+            "TEST_PRIVATE_KEY",
+            "",
+            32,
+        ));
+
+        let proofs = create_proofs(&mint, mint.legacy_keyset()?, &[2u64.pow(31), 2u64.pow(31)])?;
+        let outputs = vec![BlindedMessage {
+            amount: 2u64.pow(32),
+            b_: dhke::public_key_from_hex(
+                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+            ),
+            id: None,
+        }];
+
+        let result = mint.swap(&proofs, &mint.legacy_outputs(&outputs)?).await;
+        assert!(matches!(result, Err(MokshaMintError::InvalidAmount)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_rejects_amount_outside_denominations() -> anyhow::Result<()> {
+        let mut mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        mint.keyset = MintKeyset::new_with_denominations(
+            "TEST_PRIVATE_KEY",
+            "0/0/0/0",
+            64,
+            Denominations::OneTwoFive,
+        );
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[20, 10, 5, 2])?;
+        // 37 split into powers of two contains amounts the 1-2-5 keyset has no keys for
+        let outputs = Amount(37)
+            .split()
+            .into_iter()
+            .map(|amount| {
+                let (b_, _) = mint.dhke.step1_alice(format!("output_{amount}"), None)?;
+                Ok(BlindedMessage {
+                    amount,
+                    b_,
+                    id: None,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let result = mint.swap(&proofs, &outputs).await;
+        assert!(matches!(result, Err(MokshaMintError::InvalidAmount)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_blindsignatures_unknown_keyset() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(None, None);
+
+        let blinded_messages = vec![BlindedMessage {
+            amount: 8,
+            b_: dhke::public_key_from_hex(
+                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+            ),
+            id: Some("00ffffffffffffff".to_string()),
+        }];
+
+        let result = mint.create_blinded_signatures(&blinded_messages);
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::UnknownKeyset(id)) if id == "00ffffffffffffff"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_blindsignatures_multiple_keysets() -> anyhow::Result<()> {
+        let mut mint = create_mint_from_mocks(None, None);
+        let old_keyset = mint.keyset.clone();
+        mint.rotate_keyset(MintKeyset::new("TEST_PRIVATE_KEY", "0/0/0/1"));
+        let new_keyset = mint.keyset.clone();
+
+        let b_ = dhke::public_key_from_hex(
+            "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+        );
+        let blinded_messages = vec![
+            BlindedMessage {
+                amount: 8,
+                b_,
+                id: Some(new_keyset.keyset_id.clone()),
+            },
+            BlindedMessage {
+                amount: 2,
+                b_,
+                id: Some(old_keyset.keyset_id.clone()),
+            },
+        ];
+
+        let result = mint.create_blinded_signatures(&blinded_messages);
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InactiveKeyset(id)) if id == old_keyset.keyset_id
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_blindsignatures_v1_and_legacy_keysets() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(None, None);
+        let keyset = mint.keyset.clone();
+        let legacy_keyset = mint.legacy_keyset()?.clone();
+
+        let b_ = dhke::public_key_from_hex(
+            "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+        );
+        let blinded_messages = vec![
+            BlindedMessage {
+                amount: 8,
+                b_,
+                id: Some(legacy_keyset.keyset_id.clone()),
+            },
+            BlindedMessage {
+                amount: 8,
+                b_,
+                id: Some(keyset.keyset_id.clone()),
+            },
+            BlindedMessage {
+                amount: 2,
+                b_,
+                id: None,
+            },
+        ];
+
+        let result = mint.create_blinded_signatures(&blinded_messages)?;
+
+        let expected_keysets = [&legacy_keyset, &keyset, &keyset];
+        assert_eq!(3, result.len());
+        for ((msg, signature), keyset) in blinded_messages.iter().zip(&result).zip(expected_keysets)
+        {
+            assert_eq!(Some(keyset.keyset_id.clone()), signature.id);
+            let private_key = keyset.private_keys[&msg.amount];
+            assert_eq!(mint.dhke.step2_bob(msg.b_, &private_key)?, signature.c_);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_auth_signatures_rejects_mint_keyset() -> anyhow::Result<()> {
+        let mut mint = create_mint_from_mocks(None, None);
+        mint.auth_keyset = Some(MintKeyset::new("TEST_PRIVATE_KEY", "0/0/0/auth"));
+
+        let result = mint.create_auth_signatures(&[BlindedMessage {
+            amount: 1,
+            b_: dhke::public_key_from_hex(
+                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+            ),
+            id: Some(mint.keyset.keyset_id.clone()),
+        }]);
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::UnknownKeyset(id)) if id == mint.keyset.keyset_id
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_imbalanced() -> anyhow::Result<()> {
+        let mut mock_db = MockDatabase::new();
+        mock_db
+            .expect_get_used_proofs()
+            .returning(|| Ok(Proofs::empty()));
+        mock_db.expect_is_blacklisted().returning(|_| Ok(false));
+        // the proofs must not be marked as used
+        mock_db.expect_add_used_proofs().never();
+        let mint = create_mint_from_mocks(Some(mock_db), None);
+        let mut request = create_request_from_fixture("post_split_request_64_20.json".to_string())?;
+        request.outputs.pop();
+        let proofs = create_proofs(&mint, &mint.keyset, &[64])?;
+
+        let result = mint.swap(&proofs, &request.outputs).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::SwapAmountMismatch(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_duplicate_key() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        let request =
+            create_request_from_fixture("post_split_request_duplicate_key.json".to_string())?;
+
+        let result = mint.swap(&request.proofs, &request.outputs).await;
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// melt 20 sats with 60 tokens and receive 40 tokens as change
+    async fn test_melt_overpay() -> anyhow::Result<()> {
+        use lightning_invoice::Bolt11Invoice as LNInvoice;
+
+        let mut lightning = MockLightning::new();
+
+        lightning.expect_decode_invoice().returning(|_| {
+            Ok(
+                // 20 sat
+                LNInvoice::from_str("lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40").expect("invalid invoice")
+            )
+        });
+        lightning.expect_pay_invoice().returning(|_| {
+            Ok(PayInvoiceResult {
+                payment_hash: "hash".to_string(),
+                payment_preimage: "preimage".to_string(),
+                total_fees: 0,
+            })
+            .map_err(|_err: LightningError| MokshaMintError::InvoiceNotFound("".to_string()))
+        });
+
+        let mint = Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "0/0/0/0".to_string(),
+            Arc::new(lightning),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(create_mock_db_get_used_proofs()),
+            Default::default(),
+            Default::default(),
+        );
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[32, 16, 8, 4])?;
+        let invoice = "some invoice".to_string();
+        let change = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
+
+        let (paid, payment_preimage, change) = mint.melt(invoice, &proofs, &change).await?;
+
+        assert!(paid);
+        assert_eq!("preimage", payment_preimage);
+        assert!(change.total_amount() == 40);
+        Ok(())
+    }
+
+    fn create_melt_mint(total_fees_msat: u64) -> Mint {
+        let mut lightning = MockLightning::new();
+        lightning.expect_decode_invoice().returning(|pr| {
+            lightning_invoice::Bolt11Invoice::from_str(&pr)
+                .map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+        lightning.expect_pay_invoice().returning(move |_| {
+            Ok(PayInvoiceResult {
+                payment_hash: "hash".to_string(),
+                payment_preimage: "preimage".to_string(),
+                total_fees: total_fees_msat,
+            })
+        });
+        Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "0/0/0/0".to_string(),
+            Arc::new(lightning),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(create_mock_db_get_used_proofs()),
+            Default::default(),
+            Default::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_melt_fee_equals_reserve_returns_no_change() -> anyhow::Result<()> {
+        // 21 sats, paid with 25 sats and a fee of 4 sats
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let mint = create_melt_mint(4_000);
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+        let blank_outputs = BlindedMessage::blank(Amount(4))?
+            .into_iter()
+            .map(|(output, _, _)| output)
+            .collect::<Vec<_>>();
+
+        let (paid, _, change) = mint.melt(invoice, &proofs, &blank_outputs).await?;
+
+        assert!(paid);
+        assert!(change.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_without_change_outputs() -> anyhow::Result<()> {
+        // 21 sats, paid with 25 sats and a fee of 1 sat
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let mint = create_melt_mint(1_000);
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+
+        // the unused reserve of 3 sats is kept by the mint
+        let (paid, _, change) = mint.melt(invoice, &proofs, &[]).await?;
+
+        assert!(paid);
+        assert!(change.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_releases_proofs_if_payment_fails() -> anyhow::Result<()> {
+        // 21 sats, paid with 25 sats
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let mut lightning = MockLightning::new();
+        lightning.expect_decode_invoice().returning(|pr| {
+            lightning_invoice::Bolt11Invoice::from_str(&pr)
+                .map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+        lightning
+            .expect_pay_invoice()
+            .returning(|_| Err(MokshaMintError::LightningTimeout));
+        let mint = Mint {
+            lightning: Arc::new(lightning),
+            db: Arc::new(InMemoryDatabase::new()),
+            ..create_melt_mint(0)
+        };
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+
+        let result = mint.melt(invoice, &proofs, &[]).await;
+
+        assert!(matches!(result, Err(MokshaMintError::LightningTimeout)));
+        assert!(mint.db.get_used_proofs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_rejects_dust_proofs() -> anyhow::Result<()> {
+        // 21 sats, paid with 25 sats and a fee of 1 sat
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let mint = Mint {
+            max_dust_ratio: Some(10),
+            ..create_melt_mint(1_000)
+        };
+
+        // 1000 sats need at least 6 proofs, so at most 60 proofs are accepted
+        let dust = create_proofs(&mint, &mint.keyset, &[1; 1000])?;
+        let result = mint.melt(invoice.clone(), &dust, &[]).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::TooManyDustProofs(1000, 60))
+        ));
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+        let (paid, _, _) = mint.melt(invoice, &proofs, &[]).await?;
+        assert!(paid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_drain_mode_rejects_minting_but_melts() -> anyhow::Result<()> {
+        // 21 sats, paid with 25 sats and a fee of 4 sats
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let mint = Mint {
+            minting_disabled: true,
+            db: Arc::new(InMemoryDatabase::new()),
+            ..create_melt_mint(4_000)
+        };
+
+        let result = mint.create_invoice("key".to_string(), 21).await;
+        assert!(matches!(result, Err(MokshaMintError::MintingDisabled)));
+
+        // an invoice that has been paid before minting was disabled is still redeemed
+        let paid_invoice = Invoice {
+            paid: true,
+            ..Invoice::new(8, invoice.clone())
+        };
+        mint.db
+            .add_pending_invoice("paid_key".to_string(), &paid_invoice)
+            .await?;
+        let (b_, _) = mint.dhke.step1_alice("minted_secret", None)?;
+        let outputs = vec![BlindedMessage {
+            amount: 8,
+            b_,
+            id: None,
+        }];
+        let signatures = mint.mint_tokens("paid_key".to_string(), &outputs).await?;
+        assert_eq!(8, signatures.total_amount());
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+        let (paid, _, _) = mint.melt(invoice, &proofs, &[]).await?;
+        assert!(paid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_signs_change_for_blank_outputs() -> anyhow::Result<()> {
+        // 21 sats, paid with 25 sats and a fee of 1 sat
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let mint = create_melt_mint(1_000);
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+        let blank_outputs = BlindedMessage::blank(Amount(4))?
+            .into_iter()
+            .map(|(output, _, _)| output)
+            .collect::<Vec<_>>();
+
+        let (_, _, change) = mint.melt(invoice, &proofs, &blank_outputs).await?;
+
+        assert_eq!(
+            vec![2, 1],
+            change
+                .iter()
+                .map(|signature| signature.amount)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_splits_change_into_denominations_of_output_keyset() -> anyhow::Result<()> {
+        // 21 sats, paid with 26 sats without a fee
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let keyset = MintKeyset::new_with_denominations(
+            "TEST_PRIVATE_KEY",
+            "0/0/0/1",
+            9,
+            Denominations::OneTwoFive,
+        );
+        let mint = Mint {
+            keyset_legacy: Some(keyset.clone()),
+            ..create_melt_mint(0)
+        };
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 2])?;
+        let blank_outputs = BlindedMessage::blank(Amount(5))?
+            .into_iter()
+            .map(|(output, _, _)| BlindedMessage {
+                id: Some(keyset.keyset_id.clone()),
+                ..output
+            })
+            .collect::<Vec<_>>();
+
+        let (_, _, change) = mint.melt(invoice, &proofs, &blank_outputs).await?;
+
+        assert_eq!(
+            vec![(5, Some(keyset.keyset_id))],
+            change
+                .into_iter()
+                .map(|signature| (signature.amount, signature.id))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_rejects_unknown_change_keyset_before_paying() -> anyhow::Result<()> {
+        // 21 sats, paid with 25 sats and a fee of 1 sat
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let mint = Mint {
+            db: Arc::new(InMemoryDatabase::new()),
+            ..create_melt_mint(1_000)
+        };
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+        let blank_outputs = BlindedMessage::blank(Amount(4))?
+            .into_iter()
+            .map(|(output, _, _)| BlindedMessage {
+                id: Some("unknown".to_string()),
+                ..output
+            })
+            .collect::<Vec<_>>();
+
+        let result = mint.melt(invoice, &proofs, &blank_outputs).await;
+
+        assert!(matches!(result, Err(MokshaMintError::UnknownKeyset(_))));
+        assert!(mint.db.get_used_proofs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_proofs_amount_too_low() -> anyhow::Result<()> {
+        use lightning_invoice::Bolt11Invoice as LNInvoice;
+
+        let mut lightning = MockLightning::new();
+        lightning.expect_decode_invoice().returning(|_| {
+            Ok(
+                // 100 sat
+                LNInvoice::from_str("lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv").expect("invalid invoice")
+            )
+        });
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), Some(lightning));
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[32, 16, 8, 4])?;
+        let result = mint.melt("some invoice".to_string(), &proofs, &[]).await;
+
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvoiceAmountTooLow(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_rejects_invoice_without_amount() -> anyhow::Result<()> {
+        // a donation invoice of any amount from the BOLT11 test vectors
+        let invoice = "lnbc1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq9qrsgq357wnc5r2ueh7ck6q93dj32dlqnls087fxdwk8qakdyafkq3yap9us6v52vjjsrvywa6rt52cm9r9zqt8r2t7mlcwspyetp5h2tztugp9lfyql".to_string();
+        let mint = create_melt_mint(0);
+        let proofs = create_proofs(&mint, &mint.keyset, &[16, 8, 1])?;
+
+        let result = mint.melt(invoice, &proofs, &[]).await;
+
+        assert!(matches!(result, Err(MokshaMintError::InvalidAmount)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_rejects_invalid_proofs() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning.expect_decode_invoice().returning(|pr| {
+            LNInvoice::from_str(&pr).map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+        lightning.expect_pay_invoice().never();
+        let mut mock_db = MockDatabase::new();
+        mock_db.expect_add_used_proofs().never();
+        let mint = create_mint_from_mocks(Some(mock_db), Some(lightning));
+        // 20 sat
+        let invoice = "lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40".to_string();
+
+        // the proofs of the fixture are signed by another mint
+        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
+        let result = mint.melt(invoice, &tokens.proofs(), &[]).await;
+
+        assert!(matches!(result, Err(MokshaMintError::UnknownKeyset(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_rejects_invalid_proofs() -> anyhow::Result<()> {
+        let mut mock_db = MockDatabase::new();
+        mock_db.expect_add_used_proofs().never();
+        let mint = create_mint_from_mocks(Some(mock_db), None);
+        let request = create_request_from_fixture("post_split_request_64_20.json".to_string())?;
+
+        // the proofs of the fixture are signed by another mint
+        let result = mint.swap(&request.proofs, &request.outputs).await;
+
+        assert!(matches!(result, Err(MokshaMintError::UnknownKeyset(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_used_proofs_unspent_skips_db() -> anyhow::Result<()> {
+        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
+        let spent_proofs = Proofs::with_proof(tokens.proofs().proofs()[0].clone());
+
+        // the db is only queried once to build the filter
+        let mut mock_db = MockDatabase::new();
+        mock_db
+            .expect_get_used_proofs()
+            .times(1)
+            .returning(move || Ok(spent_proofs.clone()));
+        let mut mint = create_mint_from_mocks(Some(mock_db), None);
+        mint.load_spent_proofs_filter().await?;
+
+        let unspent_proofs = Proofs::with_proof(tokens.proofs().proofs()[1].clone());
+        mint.check_used_proofs(&unspent_proofs).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_used_proofs_spent_with_filter() -> anyhow::Result<()> {
+        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
+        let spent_proofs = Proofs::with_proof(tokens.proofs().proofs()[0].clone());
+
+        // the filter hit is confirmed by the db
+        let mut mock_db = MockDatabase::new();
+        let used_proofs = spent_proofs.clone();
+        mock_db
+            .expect_get_used_proofs()
+            .times(2)
+            .returning(move || Ok(used_proofs.clone()));
+        let mut mint = create_mint_from_mocks(Some(mock_db), None);
+        mint.load_spent_proofs_filter().await?;
+
+        let result = mint.check_used_proofs(&spent_proofs).await;
+        assert!(matches!(result, Err(MokshaMintError::ProofsAlreadyUsed(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_used_proofs_compares_secret() -> anyhow::Result<()> {
+        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
+        let spent_proof = tokens.proofs().proofs()[0].clone();
+        let used_proofs = Proofs::with_proof(spent_proof.clone());
+
+        let mut mock_db = MockDatabase::new();
+        mock_db
+            .expect_get_used_proofs()
+            .returning(move || Ok(used_proofs.clone()));
+        let mint = create_mint_from_mocks(Some(mock_db), None);
+
+        // same secret, but different keyset id and script
+        let mut altered_proof = spent_proof.clone();
+        altered_proof.keyset_id = "00ffd48b8f5ecf80".to_string();
+        altered_proof.script = Some(P2SHScript {
+            script: "OTXB1yYTvUpkLDjBkJRgSaGeYzS7WG2Jn0pV0ex5pHI=".to_string(),
+            signature: "MEQCIBsz8vZ...".to_string(),
+            address: None,
+        });
+        let result = mint
+            .check_used_proofs(&Proofs::with_proof(altered_proof))
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::ProofsAlreadyUsed(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_used_proofs_reports_indices() -> anyhow::Result<()> {
+        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
+        let proofs = tokens.proofs().proofs();
+        let used_proofs = Proofs::new(vec![proofs[0].clone(), proofs[2].clone()]);
+
+        let mut mock_db = MockDatabase::new();
+        mock_db
+            .expect_get_used_proofs()
+            .returning(move || Ok(used_proofs.clone()));
+        let mint = create_mint_from_mocks(Some(mock_db), None);
+
+        let result = mint.check_used_proofs(&tokens.proofs()).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::ProofsAlreadyUsed(indices)) if indices == vec![0, 2]
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_spendable() -> anyhow::Result<()> {
+        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
+        let proofs = tokens.proofs().proofs();
+        let used_proofs = Proofs::with_proof(proofs[1].clone());
+
+        let mut mock_db = MockDatabase::new();
+        mock_db
+            .expect_get_used_proofs()
+            .returning(move || Ok(used_proofs.clone()));
+        let mint = create_mint_from_mocks(Some(mock_db), None);
+
+        let result = mint.check_spendable(&tokens.proofs()).await?;
+        assert_eq!(vec![true, false, true, true], result);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reissue_after_keyset_rotation() -> anyhow::Result<()> {
+        let mut mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        let old_keyset = mint.keyset.clone();
+
+        let proofs = create_proofs(&mint, &old_keyset, &[8, 2])?;
+
+        mint.rotate_keyset(MintKeyset::new("TEST_PRIVATE_KEY", "0/0/0/1"));
+        assert_ne!(old_keyset.keyset_id, mint.keyset.keyset_id);
+
+        let outputs = vec![8, 2]
+            .into_iter()
+            .map(|amount| {
+                let (b_, _) = mint
+                    .dhke
+                    .step1_alice(format!("new_secret_{amount}"), None)?;
+                Ok(BlindedMessage {
+                    amount,
+                    b_,
+                    id: None,
+                })
+            })
+            .collect::<anyhow::Result<Vec<BlindedMessage>>>()?;
+
+        let signatures = mint.reissue(&proofs, &outputs).await?;
+        assert_eq!(proofs.total_amount(), signatures.total_amount());
+        assert!(signatures
+            .iter()
+            .all(|sig| sig.id == Some(mint.keyset.keyset_id.clone())));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reissue_proofs_with_pre_nut02_keyset_id() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        let pre_nut02_keyset = mint.keyset.with_pre_nut02_id();
+        assert_ne!(pre_nut02_keyset.keyset_id, mint.keyset.keyset_id);
+
+        let proofs = create_proofs(&mint, &pre_nut02_keyset, &[8, 2])?;
+        let outputs = vec![8, 2]
+            .into_iter()
+            .map(|amount| {
+                let (b_, _) = mint
+                    .dhke
+                    .step1_alice(format!("new_secret_{amount}"), None)?;
+                Ok(BlindedMessage {
+                    amount,
+                    b_,
+                    id: None,
+                })
+            })
+            .collect::<anyhow::Result<Vec<BlindedMessage>>>()?;
+
+        let signatures = mint.reissue(&proofs, &outputs).await?;
+        assert_eq!(10, signatures.total_amount());
+        assert!(signatures
+            .iter()
+            .all(|sig| sig.id == Some(mint.keyset.keyset_id.clone())));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keysets_are_loaded_after_restart() -> anyhow::Result<()> {
+        let db = Arc::new(InMemoryDatabase::new());
+        let create_mint = |db: Arc<InMemoryDatabase>| {
+            Mint::new(
+                "TEST_PRIVATE_KEY".to_string(),
+                "".to_string(),
+                Arc::new(MockLightning::new()),
+                LightningType::Lnbits(Default::default()),
+                db,
+                Default::default(),
+                Default::default(),
+            )
+        };
+
+        let mut mint = create_mint(db.clone());
+        let initial_keyset_id = mint.keyset.keyset_id.clone();
+        mint.activate_keyset("0/0/1").await?;
+        let first_keyset_id = mint.keyset.keyset_id.clone();
+        mint.activate_keyset("0/0/2").await?;
+        let second_keyset_id = mint.keyset.keyset_id.clone();
+
+        let mut restarted_mint = create_mint(db);
+        restarted_mint.load_keysets().await?;
+
+        assert_eq!(second_keyset_id, restarted_mint.keyset.keyset_id);
+        let inactive_keyset_ids = restarted_mint
+            .inactive_keysets
+            .iter()
+            .map(|keyset| keyset.keyset_id.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            vec![initial_keyset_id, first_keyset_id],
+            inactive_keyset_ids
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keyset_migration_map() -> anyhow::Result<()> {
+        let mut mint = Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "".to_string(),
+            Arc::new(MockLightning::new()),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            Default::default(),
+            Default::default(),
+        );
+        let legacy_keyset_id = mint
+            .keyset_legacy
+            .as_ref()
+            .expect("legacy keyset is missing")
+            .keyset_id
+            .clone();
+        let keyset_id = mint.keyset.keyset_id.clone();
+        assert_ne!(legacy_keyset_id, keyset_id);
+        assert_eq!(
+            HashMap::from([(legacy_keyset_id.clone(), keyset_id.clone())]),
+            mint.keyset_migration_map()
         );
 
-        Ok(Mint::new(
-            self.private_key.expect("MINT_PRIVATE_KEY not set"),
-            "".to_string(),
-            ln,
-            self.lightning_type.expect("Lightning backend not set"),
-            db,
-            fee_config,
-            self.mint_info_settings.unwrap_or_default(),
-        ))
+        // the legacy keyset still maps to the keyset with the same keys after a rotation
+        mint.activate_keyset("0/0/1").await?;
+        assert_eq!(
+            Some(&keyset_id),
+            mint.keyset_migration_map().get(&legacy_keyset_id)
+        );
+        Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::lightning::error::LightningError;
-    use crate::lightning::{LightningType, MockLightning};
-    use crate::mint::Mint;
-    use crate::model::{Invoice, PayInvoiceResult};
-    use crate::{database::MockDatabase, error::MokshaMintError};
-    use moksha_core::blind::{BlindedMessage, TotalAmount};
-    use moksha_core::dhke;
-    use moksha_core::primitives::PostSplitRequest;
-    use moksha_core::proof::Proofs;
-    use moksha_core::token::TokenV3;
-    use std::str::FromStr;
-    use std::sync::Arc;
 
     #[test]
-    fn test_fee_reserve() -> anyhow::Result<()> {
-        let mint = create_mint_from_mocks(None, None);
-        let fee = mint.fee_reserve(10000);
-        assert_eq!(4000, fee);
+    fn test_startup_summary() -> anyhow::Result<()> {
+        let mint = Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "".to_string(),
+            Arc::new(MockLightning::new()),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            Default::default(),
+            Default::default(),
+        );
+
+        let summary = mint.startup_summary();
+        assert!(summary.contains(&format!("active keyset: {}", mint.keyset.keyset_id)));
+        assert!(summary.contains("denominations: 64 (powers-of-two)"));
+        assert!(summary.contains("lightning backend: Lnbits"));
+        assert!(summary.contains("database: memory"));
         Ok(())
     }
 
+    #[cfg(feature = "custom-lightning")]
     #[tokio::test]
-    async fn test_create_blindsignatures() -> anyhow::Result<()> {
-        let mint = create_mint_from_mocks(None, None);
+    async fn test_mint_with_custom_lightning() -> anyhow::Result<()> {
+        use crate::database::memory::MEMORY_DB_URL;
+        use crate::lightning::Lightning;
+        use crate::mint::MintBuilder;
+        use crate::model::CreateInvoiceResult;
+
+        /// backend that hands out a fixed invoice, which is always paid
+        struct PaidLightning;
+
+        #[async_trait::async_trait]
+        impl Lightning for PaidLightning {
+            async fn is_invoice_paid(&self, _invoice: String) -> Result<bool, MokshaMintError> {
+                Ok(true)
+            }
 
-        let blinded_messages = vec![BlindedMessage {
-            amount: 8,
-            b_: dhke::public_key_from_hex(
-                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
-            ),
-        }];
+            async fn create_invoice(
+                &self,
+                _amount: u64,
+            ) -> Result<CreateInvoiceResult, MokshaMintError> {
+                Ok(CreateInvoiceResult {
+                    payment_hash: vec![],
+                    payment_request: "lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv".to_string(),
+                })
+            }
 
-        let result = mint.create_blinded_signatures(&blinded_messages, &mint.keyset_legacy)?;
+            async fn pay_invoice(
+                &self,
+                _payment_request: String,
+            ) -> Result<PayInvoiceResult, MokshaMintError> {
+                Err(MokshaMintError::InvoiceNotPaidYet)
+            }
+        }
 
-        assert_eq!(1, result.len());
-        assert_eq!(8, result[0].amount);
-        assert_eq!(
-            dhke::public_key_from_hex(
-                "037074c4f53e326ee14ed67125f387d160e0e729351471b69ad41f7d5d21071e15"
-            ),
-            result[0].c_
-        );
+        let mint = MintBuilder::new()
+            .with_private_key("TEST_PRIVATE_KEY".to_string())
+            .with_db(MEMORY_DB_URL.to_string())
+            .with_lightning(LightningType::Custom(Arc::new(PaidLightning)))
+            .with_fee(0.0, 0)
+            .build()
+            .await?;
+        assert_eq!("Custom", mint.lightning_type.name());
+
+        let (_, key) = mint.create_invoice("key".to_string(), 40).await?;
+        let outputs = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
+        let result = mint.mint_tokens(key, &outputs).await?;
+        assert_eq!(40, result.total_amount());
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_mint_empty() -> anyhow::Result<()> {
+    async fn test_create_invoices() -> anyhow::Result<()> {
+        // 100 sat
+        let payment_request = "lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv";
         let mut lightning = MockLightning::new();
-        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
-        let mint = create_mint_from_mocks(Some(create_mock_mint()), Some(lightning));
+        lightning.expect_create_invoice().returning(move |_| {
+            Ok(CreateInvoiceResult {
+                payment_hash: vec![],
+                payment_request: payment_request.to_string(),
+            })
+        });
+        lightning.expect_decode_invoice().returning(|pr| {
+            lightning_invoice::Bolt11Invoice::from_str(&pr)
+                .map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+        let mint = Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "".to_string(),
+            Arc::new(lightning),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            Default::default(),
+            Default::default(),
+        );
 
-        let outputs = vec![];
-        let result = mint
-            .mint_tokens("somehash".to_string(), &outputs, &mint.keyset_legacy)
+        let quotes = mint
+            .create_invoices(&[10, 20, 30], &CurrencyUnit::Sat)
             .await?;
-        assert!(result.is_empty());
+        assert_eq!(3, quotes.len());
+        let quote_ids = quotes
+            .iter()
+            .map(|quote| quote.quote_id)
+            .collect::<HashSet<_>>();
+        assert_eq!(3, quote_ids.len());
+
+        for (quote, amount) in quotes.into_iter().zip([10, 20, 30]) {
+            assert_eq!(amount, quote.amount);
+            assert_eq!(
+                Invoice::new(amount, payment_request.to_string()),
+                mint.db
+                    .get_pending_invoice(quote.quote_id.to_string())
+                    .await?
+            );
+            assert_eq!(
+                quote.amount,
+                mint.db.get_bolt11_mint_quote(&quote.quote_id).await?.amount
+            );
+        }
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_mint_valid() -> anyhow::Result<()> {
+    async fn test_create_invoices_rejects_zero_amount() -> anyhow::Result<()> {
+        let mint = Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "".to_string(),
+            Arc::new(MockLightning::new()),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            Default::default(),
+            Default::default(),
+        );
+
+        let result = mint.create_invoices(&[10, 0], &CurrencyUnit::Sat).await;
+        assert!(matches!(result, Err(MokshaMintError::InvalidAmount)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_is_invoice_paid_is_cached() -> anyhow::Result<()> {
         let mut lightning = MockLightning::new();
-        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
-        let mint = create_mint_from_mocks(Some(create_mock_mint()), Some(lightning));
+        lightning
+            .expect_invoice_id()
+            .returning(|_| Ok("invoice_id".to_string()));
+        lightning
+            .expect_is_invoice_paid()
+            .times(1)
+            .returning(|_| Ok(true));
+        let mint = Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "".to_string(),
+            Arc::new(lightning),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            Default::default(),
+            Default::default(),
+        );
+        mint.db
+            .add_pending_invoice(
+                "key".to_string(),
+                &Invoice::new(100, "lnbcrt1u1...".to_string()),
+            )
+            .await?;
 
-        let outputs = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
-        let result = mint
-            .mint_tokens("somehash".to_string(), &outputs, &mint.keyset_legacy)
+        assert!(mint.is_invoice_paid("key").await?);
+        // the second check is answered from the stored flag
+        assert!(mint.is_invoice_paid("key").await?);
+        assert!(mint.db.get_pending_invoice("key".to_string()).await?.paid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pending_invoice() -> anyhow::Result<()> {
+        let mint = Mint::new(
+            "TEST_PRIVATE_KEY".to_string(),
+            "".to_string(),
+            Arc::new(MockLightning::new()),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            Default::default(),
+            Default::default(),
+        );
+        let invoice = Invoice::new(100, "lnbcrt1u1...".to_string());
+        mint.db
+            .add_pending_invoice("key".to_string(), &invoice)
             .await?;
-        assert_eq!(40, result.total_amount());
+
+        mint.cancel_pending_invoice("key".to_string()).await?;
+
+        let outputs = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
+        let result = mint.mint_tokens("key".to_string(), &outputs).await;
+        assert!(matches!(result, Err(MokshaMintError::InvoiceNotFound(_))));
+
+        let result = mint.cancel_pending_invoice("key".to_string()).await;
+        assert!(matches!(result, Err(MokshaMintError::InvoiceNotFound(_))));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_split_zero() -> anyhow::Result<()> {
-        let blinded_messages = vec![];
+    async fn test_reissue_invalid_proof() -> anyhow::Result<()> {
         let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+        let other_keyset = MintKeyset::new("OTHER_PRIVATE_KEY", "0/0/0/0");
+
+        // signed by a keyset the mint doesn't know, but claiming to be from the active one
+        let proofs = create_proofs(&mint, &other_keyset, &[8])?.proofs();
+        let proofs = Proofs::new(
+            proofs
+                .into_iter()
+                .map(|mut proof| {
+                    proof.keyset_id = mint.keyset.keyset_id.clone();
+                    proof
+                })
+                .collect(),
+        );
 
-        let proofs = Proofs::empty();
-        let result = mint
-            .swap(&proofs, &blinded_messages, &mint.keyset_legacy)
-            .await?;
+        let result = mint.reissue(&proofs, &[]).await;
+        assert!(matches!(result, Err(MokshaMintError::InvalidProof(_))));
+        Ok(())
+    }
 
-        assert!(result.is_empty());
+    #[tokio::test]
+    async fn test_reissue_proof_with_script() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[8])?.proofs();
+        let proofs = Proofs::new(
+            proofs
+                .into_iter()
+                .map(|mut proof| {
+                    proof.script = Some(P2SHScript {
+                        script: "OTXB1yYTvUpkLDjBkJRgSaGeYzS7WG2Jn0pV0ex5pHI=".to_string(),
+                        signature: "MEQCIBsz8vZ...".to_string(),
+                        address: None,
+                    });
+                    proof
+                })
+                .collect(),
+        );
+
+        let result = mint.reissue(&proofs, &[]).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::UnsupportedProofFeature(_, _))
+        ));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_split_64_in_20() -> anyhow::Result<()> {
+    async fn test_reissue_proof_amount_key_mismatch() -> anyhow::Result<()> {
         let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
-        let request = create_request_from_fixture("post_split_request_64_20.json".to_string())?;
 
-        let result = mint
-            .swap(&request.proofs, &request.outputs, &mint.keyset_legacy)
-            .await?;
-        assert_eq!(result.total_amount(), 64);
+        // signed with the key for amount 1, but claiming an amount of 64
+        let proofs = create_proofs(&mint, &mint.keyset, &[1])?.proofs();
+        let proofs = Proofs::new(
+            proofs
+                .into_iter()
+                .map(|mut proof| {
+                    proof.amount = 64;
+                    proof
+                })
+                .collect(),
+        );
 
-        let prv_lst = result.get(result.len() - 2).unwrap();
-        let lst = result.last().unwrap();
+        let result = mint.reissue(&proofs, &[]).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::ProofAmountKeyMismatch(_, 64, 1))
+        ));
+        Ok(())
+    }
 
-        assert_eq!(prv_lst.amount, 4);
-        assert_eq!(lst.amount, 16);
+    fn create_proofs(mint: &Mint, keyset: &MintKeyset, amounts: &[u64]) -> anyhow::Result<Proofs> {
+        let proofs = amounts
+            .iter()
+            .map(|amount| {
+                let secret = format!("secret_{amount}");
+                let (b_, r) = mint.dhke.step1_alice(secret.clone(), None)?;
+                let c_ = mint.dhke.step2_bob(b_, &keyset.private_keys[amount])?;
+                let c = mint.dhke.step3_alice(c_, r, keyset.public_keys[amount])?;
+                Ok(Proof::new(*amount, secret, c, keyset.keyset_id.clone()))
+            })
+            .collect::<anyhow::Result<Vec<Proof>>>()?;
+        Ok(Proofs::new(proofs))
+    }
+
+    #[test]
+    fn test_verify_proofs_amount_not_in_keyset() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(None, None);
+        let mut proof = create_proofs(&mint, &mint.keyset, &[1])?.proofs()[0].clone();
+        // the keyset only has keys for powers of two
+        proof.amount = 3;
+
+        let result = mint.verify_proofs(&Proofs::new(vec![proof]));
+        assert!(matches!(result, Err(MokshaMintError::InvalidAmount)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_proofs_batch_matches_single_proofs() -> anyhow::Result<()> {
+        let mint = create_mint_from_mocks(None, None);
+        let keyset = &mint.keyset;
+
+        let proofs = (0..200u64)
+            .map(|i| {
+                let amount = 1 << (i % 10);
+                let secret = format!("secret_{i}");
+                let (b_, r) = mint.dhke.step1_alice(secret.clone(), None)?;
+                let c_ = mint.dhke.step2_bob(b_, &keyset.private_keys[&amount])?;
+                let c = mint.dhke.step3_alice(c_, r, keyset.public_keys[&amount])?;
+                Ok(Proof::new(amount, secret, c, keyset.keyset_id.clone()))
+            })
+            .collect::<anyhow::Result<Vec<Proof>>>()?;
+        let proofs = Proofs::new(proofs);
+        assert!(proofs
+            .proofs()
+            .iter()
+            .all(|proof| mint.verify_proof(keyset, proof).is_ok()));
+        assert!(mint.verify_proofs(&proofs).is_ok());
+
+        // changing the secret invalidates the signature
+        let tampered = Proofs::new(
+            proofs
+                .proofs()
+                .into_iter()
+                .enumerate()
+                .map(|(i, mut proof)| {
+                    if i % 50 == 42 {
+                        proof.secret = format!("tampered_{i}");
+                    }
+                    proof
+                })
+                .collect(),
+        );
+        let single_results = tampered
+            .proofs()
+            .iter()
+            .map(|proof| mint.verify_proof(keyset, proof).map_err(|e| e.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(4, single_results.iter().filter(|r| r.is_err()).count());
+        assert_eq!(
+            single_results.into_iter().find_map(Result::err),
+            mint.verify_proofs(&tampered).err().map(|e| e.to_string())
+        );
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_split_duplicate_key() -> anyhow::Result<()> {
-        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), None);
-        let request =
-            create_request_from_fixture("post_split_request_duplicate_key.json".to_string())?;
+    async fn test_execute_melt_rejects_changed_fee_reserve() -> anyhow::Result<()> {
+        let mint = create_melt_quote_mint();
+        let quote = create_melt_quote(&mint, 100, 4).await?;
+
+        // the fee reserve was raised after the wallet created its proofs for 100 + 4 sats
+        mint.db
+            .update_bolt11_melt_quote(&Bolt11MeltQuote {
+                fee_reserve: 10,
+                ..quote.clone()
+            })
+            .await?;
 
+        let proofs = create_proofs(&mint, &mint.keyset, &[64, 32, 8])?;
         let result = mint
-            .swap(&request.proofs, &request.outputs, &mint.keyset_legacy)
+            .execute_melt(&quote.quote_id.to_string(), &proofs, &[], None)
             .await;
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::MeltQuoteMismatch(_, _))
+        ));
         Ok(())
     }
 
     #[tokio::test]
-    /// melt 20 sats with 60 tokens and receive 40 tokens as change
-    async fn test_melt_overpay() -> anyhow::Result<()> {
-        use lightning_invoice::Bolt11Invoice as LNInvoice;
+    async fn test_execute_melt_rejects_altered_amount() -> anyhow::Result<()> {
+        let mint = create_melt_quote_mint();
+        // the invoice of the quote is for 100 sats
+        let quote = create_melt_quote(&mint, 50, 4).await?;
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[64, 32, 8])?;
+        let result = mint
+            .execute_melt(&quote.quote_id.to_string(), &proofs, &[], None)
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::MeltQuoteMismatch(_, _))
+        ));
+        Ok(())
+    }
 
+    #[tokio::test]
+    async fn test_execute_melt_rejects_fee_above_maximum() -> anyhow::Result<()> {
         let mut lightning = MockLightning::new();
+        lightning.expect_pay_invoice().never();
+        let mint = Mint {
+            lightning: Arc::new(lightning),
+            ..create_melt_quote_mint()
+        };
+        let quote = create_melt_quote(&mint, 100, 4).await?;
 
-        lightning.expect_decode_invoice().returning(|_| {
-            Ok(
-                // 20 sat
-                LNInvoice::from_str("lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40").expect("invalid invoice")
-            )
-        });
-        lightning.expect_pay_invoice().returning(|_| {
-            Ok(PayInvoiceResult {
-                payment_hash: "hash".to_string(),
-                total_fees: 0,
-            })
-            .map_err(|_err: LightningError| MokshaMintError::InvoiceNotFound("".to_string()))
+        let proofs = create_proofs(&mint, &mint.keyset, &[64, 32, 8])?;
+        let result = mint
+            .execute_melt(&quote.quote_id.to_string(), &proofs, &[], Some(3))
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::FeeExceedsMaximum(4, 3))
+        ));
+        assert!(!mint.db.get_bolt11_melt_quote(&quote.quote_id).await?.paid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_melt_failed_payment_keeps_quote_unpaid() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning.expect_decode_invoice().returning(|pr| {
+            lightning_invoice::Bolt11Invoice::from_str(&pr)
+                .map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
         });
+        lightning
+            .expect_pay_invoice()
+            .returning(|_| Err(MokshaMintError::LightningTimeout));
+        let mint = Mint {
+            lightning: Arc::new(lightning),
+            ..create_melt_quote_mint()
+        };
+        let quote = create_melt_quote(&mint, 100, 4).await?;
 
-        let mint = Mint::new(
+        let proofs = create_proofs(&mint, &mint.keyset, &[64, 32, 8])?;
+        let result = mint
+            .execute_melt(&quote.quote_id.to_string(), &proofs, &[], None)
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::LightningTimeout)));
+        assert!(!mint.db.get_bolt11_melt_quote(&quote.quote_id).await?.paid);
+        assert_eq!(vec![true, true, true], mint.check_spendable(&proofs).await?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_expiry_uses_default() -> anyhow::Result<()> {
+        let mint = create_melt_quote_mint();
+        // the invoice expires in 2123
+        let invoice = LNInvoice::from_str("lnbcrt5u1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxq8zals8sqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssq2psnxdza6tl2yhk4rj9z5pj6vv9l9e53ve4645hj0zcn8e5t64q566xj0928r6ln6nw4n5ey9f43r2jarxmzst85qpf42k4s22n55cgqgjrc6w")?;
+
+        let before = Utc::now().timestamp() as u64;
+        let expiry = mint.quote_expiry(&invoice);
+        let after = Utc::now().timestamp() as u64;
+        assert!(expiry >= before + DEFAULT_QUOTE_EXPIRY_SECS);
+        assert!(expiry <= after + DEFAULT_QUOTE_EXPIRY_SECS);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_expiry_capped_by_invoice() -> anyhow::Result<()> {
+        let mint = create_melt_quote_mint();
+        // created at 1686989847 with an expiry of one day
+        let invoice = LNInvoice::from_str("lnbcrt200n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqwgzyh9s8m5h45t790jejvyjlfrf0uv645sxwd5pj6nwm55mxy0c52np3z8xwcuy6umft9hjaxa0u6yfe32y99alcrjfv3s3j4e0f0lgq3a7fvq")?;
+
+        assert_eq!(1_686_989_847 + 86_400, mint.quote_expiry(&invoice));
+        Ok(())
+    }
+
+    fn create_melt_quote_mint() -> Mint {
+        let mut lightning = MockLightning::new();
+        lightning.expect_decode_invoice().returning(|pr| {
+            lightning_invoice::Bolt11Invoice::from_str(&pr)
+                .map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+        Mint::new(
             "TEST_PRIVATE_KEY".to_string(),
-            "0/0/0/0".to_string(),
+            "".to_string(),
             Arc::new(lightning),
             LightningType::Lnbits(Default::default()),
-            Arc::new(create_mock_db_get_used_proofs()),
+            Arc::new(InMemoryDatabase::new()),
             Default::default(),
             Default::default(),
-        );
+        )
+    }
 
-        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
-        let invoice = "some invoice".to_string();
+    async fn create_melt_quote(
+        mint: &Mint,
+        amount: u64,
+        fee_reserve: u64,
+    ) -> anyhow::Result<Bolt11MeltQuote> {
+        // 100 sat
+        let quote = Bolt11MeltQuote {
+            quote_id: uuid::Uuid::new_v4(),
+            amount,
+            fee_reserve,
+            payment_request: "lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv".to_string(),
+            expiry: 0,
+            paid: false,
+        };
+        mint.db.add_bolt11_melt_quote(&quote).await?;
+        Ok(quote)
+    }
+
+    #[tokio::test]
+    async fn test_melt_onchain() -> anyhow::Result<()> {
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_pay_onchain()
+            .withf(move |addr, amount| addr == address && *amount == 20)
+            .returning(|_, _| Ok("txid".to_string()));
+        let mint = create_mint_from_mocks(Some(create_mock_db_get_used_proofs()), Some(lightning));
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[32, 16, 8, 4])?;
         let change = create_blinded_msgs_from_fixture("blinded_messages_40.json".to_string())?;
 
-        let (paid, _payment_hash, change) = mint
-            .melt(invoice, &tokens.proofs(), &change, &mint.keyset_legacy)
+        let (txid, change) = mint
+            .melt_onchain(address.to_string(), 20, &proofs, &change)
             .await?;
 
-        assert!(paid);
-        assert!(change.total_amount() == 40);
+        assert_eq!("txid", txid);
+        assert_eq!(40, change.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_onchain_not_supported() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_pay_onchain()
+            .returning(|_, _| Err(MokshaMintError::OnchainNotSupported));
+        let mut mock_db = create_mock_db_get_used_proofs();
+        // the proofs are released again, because the payment failed
+        mock_db
+            .expect_delete_used_proofs()
+            .times(1)
+            .returning(|_| Ok(()));
+        let mint = create_mint_from_mocks(Some(mock_db), Some(lightning));
+
+        let proofs = create_proofs(&mint, &mint.keyset, &[32, 16, 8, 4])?;
+        let result = mint
+            .melt_onchain("bc1q...".to_string(), 20, &proofs, &[])
+            .await;
+
+        assert!(matches!(result, Err(MokshaMintError::OnchainNotSupported)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_onchain_rejects_invalid_proofs() -> anyhow::Result<()> {
+        let mut lightning = MockLightning::new();
+        lightning.expect_pay_onchain().never();
+        let mut mock_db = MockDatabase::new();
+        mock_db.expect_add_used_proofs().never();
+        let mint = create_mint_from_mocks(Some(mock_db), Some(lightning));
+
+        // the proofs of the fixture are signed by another mint
+        let tokens = create_token_from_fixture("token_60.cashu".to_string())?;
+        let result = mint
+            .melt_onchain("bc1q...".to_string(), 20, &tokens.proofs(), &[])
+            .await;
+
+        assert!(matches!(result, Err(MokshaMintError::UnknownKeyset(_))));
         Ok(())
     }
 
@@ -507,7 +3176,11 @@ mod tests {
 
         let lightning = match mock_ln {
             Some(ln) => Arc::new(ln),
-            None => Arc::new(MockLightning::new()),
+            None => {
+                let mut ln = MockLightning::new();
+                ln.expect_min_fee_reserve().returning(|| MSat(0));
+                Arc::new(ln)
+            }
         };
 
         //let lightning = Arc::new(MockLightning::new());
@@ -528,6 +3201,7 @@ mod tests {
             .expect_get_used_proofs()
             .returning(|| Ok(Proofs::empty()));
         mock_db.expect_add_used_proofs().returning(|_| Ok(()));
+        mock_db.expect_is_blacklisted().returning(|_| Ok(false));
         mock_db
     }
 
@@ -538,6 +3212,7 @@ mod tests {
         let invoice = Invoice{
             amount: 100,
             payment_request: "lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv".to_string(),            
+            paid: false,
         };
         mock_db
             .expect_get_used_proofs()
@@ -548,7 +3223,11 @@ mod tests {
         mock_db
             .expect_get_pending_invoice()
             .returning(move |_| Ok(invoice.clone()));
+        mock_db
+            .expect_is_invoice_marked_paid()
+            .returning(|_| Ok(false));
         mock_db.expect_add_used_proofs().returning(|_| Ok(()));
+        mock_db.expect_is_blacklisted().returning(|_| Ok(false));
         mock_db
     }
 }
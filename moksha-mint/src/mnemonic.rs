@@ -0,0 +1,163 @@
+//! Derives the secret of the mint from a [BIP39](https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki)
+//! mnemonic, so operators can back up a mnemonic instead of the raw secret.
+
+use bip39::Mnemonic;
+use bitcoin_hashes::{hmac, sha512, Hash, HashEngine};
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+
+use crate::error::MokshaMintError;
+
+/// derivation path that is used if `MINT_MNEMONIC_DERIVATION_PATH` is not set
+pub const DEFAULT_MNEMONIC_DERIVATION_PATH: &str = "m/129372'/0'";
+
+/// child indices from this value on are hardened
+const HARDENED_INDEX: u32 = 1 << 31;
+
+/// Returns the secret of the mint for the mnemonic and derivation path. The secret is the hex
+/// encoded private key that is derived from the BIP39 seed of the mnemonic along the
+/// [BIP32](https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki) path, so the same
+/// mnemonic and path always result in the same keysets.
+pub fn secret_from_mnemonic(
+    mnemonic: &str,
+    derivation_path: &str,
+) -> Result<String, MokshaMintError> {
+    let indices = parse_derivation_path(derivation_path)?;
+    let mnemonic = Mnemonic::parse_normalized(mnemonic.trim())
+        .map_err(|err| MokshaMintError::InvalidMnemonic(err.to_string()))?;
+    let seed = mnemonic.to_seed_normalized("");
+
+    let secp = Secp256k1::new();
+    let invalid_key = || MokshaMintError::InvalidDerivationPath(derivation_path.to_owned());
+    let (mut key, mut chain_code) = split_hmac(b"Bitcoin seed", &seed);
+    let mut private_key = SecretKey::from_slice(&key).map_err(|_| invalid_key())?;
+    for index in indices {
+        let mut data = match index >= HARDENED_INDEX {
+            true => [&[0u8][..], &private_key.secret_bytes()[..]].concat(),
+            false => PublicKey::from_secret_key(&secp, &private_key)
+                .serialize()
+                .to_vec(),
+        };
+        data.extend_from_slice(&index.to_be_bytes());
+
+        (key, chain_code) = split_hmac(&chain_code, &data);
+        let tweak = Scalar::from_be_bytes(key).map_err(|_| invalid_key())?;
+        private_key = private_key.add_tweak(&tweak).map_err(|_| invalid_key())?;
+    }
+    Ok(hex::encode(private_key.secret_bytes()))
+}
+
+/// Parses a BIP32 path like `m/129372'/0'` into the child indices. Hardened indices are marked
+/// with `'` or `h`.
+fn parse_derivation_path(derivation_path: &str) -> Result<Vec<u32>, MokshaMintError> {
+    let invalid = || MokshaMintError::InvalidDerivationPath(derivation_path.to_owned());
+    let mut components = derivation_path.trim().split('/');
+    if components.next() != Some("m") {
+        return Err(invalid());
+    }
+
+    components
+        .map(|component| {
+            let (index, hardened) = match component
+                .strip_suffix('\'')
+                .or_else(|| component.strip_suffix('h'))
+            {
+                Some(index) => (index, true),
+                None => (component, false),
+            };
+            if index.is_empty() || !index.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            match index.parse::<u32>() {
+                Ok(index) if index < HARDENED_INDEX => Ok(match hardened {
+                    true => index + HARDENED_INDEX,
+                    false => index,
+                }),
+                _ => Err(invalid()),
+            }
+        })
+        .collect()
+}
+
+/// Returns the two halves of the HMAC-SHA512 of the data
+fn split_hmac(key: &[u8], data: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let mut engine = hmac::HmacEngine::<sha512::Hash>::new(key);
+    engine.input(data);
+    let hmac = hmac::Hmac::<sha512::Hash>::from_engine(engine).to_byte_array();
+
+    let mut left = [0u8; 32];
+    let mut right = [0u8; 32];
+    left.copy_from_slice(&hmac[..32]);
+    right.copy_from_slice(&hmac[32..]);
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::keyset::MintKeyset;
+
+    use super::{parse_derivation_path, secret_from_mnemonic, DEFAULT_MNEMONIC_DERIVATION_PATH};
+    use crate::error::MokshaMintError;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_secret_from_mnemonic() -> anyhow::Result<()> {
+        let secret = secret_from_mnemonic(MNEMONIC, DEFAULT_MNEMONIC_DERIVATION_PATH)?;
+        assert_eq!(
+            "4453a3b0cfdb4b76ee387abb190ac205fd362e54e85252fb9e002df2d40cfeea",
+            secret
+        );
+
+        // the keyset id only depends on the mnemonic and the path
+        let keyset_id = MintKeyset::new(&secret, "").keyset_id;
+        let secret_again = secret_from_mnemonic(MNEMONIC, DEFAULT_MNEMONIC_DERIVATION_PATH)?;
+        assert_eq!(keyset_id, MintKeyset::new(&secret_again, "").keyset_id);
+
+        let other_path = secret_from_mnemonic(MNEMONIC, "m/129372'/1'")?;
+        assert_ne!(keyset_id, MintKeyset::new(&other_path, "").keyset_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_derivation_path() -> anyhow::Result<()> {
+        assert_eq!(
+            vec![0x8000_0000, 1, 0x8000_0002],
+            parse_derivation_path("m/0'/1/2h")?
+        );
+        assert!(parse_derivation_path("m")?.is_empty());
+
+        for path in [
+            "",
+            "129372'/0'",
+            "m/129372''",
+            "m/-1",
+            "m//0",
+            "m/2147483648",
+            "m/0x1",
+        ] {
+            let result = parse_derivation_path(path);
+            assert!(
+                matches!(result, Err(MokshaMintError::InvalidDerivationPath(_))),
+                "{path}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_secret_from_invalid_derivation_path() -> anyhow::Result<()> {
+        let result = secret_from_mnemonic(MNEMONIC, "m/129372'/0'/");
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvalidDerivationPath(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_secret_from_invalid_mnemonic() -> anyhow::Result<()> {
+        let result = secret_from_mnemonic("abandon abandon", DEFAULT_MNEMONIC_DERIVATION_PATH);
+        assert!(matches!(result, Err(MokshaMintError::InvalidMnemonic(_))));
+        Ok(())
+    }
+}
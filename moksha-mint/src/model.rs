@@ -1,5 +1,61 @@
 use serde::{Deserialize, Serialize};
 
+/// An amount in satoshis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Sat(pub u64);
+
+/// An amount in millisatoshis. All conversions between msat and sat go through [`MSat`] and
+/// [`Sat`], so the rounding is explicit: amounts the mint has to cover, like fee reserves and
+/// paid fees, are rounded up, so the mint never under-reserves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MSat(pub u64);
+
+impl MSat {
+    /// Converts to satoshis, rounding up so a sub-sat remainder is never lost
+    pub fn to_sat_ceil(self) -> Sat {
+        Sat(self.0.div_ceil(1_000))
+    }
+
+    /// Converts to satoshis, dropping a sub-sat remainder. Only use this for amounts the mint
+    /// pays out, e.g. the part of an amount that can be issued as ecash.
+    pub fn to_sat_floor(self) -> Sat {
+        Sat(self.0 / 1_000)
+    }
+}
+
+impl Sat {
+    pub fn to_msat(self) -> MSat {
+        MSat(self.0 * 1_000)
+    }
+}
+
+impl From<Sat> for MSat {
+    fn from(sat: Sat) -> Self {
+        sat.to_msat()
+    }
+}
+
+impl std::fmt::Display for Sat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} sat", self.0)
+    }
+}
+
+impl std::fmt::Display for MSat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} msat", self.0)
+    }
+}
+
+/// Amount, fee reserve and expiry of a melt quote for an invoice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MeltQuoteInfo {
+    pub amount_sat: u64,
+    pub fee_reserve: u64,
+    /// unix timestamp in seconds
+    pub expiry: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetMintQuery {
     pub amount: u64,
@@ -10,10 +66,19 @@ pub struct PostMintQuery {
     pub hash: String,
 }
 
+/// Body of the admin request that adds the secret of a compromised proof to the blacklist
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostBlacklistRequest {
+    pub secret: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Invoice {
     pub amount: u64,
     pub payment_request: String,
+    /// set once the invoice has been confirmed paid, so it doesn't have to be checked again
+    #[serde(default)]
+    pub paid: bool,
 }
 
 impl Invoice {
@@ -21,10 +86,21 @@ impl Invoice {
         Self {
             amount,
             payment_request,
+            paid: false,
         }
     }
 }
 
+/// A keyset that has been activated by the mint. Only the derivation path is stored, the keys are
+/// derived again from the mint seed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeysetInfo {
+    pub keyset_id: String,
+    pub derivation_path: String,
+    /// unix timestamp in seconds
+    pub activated_at: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateInvoiceResult {
     pub payment_hash: Vec<u8>,
@@ -34,10 +110,19 @@ pub struct CreateInvoiceResult {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PayInvoiceResult {
     pub payment_hash: String,
+    /// hex encoded preimage of the payment hash, proves that the invoice was paid
+    pub payment_preimage: String,
     /// total fees in msat
     pub total_fees: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct WebhookEvent {
+    /// id of the invoice at the lightning backend
+    pub invoice_id: String,
+    pub paid: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateInvoiceParams {
     pub amount: u64,
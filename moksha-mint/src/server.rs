@@ -1,35 +1,46 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
+use crate::cache::{ResponseCache, CACHED_PATHS};
 use crate::error::MokshaMintError;
-use axum::extract::{Path, Query, Request, State};
-use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::body::Body;
+use axum::extract::{DefaultBodyLimit, Path, Query, Request, State};
+use axum::http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    HeaderMap, HeaderName, HeaderValue, StatusCode,
+};
 use axum::middleware::Next;
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get_service, post};
 use axum::{middleware, Router};
 use axum::{routing::get, Json};
-use chrono::{Duration, Utc};
 use moksha_core::keyset::{generate_hash, Keysets, V1Keyset, V1Keysets};
 use moksha_core::proof::Proofs;
-use moksha_core::proof::{P2SHScript, Proof};
+use moksha_core::proof::{AuthProof, P2SHScript, Proof};
 use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
+use crate::lightning::{constant_time_eq, LightningType};
 use crate::mint::Mint;
-use crate::model::{GetMintQuery, PostMintQuery};
+use crate::model::{GetMintQuery, PostBlacklistRequest, PostMintQuery};
+use crate::validation::ValidatedJson;
 use moksha_core::blind::BlindedMessage;
 use moksha_core::blind::BlindedSignature;
 use moksha_core::primitives::{
-    Bolt11MeltQuote, Bolt11MintQuote, CheckFeesRequest, CheckFeesResponse, CurrencyUnit,
-    KeyResponse, KeysResponse, MintInfoResponse, MintLegacyInfoResponse, Nut10, Nut11, Nut12, Nut4,
-    Nut5, Nut6, Nut7, Nut8, Nut9, Nuts, PaymentMethod, PaymentRequest, PostMeltBolt11Request,
+    Bolt11MeltQuote, Bolt11MintQuote, CachedEndpoint, CheckFeesRequest, CheckFeesResponse,
+    CheckSpendableRequest, CheckSpendableResponse, CurrencyUnit, KeyResponse, KeysResponse,
+    MintInfoResponse, MintLegacyInfoResponse, MintVersionResponse, Nut10, Nut11, Nut12, Nut19,
+    Nut4, Nut5, Nut6, Nut7, Nut8, Nut9, Nuts, PaymentMethod, PaymentRequest,
+    PostAuthBlindMintRequest, PostAuthBlindMintResponse, PostMeltBolt11Request,
     PostMeltBolt11Response, PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response,
     PostMeltRequest, PostMeltResponse, PostMintBolt11Request, PostMintBolt11Response,
-    PostMintQuoteBolt11Request, PostMintQuoteBolt11Response, PostMintRequest, PostMintResponse,
-    PostSplitRequest, PostSplitResponse, PostSwapRequest, PostSwapResponse,
+    PostMintQuoteBolt11Request, PostMintQuoteBolt11Response, PostMintQuotesBolt11Request,
+    PostMintQuotesBolt11Response, PostMintRequest, PostMintResponse, PostSplitRequest,
+    PostSplitResponse, PostSwapRequest, PostSwapResponse,
 };
 use secp256k1::PublicKey;
 
@@ -37,6 +48,7 @@ use tower_http::services::ServeDir;
 
 use tower_http::{
     cors::{Any, CorsLayer},
+    limit::RequestBodyLimitLayer,
     trace::TraceLayer,
 };
 use tracing::{event, info, Level};
@@ -51,11 +63,34 @@ pub async fn run_server(
     addr: SocketAddr,
     serve_wallet_path: Option<PathBuf>,
     api_prefix: Option<String>,
+    base_path: Option<String>,
+) -> anyhow::Result<()> {
+    run_server_with_shutdown(
+        mint,
+        addr,
+        serve_wallet_path,
+        api_prefix,
+        base_path,
+        shutdown_signal(),
+    )
+    .await
+}
+
+/// Runs the server until the shutdown future completes. In-flight requests are drained before
+/// the database connections are closed.
+pub async fn run_server_with_shutdown(
+    mint: Mint,
+    addr: SocketAddr,
+    serve_wallet_path: Option<PathBuf>,
+    api_prefix: Option<String>,
+    base_path: Option<String>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
 ) -> anyhow::Result<()> {
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
         .init();
     info!("listening on: {}", addr);
+    info!("{}", mint.startup_summary());
     info!("mint_info: {:?}", mint.mint_info);
     info!("lightning_backend: {}", mint.lightning_type);
     if serve_wallet_path.is_some() {
@@ -66,10 +101,11 @@ pub async fn run_server(
     }
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    let db = mint.db.clone();
 
     axum::serve(
         listener,
-        app(mint, serve_wallet_path, api_prefix)
+        with_base_path(app(mint, serve_wallet_path, api_prefix), base_path)
             .layer(
                 CorsLayer::new()
                     .allow_origin(Any)
@@ -78,11 +114,40 @@ pub async fn run_server(
             )
             .into_make_service(),
     )
+    .with_graceful_shutdown(shutdown)
     .await?;
 
+    info!("server stopped, closing database connections");
+    db.close().await;
     Ok(())
 }
 
+/// Completes on SIGINT or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("received shutdown signal");
+}
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
@@ -91,16 +156,20 @@ pub async fn run_server(
         get_keysets,
         post_mint_bolt11,
         post_mint_quote_bolt11,
+        post_mint_quotes_bolt11,
         get_mint_quote_bolt11,
         post_melt_bolt11,
         post_melt_quote_bolt11,
         get_melt_quote_bolt11,
         post_swap,
+        get_auth_blind_keys,
         get_info,
+        get_version,
         get_health,
     ),
     components(schemas(
         MintInfoResponse,
+        MintVersionResponse,
         Nuts,
         Nut4,
         Nut5,
@@ -111,6 +180,8 @@ pub async fn run_server(
         Nut10,
         Nut11,
         Nut12,
+        Nut19,
+        CachedEndpoint,
         CurrencyUnit,
         PaymentMethod,
         KeysResponse,
@@ -123,6 +194,8 @@ pub async fn run_server(
         Proofs,
         PostMintQuoteBolt11Request,
         PostMintQuoteBolt11Response,
+        PostMintQuotesBolt11Request,
+        PostMintQuotesBolt11Response,
         PostMeltQuoteBolt11Request,
         PostMeltQuoteBolt11Response,
         PostMeltBolt11Request,
@@ -137,38 +210,96 @@ pub async fn run_server(
 struct ApiDoc;
 
 fn app(mint: Mint, serve_wallet_path: Option<PathBuf>, prefix: Option<String>) -> Router {
+    // the mint is shared by all handlers instead of being cloned for every request
+    let mint = Arc::new(mint);
+    // minting, melting and swapping require a blind auth proof if auth is enabled
+    let auth = middleware::from_fn_with_state(mint.clone(), require_auth);
+    // retried mint, melt and swap requests get the cached response (NUT-19). The cache wraps
+    // the auth check, so a retry doesn't need a new auth proof.
+    let cache = middleware::from_fn_with_state(mint.clone(), cache_response);
+
     let legacy_routes = Router::new()
         .route("/keys", get(get_legacy_keys))
         .route("/keysets", get(get_legacy_keysets))
-        .route("/mint", get(get_legacy_mint).post(post_legacy_mint))
+        .route(
+            "/mint",
+            get(get_legacy_mint).merge(post(post_legacy_mint).route_layer(auth.clone())),
+        )
         .route("/checkfees", post(post_legacy_check_fees))
-        .route("/melt", post(post_legacy_melt))
-        .route("/split", post(post_legacy_split))
+        .route("/check", post(post_legacy_check_spendable))
+        .route("/melt", post(post_legacy_melt).route_layer(auth.clone()))
+        .route("/split", post(post_legacy_split).route_layer(auth.clone()))
         .route("/info", get(get_legacy_info));
 
+    // the admin token is used as clear auth for issuing blind auth proofs
+    let admin_routes = Router::new()
+        .route("/v1/admin/proofs", get(get_admin_used_proofs))
+        .route(
+            "/v1/admin/keysets/migration",
+            get(get_admin_keyset_migration),
+        )
+        .route(
+            "/v1/admin/invoice/:key/cancel",
+            post(post_admin_cancel_invoice),
+        )
+        .route("/v1/admin/blacklist", post(post_admin_blacklist))
+        .route("/v1/auth/blind/mint", post(post_auth_blind_mint))
+        .route_layer(middleware::from_fn_with_state(
+            mint.clone(),
+            require_admin_token,
+        ));
+
     let routes = Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .route("/v1/keys", get(get_keys))
         .route("/v1/keys/:id", get(get_keys_by_id))
         .route("/v1/keysets", get(get_keysets))
         .route("/v1/mint/quote/bolt11", post(post_mint_quote_bolt11))
+        .route("/v1/mint/quotes/bolt11", post(post_mint_quotes_bolt11))
         .route("/v1/mint/quote/bolt11/:quote", get(get_mint_quote_bolt11))
-        .route("/v1/mint/bolt11", post(post_mint_bolt11))
+        .route(
+            "/v1/mint/bolt11",
+            post(post_mint_bolt11)
+                .route_layer(auth.clone())
+                .route_layer(cache.clone()),
+        )
         .route("/v1/melt/quote/bolt11", post(post_melt_quote_bolt11))
         .route("/v1/melt/quote/bolt11/:quote", get(get_melt_quote_bolt11))
-        .route("/v1/melt/bolt11", post(post_melt_bolt11))
-        .route("/v1/swap", post(post_swap))
-        .route("/v1/info", get(get_info));
+        .route(
+            "/v1/melt/bolt11",
+            post(post_melt_bolt11)
+                .route_layer(auth.clone())
+                .route_layer(cache.clone()),
+        )
+        .route(
+            "/v1/swap",
+            post(post_swap).route_layer(auth).route_layer(cache),
+        )
+        .route("/v1/auth/blind/keys", get(get_auth_blind_keys))
+        .route("/v1/info", get(get_info))
+        .route("/v1/version", get(get_version))
+        .route("/webhook/:backend", post(post_webhook))
+        .merge(admin_routes);
 
     let general_routes = Router::new().route("/health", get(get_health));
 
     let prefix = prefix.unwrap_or_else(|| "".to_owned());
 
-    let router = Router::new()
-        .nest(&prefix, legacy_routes)
+    // the legacy routes are only served if the mint has a legacy keyset
+    let router = if mint.keyset_legacy.is_some() {
+        Router::new().nest(&prefix, legacy_routes)
+    } else {
+        Router::new()
+    };
+
+    // the limit applies to all routes, so axum's default limit for extractors is replaced
+    let max_body_bytes = mint.max_body_bytes;
+    let router = router
         .nest(&prefix, routes)
         .nest("", general_routes)
         .with_state(mint)
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_body_bytes))
         .layer(TraceLayer::new_for_http());
 
     if let Some(serve_wallet_path) = serve_wallet_path {
@@ -181,6 +312,21 @@ fn app(mint: Mint, serve_wallet_path: Option<PathBuf>, prefix: Option<String>) -
     router
 }
 
+/// Mounts all routes under the given base path, e.g. `/mint-a` serves `/v1/keys` as
+/// `/mint-a/v1/keys`. This is useful if several mints are served behind the same reverse proxy.
+/// Unlike the api prefix, the base path also applies to the health check and the served wallet.
+/// Both combine as `<base path><api prefix>/v1/keys`, e.g. `/mint-a/api/v1/keys`.
+fn with_base_path(router: Router, base_path: Option<String>) -> Router {
+    match base_path
+        .as_deref()
+        .map(|path| path.trim_matches('/'))
+        .filter(|path| !path.is_empty())
+    {
+        Some(path) => Router::new().nest(&format!("/{path}"), router),
+        None => router,
+    }
+}
+
 /// This function adds response headers that are specific to Flutter web applications.
 ///
 /// It sets the `cross-origin-embedder-policy` header to `require-corp` and the
@@ -214,32 +360,127 @@ async fn add_response_headers(
     Ok(res)
 }
 
+/// Rejects requests that don't carry the configured admin token as bearer token.
+async fn require_admin_token(
+    State(mint): State<Arc<Mint>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match (mint.admin_token.as_ref(), token) {
+        (Some(expected), Some(token))
+            if constant_time_eq(token.as_bytes(), expected.as_bytes()) =>
+        {
+            Ok(next.run(req).await)
+        }
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Rejects requests without a valid blind auth proof in the `Blind-auth` header, if the mint
+/// requires auth. Every auth proof can only be used once.
+async fn require_auth(
+    State(mint): State<Arc<Mint>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, MokshaMintError> {
+    let auth_proof = req
+        .headers()
+        .get(BLIND_AUTH_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(AuthProof::deserialize)
+        .transpose()
+        .map_err(|_| MokshaMintError::AuthRequired)?;
+
+    mint.verify_auth_proof(auth_proof).await?;
+    Ok(next.run(req).await)
+}
+
+const BLIND_AUTH_HEADER: &str = "Blind-auth";
+
+/// Returns the cached response if the same request has been processed before. Only successful
+/// responses are cached, so failed requests can be retried.
+async fn cache_response(
+    State(mint): State<Arc<Mint>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, mint.max_body_bytes)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+    let key = ResponseCache::key(parts.uri.path(), &body);
+
+    if let Some(cached) = mint.response_cache.get(&key) {
+        return Ok(([(CONTENT_TYPE, "application/json")], cached).into_response());
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if !response.status().is_success() {
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    mint.response_cache.insert(key, body.to_vec());
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+async fn get_admin_used_proofs(
+    State(mint): State<Arc<Mint>>,
+) -> Result<Json<Proofs>, MokshaMintError> {
+    Ok(Json(mint.db.get_used_proofs().await?))
+}
+
+/// Returns the map of legacy keyset ids to the v1 keyset ids with the same keys
+async fn get_admin_keyset_migration(
+    State(mint): State<Arc<Mint>>,
+) -> Json<HashMap<String, String>> {
+    Json(mint.keyset_migration_map())
+}
+
+async fn post_admin_cancel_invoice(
+    Path(key): Path<String>,
+    State(mint): State<Arc<Mint>>,
+) -> Result<StatusCode, MokshaMintError> {
+    mint.cancel_pending_invoice(key).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn post_admin_blacklist(
+    State(mint): State<Arc<Mint>>,
+    Json(request): Json<PostBlacklistRequest>,
+) -> Result<StatusCode, MokshaMintError> {
+    mint.blacklist_secret(&request.secret).await?;
+    Ok(StatusCode::OK)
+}
+
 async fn post_legacy_split(
-    State(mint): State<Mint>,
-    Json(swap_request): Json<PostSplitRequest>,
+    State(mint): State<Arc<Mint>>,
+    ValidatedJson(swap_request): ValidatedJson<PostSplitRequest>,
 ) -> Result<Json<PostSplitResponse>, MokshaMintError> {
-    let response = mint
-        .swap(
-            &swap_request.proofs,
-            &swap_request.outputs,
-            &mint.keyset_legacy,
-        )
-        .await?;
+    mint.check_max_outputs(&swap_request.outputs)?;
+    let outputs = mint.legacy_outputs(&swap_request.outputs)?;
+    let response = mint.swap(&swap_request.proofs, &outputs).await?;
 
     Ok(Json(PostSplitResponse::with_promises(response)))
 }
 
 async fn post_legacy_melt(
-    State(mint): State<Mint>,
-    Json(melt_request): Json<PostMeltRequest>,
+    State(mint): State<Arc<Mint>>,
+    ValidatedJson(melt_request): ValidatedJson<PostMeltRequest>,
 ) -> Result<Json<PostMeltResponse>, MokshaMintError> {
+    mint.check_max_outputs(&melt_request.outputs)?;
+    let outputs = mint.legacy_outputs(&melt_request.outputs)?;
     let (paid, preimage, change) = mint
-        .melt(
-            melt_request.pr,
-            &melt_request.proofs,
-            &melt_request.outputs,
-            &mint.keyset_legacy,
-        )
+        .melt(melt_request.pr, &melt_request.proofs, &outputs)
         .await?;
 
     Ok(Json(PostMeltResponse {
@@ -250,62 +491,84 @@ async fn post_legacy_melt(
 }
 
 async fn post_legacy_check_fees(
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
     Json(_check_fees): Json<CheckFeesRequest>,
 ) -> Result<Json<CheckFeesResponse>, MokshaMintError> {
     let invoice = mint.lightning.decode_invoice(_check_fees.pr).await?;
 
     Ok(Json(CheckFeesResponse {
-        fee: mint.fee_reserve(
-            invoice
-                .amount_milli_satoshis()
-                .ok_or_else(|| crate::error::MokshaMintError::InvalidAmount)?,
-        ),
+        fee: mint.estimate_fee_reserve(&invoice).await?.0,
+    }))
+}
+
+async fn post_legacy_check_spendable(
+    State(mint): State<Arc<Mint>>,
+    Json(request): Json<CheckSpendableRequest>,
+) -> Result<Json<CheckSpendableResponse>, MokshaMintError> {
+    let spendable = mint.check_spendable(&request.proofs).await?;
+
+    Ok(Json(CheckSpendableResponse {
+        pending: vec![false; spendable.len()],
+        spendable,
     }))
 }
 
 async fn get_legacy_info(
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
 ) -> Result<Json<MintLegacyInfoResponse>, MokshaMintError> {
     let mint_info = MintLegacyInfoResponse {
-        name: mint.mint_info.name,
-        pubkey: mint.keyset_legacy.mint_pubkey,
+        name: mint.mint_info.name.clone(),
+        pubkey: mint.keyset.mint_pubkey,
         version: match mint.mint_info.version {
             true => Some(env!("CARGO_PKG_VERSION").to_owned()),
             _ => None,
         },
-        description: mint.mint_info.description,
-        description_long: mint.mint_info.description_long,
-        contact: mint.mint_info.contact,
-        nuts: vec![
-            "NUT-00".to_string(),
-            "NUT-01".to_string(),
-            "NUT-02".to_string(),
-            "NUT-03".to_string(),
-            "NUT-04".to_string(),
-            "NUT-05".to_string(),
-            "NUT-06".to_string(),
-            "NUT-08".to_string(),
-            "NUT-09".to_string(),
-        ],
-        motd: mint.mint_info.motd,
+        description: mint.mint_info.description.clone(),
+        description_long: mint.mint_info.description_long.clone(),
+        contact: mint.mint_info.contact_info(),
+        nuts: match mint.mint_info.nuts.is_empty() {
+            true => vec![
+                "NUT-00".to_string(),
+                "NUT-01".to_string(),
+                "NUT-02".to_string(),
+                "NUT-03".to_string(),
+                "NUT-04".to_string(),
+                "NUT-05".to_string(),
+                "NUT-06".to_string(),
+                "NUT-08".to_string(),
+                "NUT-09".to_string(),
+            ],
+            _ => mint
+                .mint_info
+                .nuts
+                .iter()
+                .map(|nut| format!("NUT-{nut:02}"))
+                .collect(),
+        },
+        motd: mint.mint_info.motd.clone(),
+        tos_url: mint.mint_info.tos_url.clone(),
         parameter: Default::default(),
     };
     Ok(Json(mint_info))
 }
 
 async fn get_legacy_mint(
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
     Query(mint_query): Query<GetMintQuery>,
 ) -> Result<Json<PaymentRequest>, MokshaMintError> {
     let (pr, hash) = mint
         .create_invoice(generate_hash(), mint_query.amount)
         .await?;
-    Ok(Json(PaymentRequest { pr, hash }))
+    Ok(Json(PaymentRequest {
+        pr,
+        hash,
+        amount: Some(mint_query.amount),
+        unit: Some(mint.keyset.unit()),
+    }))
 }
 
 async fn post_legacy_mint(
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
     Query(mint_query): Query<PostMintQuery>,
     Json(blinded_messages): Json<PostMintRequest>,
 ) -> Result<Json<PostMintResponse>, MokshaMintError> {
@@ -313,25 +576,56 @@ async fn post_legacy_mint(
         Level::INFO,
         "post_mint: {mint_query:#?} {blinded_messages:#?}"
     );
+    mint.check_max_outputs(&blinded_messages.outputs)?;
 
-    let promises = mint
-        .mint_tokens(
-            mint_query.hash,
-            &blinded_messages.outputs,
-            &mint.keyset_legacy,
-        )
-        .await?;
+    let outputs = mint.legacy_outputs(&blinded_messages.outputs)?;
+    let promises = mint.mint_tokens(mint_query.hash, &outputs).await?;
     Ok(Json(PostMintResponse { promises }))
 }
 
 async fn get_legacy_keys(
-    State(mint): State<Mint>,
-) -> Result<Json<HashMap<u64, PublicKey>>, MokshaMintError> {
-    Ok(Json(mint.keyset_legacy.public_keys))
+    State(mint): State<Arc<Mint>>,
+) -> Result<Json<BTreeMap<u64, PublicKey>>, MokshaMintError> {
+    Ok(Json(
+        mint.legacy_keyset()?
+            .public_keys
+            .clone()
+            .into_iter()
+            .collect(),
+    ))
+}
+
+async fn get_legacy_keysets(
+    State(mint): State<Arc<Mint>>,
+) -> Result<Json<Keysets>, MokshaMintError> {
+    Ok(Json(Keysets::new(vec![mint
+        .legacy_keyset()?
+        .keyset_id
+        .clone()])))
 }
 
-async fn get_legacy_keysets(State(mint): State<Mint>) -> Result<Json<Keysets>, MokshaMintError> {
-    Ok(Json(Keysets::new(vec![mint.keyset_legacy.keyset_id])))
+/// Handles invoice-paid callbacks of lightning backends that support webhooks (Alby, Strike).
+/// Invoices that are reported as paid don't need to be polled anymore when minting tokens.
+async fn post_webhook(
+    Path(backend): Path<String>,
+    State(mint): State<Arc<Mint>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<StatusCode, MokshaMintError> {
+    let is_active_backend = matches!(
+        (&mint.lightning_type, backend.as_str()),
+        (LightningType::Alby(_), "alby") | (LightningType::Strike(_), "strike")
+    );
+    if !is_active_backend {
+        return Err(MokshaMintError::WebhookNotSupported);
+    }
+
+    let event = mint.lightning.verify_webhook(&headers, &body).await?;
+    info!("webhook event: {:?}", event);
+    if event.paid {
+        mint.db.add_paid_invoice(&event.invoice_id).await?;
+    }
+    Ok(StatusCode::OK)
 }
 
 #[utoipa::path(
@@ -356,11 +650,12 @@ async fn get_health() -> impl IntoResponse {
         ),
     )]
 async fn post_swap(
-    State(mint): State<Mint>,
-    Json(swap_request): Json<PostSwapRequest>,
+    State(mint): State<Arc<Mint>>,
+    ValidatedJson(swap_request): ValidatedJson<PostSwapRequest>,
 ) -> Result<Json<PostSwapResponse>, MokshaMintError> {
+    mint.check_max_outputs(&swap_request.outputs)?;
     let response = mint
-        .swap(&swap_request.inputs, &swap_request.outputs, &mint.keyset)
+        .swap(&swap_request.inputs, &swap_request.outputs)
         .await?;
 
     Ok(Json(PostSwapResponse {
@@ -368,6 +663,39 @@ async fn post_swap(
     }))
 }
 
+#[utoipa::path(
+        get,
+        path = "/v1/auth/blind/keys",
+        responses(
+            (status = 200, description = "get the keys for blind auth proofs", body = [KeysResponse])
+        )
+    )]
+async fn get_auth_blind_keys(
+    State(mint): State<Arc<Mint>>,
+) -> Result<Json<KeysResponse>, MokshaMintError> {
+    let auth_keyset = mint
+        .auth_keyset
+        .as_ref()
+        .ok_or(MokshaMintError::AuthNotEnabled)?;
+    Ok(Json(KeysResponse {
+        keysets: vec![KeyResponse {
+            id: auth_keyset.keyset_id.clone(),
+            unit: auth_keyset.unit(),
+            keys: auth_keyset.public_keys.clone().into_iter().collect(),
+        }],
+    }))
+}
+
+async fn post_auth_blind_mint(
+    State(mint): State<Arc<Mint>>,
+    Json(request): Json<PostAuthBlindMintRequest>,
+) -> Result<Json<PostAuthBlindMintResponse>, MokshaMintError> {
+    mint.check_max_outputs(&request.outputs)?;
+    Ok(Json(PostAuthBlindMintResponse {
+        signatures: mint.create_auth_signatures(&request.outputs)?,
+    }))
+}
+
 #[utoipa::path(
         get,
         path = "/v1/keys",
@@ -375,12 +703,12 @@ async fn post_swap(
             (status = 200, description = "get keys", body = [KeysResponse])
         )
     )]
-async fn get_keys(State(mint): State<Mint>) -> Result<Json<KeysResponse>, MokshaMintError> {
+async fn get_keys(State(mint): State<Arc<Mint>>) -> Result<Json<KeysResponse>, MokshaMintError> {
     Ok(Json(KeysResponse {
         keysets: vec![KeyResponse {
             id: mint.keyset.keyset_id.clone(),
-            unit: CurrencyUnit::Sat,
-            keys: mint.keyset.public_keys.clone(),
+            unit: mint.keyset.unit(),
+            keys: mint.keyset.public_keys.clone().into_iter().collect(),
         }],
     }))
 }
@@ -397,17 +725,19 @@ async fn get_keys(State(mint): State<Mint>) -> Result<Json<KeysResponse>, Moksha
     )]
 async fn get_keys_by_id(
     Path(id): Path<String>,
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
 ) -> Result<Json<KeysResponse>, MokshaMintError> {
-    if id != mint.keyset.keyset_id {
-        return Err(MokshaMintError::KeysetNotFound(id));
-    }
+    // rotated out keysets are served as well, so wallets can verify their proofs (NUT-12)
+    let keyset = std::iter::once(&mint.keyset)
+        .chain(mint.inactive_keysets.iter())
+        .find(|keyset| keyset.keyset_id == id)
+        .ok_or(MokshaMintError::UnknownKeyset(id))?;
 
     Ok(Json(KeysResponse {
         keysets: vec![KeyResponse {
-            id: mint.keyset.keyset_id.clone(),
-            unit: CurrencyUnit::Sat,
-            keys: mint.keyset.public_keys.clone(),
+            id: keyset.keyset_id.clone(),
+            unit: keyset.unit(),
+            keys: keyset.public_keys.clone().into_iter().collect(),
         }],
     }))
 }
@@ -419,12 +749,23 @@ async fn get_keys_by_id(
             (status = 200, description = "get keysets", body = [V1Keysets])
         ),
     )]
-async fn get_keysets(State(mint): State<Mint>) -> Result<Json<V1Keysets>, MokshaMintError> {
-    Ok(Json(V1Keysets::new(
-        mint.keyset.keyset_id,
-        CurrencyUnit::Sat,
-        true,
-    )))
+async fn get_keysets(State(mint): State<Arc<Mint>>) -> Result<Json<V1Keysets>, MokshaMintError> {
+    // rotated out keysets are still listed, so wallets can verify and reissue their proofs
+    let inactive_keysets = mint.inactive_keysets.iter().map(|keyset| V1Keyset {
+        id: keyset.keyset_id.clone(),
+        unit: keyset.unit(),
+        active: false,
+    });
+
+    Ok(Json(V1Keysets {
+        keysets: std::iter::once(V1Keyset {
+            id: mint.keyset.keyset_id.clone(),
+            unit: mint.keyset.unit(),
+            active: true,
+        })
+        .chain(inactive_keysets)
+        .collect(),
+    }))
 }
 
 #[utoipa::path(
@@ -436,24 +777,48 @@ async fn get_keysets(State(mint): State<Mint>) -> Result<Json<V1Keysets>, Moksha
         ),
     )]
 async fn post_mint_quote_bolt11(
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
     Json(request): Json<PostMintQuoteBolt11Request>,
 ) -> Result<Json<PostMintQuoteBolt11Response>, MokshaMintError> {
     // FIXME check currency unit
     let key = Uuid::new_v4();
     let (pr, _hash) = mint.create_invoice(key.to_string(), request.amount).await?;
+    let invoice = mint.lightning.decode_invoice(pr.clone()).await?;
 
     let quote = Bolt11MintQuote {
         quote_id: key,
         payment_request: pr.clone(),
-        expiry: quote_expiry(), // FIXME use timestamp type in DB
+        expiry: mint.quote_expiry(&invoice), // FIXME use timestamp type in DB
         paid: false,
+        amount: request.amount,
+        unit: request.unit,
     };
 
     mint.db.add_bolt11_mint_quote(&quote).await?;
     Ok(Json(quote.into()))
 }
 
+#[utoipa::path(
+        post,
+        path = "/v1/mint/quotes/bolt11",
+        request_body = PostMintQuotesBolt11Request,
+        responses(
+            (status = 200, description = "post mint quotes", body = PostMintQuotesBolt11Response)
+        ),
+    )]
+async fn post_mint_quotes_bolt11(
+    State(mint): State<Arc<Mint>>,
+    Json(request): Json<PostMintQuotesBolt11Request>,
+) -> Result<Json<PostMintQuotesBolt11Response>, MokshaMintError> {
+    let quotes = mint
+        .create_invoices(&request.amounts, &request.unit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Ok(Json(PostMintQuotesBolt11Response { quotes }))
+}
+
 #[utoipa::path(
         post,
         path = "/v1/mint/bolt11/{quote_id}",
@@ -466,11 +831,12 @@ async fn post_mint_quote_bolt11(
         )
     )]
 async fn post_mint_bolt11(
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
     Json(request): Json<PostMintBolt11Request>,
 ) -> Result<Json<PostMintBolt11Response>, MokshaMintError> {
+    mint.check_max_outputs(&request.outputs)?;
     let signatures = mint
-        .mint_tokens(request.quote.clone(), &request.outputs, &mint.keyset)
+        .mint_tokens(request.quote.clone(), &request.outputs)
         .await?;
 
     let old_quote = &mint
@@ -496,26 +862,18 @@ async fn post_mint_bolt11(
         ),
     )]
 async fn post_melt_quote_bolt11(
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
     Json(melt_request): Json<PostMeltQuoteBolt11Request>,
 ) -> Result<Json<PostMeltQuoteBolt11Response>, MokshaMintError> {
-    let invoice = mint
-        .lightning
-        .decode_invoice(melt_request.request.clone())
-        .await?;
-    let amount = invoice
-        .amount_milli_satoshis()
-        .ok_or_else(|| crate::error::MokshaMintError::InvalidAmount)?;
-    let fee_reserve = mint.fee_reserve(amount) / 1_000; // FIXME check if this is correct
-    info!("fee_reserve: {}", fee_reserve);
+    let info = mint.quote_melt(&melt_request.request).await?;
+    info!("fee_reserve: {}", info.fee_reserve);
 
-    let amount_sat = amount / 1_000;
     let key = Uuid::new_v4();
     let quote = Bolt11MeltQuote {
         quote_id: key,
-        amount: amount_sat,
-        fee_reserve,
-        expiry: quote_expiry(),
+        amount: info.amount_sat,
+        fee_reserve: info.fee_reserve,
+        expiry: info.expiry,
         payment_request: melt_request.request.clone(),
         paid: false,
     };
@@ -526,12 +884,6 @@ async fn post_melt_quote_bolt11(
     })?))
 }
 
-fn quote_expiry() -> u64 {
-    // FIXME add config option for expiry
-    let now = Utc::now() + Duration::minutes(30);
-    now.timestamp() as u64
-}
-
 #[utoipa::path(
         post,
         path = "/v1/melt/bolt11",
@@ -541,25 +893,18 @@ fn quote_expiry() -> u64 {
         ),
     )]
 async fn post_melt_bolt11(
-    State(mint): State<Mint>,
-    Json(melt_request): Json<PostMeltBolt11Request>,
+    State(mint): State<Arc<Mint>>,
+    ValidatedJson(melt_request): ValidatedJson<PostMeltBolt11Request>,
 ) -> Result<Json<PostMeltBolt11Response>, MokshaMintError> {
-    let quote = mint
-        .db
-        .get_bolt11_melt_quote(&Uuid::from_str(&melt_request.quote).unwrap()) // FIXME remove unwrap
-        .await?;
-
+    mint.check_max_outputs(&melt_request.outputs)?;
     let (paid, payment_preimage, change) = mint
-        .melt(
-            quote.payment_request.to_owned(),
+        .execute_melt(
+            &melt_request.quote,
             &melt_request.inputs,
             &melt_request.outputs,
-            &mint.keyset,
+            melt_request.max_fee,
         )
         .await?;
-    mint.db
-        .update_bolt11_melt_quote(&Bolt11MeltQuote { paid, ..quote })
-        .await?;
 
     Ok(Json(PostMeltBolt11Response {
         paid,
@@ -580,7 +925,7 @@ async fn post_melt_bolt11(
     )]
 async fn get_mint_quote_bolt11(
     Path(quote_id): Path<String>,
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
 ) -> Result<Json<PostMintQuoteBolt11Response>, MokshaMintError> {
     info!("get_quote: {}", quote_id);
     let quote = mint
@@ -588,10 +933,8 @@ async fn get_mint_quote_bolt11(
         .get_bolt11_mint_quote(&Uuid::from_str(&quote_id).unwrap()) // FIXME
         .await?;
 
-    let paid = mint
-        .lightning
-        .is_invoice_paid(quote.payment_request.clone())
-        .await?;
+    // the pending invoice is deleted once the quote has been minted
+    let paid = quote.paid || mint.is_invoice_paid(&quote_id).await?;
 
     Ok(Json(Bolt11MintQuote { paid, ..quote }.into()))
 }
@@ -608,7 +951,7 @@ async fn get_mint_quote_bolt11(
     )]
 async fn get_melt_quote_bolt11(
     Path(quote_id): Path<String>,
-    State(mint): State<Mint>,
+    State(mint): State<Arc<Mint>>,
 ) -> Result<Json<PostMeltQuoteBolt11Response>, MokshaMintError> {
     info!("get_melt_quote: {}", quote_id);
     let quote = mint
@@ -627,45 +970,118 @@ async fn get_melt_quote_bolt11(
             (status = 200, description = "get mint info", body = [MintInfoResponse])
         )
     )]
-async fn get_info(State(mint): State<Mint>) -> Result<Json<MintInfoResponse>, MokshaMintError> {
+async fn get_info(
+    State(mint): State<Arc<Mint>>,
+) -> Result<Json<MintInfoResponse>, MokshaMintError> {
+    let default_nuts = Nuts::default();
+    let supports = |nut, default| mint.mint_info.advertises_nut(nut, default);
     let mint_info = MintInfoResponse {
-        name: mint.mint_info.name,
-        pubkey: mint.keyset_legacy.mint_pubkey,
+        name: mint.mint_info.name.clone(),
+        pubkey: mint.keyset.mint_pubkey,
         version: match mint.mint_info.version {
             true => Some(env!("CARGO_PKG_VERSION").to_owned()),
             _ => None,
         },
-        description: mint.mint_info.description,
-        description_long: mint.mint_info.description_long,
-        contact: mint.mint_info.contact,
-        nuts: Nuts::default(),
-        motd: mint.mint_info.motd,
+        description: mint.mint_info.description.clone(),
+        description_long: mint.mint_info.description_long.clone(),
+        contact: mint.mint_info.contact_info(),
+        nuts: Nuts {
+            nut4: Nut4 {
+                disabled: mint.minting_disabled || !supports(4, true),
+                ..Default::default()
+            },
+            nut6: Nut6 {
+                supported: supports(6, default_nuts.nut6.supported),
+            },
+            nut7: Nut7 {
+                supported: supports(7, default_nuts.nut7.supported),
+            },
+            nut8: Nut8 {
+                supported: supports(8, default_nuts.nut8.supported),
+            },
+            nut9: Nut9 {
+                supported: supports(9, default_nuts.nut9.supported),
+            },
+            nut10: Nut10 {
+                supported: supports(10, default_nuts.nut10.supported),
+            },
+            nut11: Nut11 {
+                supported: supports(11, default_nuts.nut11.supported),
+            },
+            nut12: Nut12 {
+                supported: supports(12, default_nuts.nut12.supported),
+            },
+            nut19: Nut19 {
+                ttl: Some(mint.response_cache.ttl().as_secs()),
+                cached_endpoints: CACHED_PATHS
+                    .iter()
+                    .map(|path| CachedEndpoint {
+                        method: "POST".to_owned(),
+                        path: path.to_string(),
+                    })
+                    .collect(),
+            },
+            ..Default::default()
+        },
+        motd: mint.mint_info.motd.clone(),
+        tos_url: mint.mint_info.tos_url.clone(),
     };
     Ok(Json(mint_info))
 }
 
+#[utoipa::path(
+        get,
+        path = "/v1/version",
+        responses(
+            (status = 200, description = "get mint version and uptime", body = [MintVersionResponse])
+        )
+    )]
+async fn get_version(
+    State(mint): State<Arc<Mint>>,
+) -> Result<Json<MintVersionResponse>, MokshaMintError> {
+    Ok(Json(MintVersionResponse {
+        name: mint.mint_info.name.clone(),
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        uptime_secs: mint.started_at.elapsed().as_secs(),
+    }))
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, sync::Arc};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        str::FromStr,
+        sync::Arc,
+        time::Duration,
+    };
 
-    use crate::server::app;
+    use crate::server::{app, run_server_with_shutdown, with_base_path};
     use axum::{
         body::Body,
         http::{Request, StatusCode},
+        Router,
     };
     use http_body_util::BodyExt;
     use moksha_core::{
-        keyset::{Keysets, V1Keysets},
-        primitives::{CurrencyUnit, KeysResponse, MintLegacyInfoResponse},
+        blind::BlindedMessage,
+        keyset::{Keysets, MintKeyset, V1Keysets},
+        primitives::{
+            CurrencyUnit, KeysResponse, MintInfoResponse, MintLegacyInfoResponse, PaymentRequest,
+            PostMintQuoteBolt11Response, PostSwapRequest, PostSwapResponse,
+        },
+        proof::{AuthProof, Proof, Proofs},
     };
     use secp256k1::PublicKey;
     use tower::ServiceExt;
 
     use crate::{
-        database::MockDatabase,
+        cache::{ResponseCache, DEFAULT_RESPONSE_CACHE_TTL_SECS},
+        database::{memory::InMemoryDatabase, MockDatabase},
+        error::MokshaMintError,
         info::MintInfoSettings,
-        lightning::{LightningType, MockLightning},
-        mint::{LightningFeeConfig, Mint},
+        lightning::{AlbyLightning, LightningType, MockLightning},
+        mint::{LightningFeeConfig, Mint, AUTH_DERIVATION_PATH, DEFAULT_MAX_BODY_BYTES},
+        model::{CreateInvoiceResult, Invoice},
     };
 
     #[tokio::test]
@@ -724,6 +1140,270 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_admin_route_valid_token() -> anyhow::Result<()> {
+        let mut db = MockDatabase::new();
+        db.expect_get_used_proofs()
+            .returning(|| Ok(Proofs::empty()));
+        let app = app(create_admin_mint(db), None, None);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/admin/proofs")
+                    .header("Authorization", "Bearer admin_secret")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin_route_wrong_token() -> anyhow::Result<()> {
+        let app = app(create_admin_mint(MockDatabase::new()), None, None);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/admin/proofs")
+                    .header("Authorization", "Bearer wrong_secret")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin_route_missing_token() -> anyhow::Result<()> {
+        let app = app(create_admin_mint(MockDatabase::new()), None, None);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/admin/proofs")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin_cancel_invoice() -> anyhow::Result<()> {
+        let mut db = MockDatabase::new();
+        db.expect_get_pending_invoice()
+            .returning(|_| Ok(Invoice::new(100, "lnbcrt1u1...".to_string())));
+        db.expect_delete_pending_invoice()
+            .withf(|key| key == "key")
+            .times(1)
+            .returning(|_| Ok(()));
+        let app = app(create_admin_mint(db), None, None);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/admin/invoice/key/cancel")
+                    .header("Authorization", "Bearer admin_secret")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin_keyset_migration() -> anyhow::Result<()> {
+        let mint = create_admin_mint(MockDatabase::new());
+        let legacy_keyset_id = mint
+            .keyset_legacy
+            .as_ref()
+            .expect("legacy keyset is missing")
+            .keyset_id
+            .clone();
+        let keyset_id = mint.keyset.keyset_id.clone();
+        let response = app(mint, None, None)
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/admin/keysets/migration")
+                    .header("Authorization", "Bearer admin_secret")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let migration = serde_json::from_slice::<HashMap<String, String>>(&body)?;
+        assert_eq!(Some(&keyset_id), migration.get(&legacy_keyset_id));
+        Ok(())
+    }
+
+    fn create_admin_mint(db: MockDatabase) -> Mint {
+        Mint {
+            admin_token: Some("admin_secret".to_string()),
+            ..Mint::new(
+                "mytestsecret".to_string(),
+                "".to_string(),
+                Arc::new(MockLightning::new()),
+                LightningType::Lnbits(Default::default()),
+                Arc::new(db),
+                LightningFeeConfig::default(),
+                Default::default(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() -> anyhow::Result<()> {
+        let mut db = MockDatabase::new();
+        db.expect_close().times(1).returning(|| ());
+        let mint = Mint::new(
+            "mytestsecret".to_string(),
+            "".to_string(),
+            Arc::new(MockLightning::new()),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(db),
+            LightningFeeConfig::default(),
+            Default::default(),
+        );
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(run_server_with_shutdown(
+            mint,
+            "127.0.0.1:0".parse()?,
+            None,
+            None,
+            None,
+            async {
+                shutdown_rx.await.ok();
+            },
+        ));
+
+        shutdown_tx
+            .send(())
+            .expect("server stopped before shutdown");
+        tokio::time::timeout(std::time::Duration::from_secs(5), server).await???;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_base_path() -> anyhow::Result<()> {
+        let app = with_base_path(
+            app(create_mock_mint(Default::default()), None, None),
+            Some("/mint-a/".to_string()),
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/mint-a/v1/keys")
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(Request::builder().uri("/v1/keys").body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_base_path_with_api_prefix() -> anyhow::Result<()> {
+        let app = with_base_path(
+            app(
+                create_mock_mint(Default::default()),
+                None,
+                Some("/api".to_string()),
+            ),
+            Some("/mint-a".to_string()),
+        );
+
+        for (uri, status) in [
+            ("/mint-a/api/v1/keys", StatusCode::OK),
+            ("/mint-a/health", StatusCode::OK),
+            ("/mint-a/v1/keys", StatusCode::NOT_FOUND),
+            ("/api/mint-a/v1/keys", StatusCode::NOT_FOUND),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+                .await?;
+            assert_eq!(response.status(), status, "{uri}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_legacy_mint_echoes_amount() -> anyhow::Result<()> {
+        let app = app(create_invoice_mint(), None, None);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/mint?amount=500")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let payment_request = serde_json::from_slice::<PaymentRequest>(&body)?;
+        assert_eq!(Some(500), payment_request.amount);
+        assert_eq!(Some(CurrencyUnit::Sat), payment_request.unit);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_mint_quote_echoes_amount() -> anyhow::Result<()> {
+        let app = app(create_invoice_mint(), None, None);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/mint/quote/bolt11")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"amount":500,"unit":"sat"}"#))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let quote = serde_json::from_slice::<PostMintQuoteBolt11Response>(&body)?;
+        assert_eq!(Some(500), quote.amount);
+        assert_eq!(Some(CurrencyUnit::Sat), quote.unit);
+        Ok(())
+    }
+
+    /// mint with an in-memory database whose lightning backend creates a fixed invoice
+    fn create_invoice_mint() -> Mint {
+        let pr = "lnbcrt5u1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxq8zals8sqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssq2psnxdza6tl2yhk4rj9z5pj6vv9l9e53ve4645hj0zcn8e5t64q566xj0928r6ln6nw4n5ey9f43r2jarxmzst85qpf42k4s22n55cgqgjrc6w";
+        let mut lightning = MockLightning::new();
+        lightning.expect_create_invoice().returning(move |_| {
+            Ok(CreateInvoiceResult {
+                payment_hash: vec![0; 32],
+                payment_request: pr.to_string(),
+            })
+        });
+        lightning.expect_decode_invoice().returning(|pr| {
+            lightning_invoice::Bolt11Invoice::from_str(&pr)
+                .map_err(|err| MokshaMintError::DecodeInvoice(pr.clone(), err))
+        });
+
+        Mint::new(
+            "mytestsecret".to_string(),
+            "".to_string(),
+            Arc::new(lightning),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            LightningFeeConfig::default(),
+            Default::default(),
+        )
+    }
+
     fn create_mock_mint(mint_info: MintInfoSettings) -> Mint {
         let db = Arc::new(MockDatabase::new());
         let lightning = Arc::new(MockLightning::new());
@@ -759,6 +1439,31 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_keys_v1_sorted_by_amount() -> anyhow::Result<()> {
+        let app = app(create_mock_mint(Default::default()), None, None);
+        let mut bodies = vec![];
+        for _ in 0..3 {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri("/v1/keys").body(Body::empty())?)
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            bodies.push(String::from_utf8(body.to_vec())?);
+        }
+        assert!(bodies.iter().all(|body| body == &bodies[0]));
+
+        let (_, keys) = bodies[0].split_once(r#""keys":"#).expect("keys not found");
+        let amounts = keys
+            .split('"')
+            .filter_map(|part| part.parse::<u64>().ok())
+            .collect::<Vec<_>>();
+        assert_eq!(64, amounts.len());
+        assert!(amounts.windows(2).all(|pair| pair[0] < pair[1]));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_keysets_v1() -> anyhow::Result<()> {
         let app = app(create_mock_mint(Default::default()), None, None);
@@ -769,11 +1474,543 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
         let keysets = serde_json::from_slice::<V1Keysets>(&body)?;
+        // the pre NUT-02 id of the active keyset is not listed
         assert_eq!(1, keysets.keysets.len());
+        assert!(keysets.keysets[0].active);
         assert_eq!(16, keysets.keysets[0].id.len());
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_keysets_v1_includes_unit() -> anyhow::Result<()> {
+        let app = app(create_mock_mint(Default::default()), None, None);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/keysets").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let keysets = serde_json::from_slice::<serde_json::Value>(&body)?;
+        let keyset = serde_json::to_string(&keysets["keysets"][0])?;
+        assert!(keyset.contains(r#""unit":"sat""#), "{keyset}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_keysets_v1_after_rotation() -> anyhow::Result<()> {
+        let mut mint = create_mock_mint(Default::default());
+        let old_keyset_id = mint.keyset.keyset_id.clone();
+        let new_keyset = MintKeyset::new("mynewtestsecret", "");
+        let new_keyset_id = new_keyset.keyset_id.clone();
+        mint.rotate_keyset(new_keyset);
+
+        let app = app(mint, None, None);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/keysets").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let keysets = serde_json::from_slice::<V1Keysets>(&body)?;
+        assert_eq!(2, keysets.keysets.len());
+
+        let new = keysets.keysets.iter().find(|k| k.id == new_keyset_id);
+        assert!(new.expect("new keyset is missing").active);
+        let old = keysets.keysets.iter().find(|k| k.id == old_keyset_id);
+        assert!(!old.expect("old keyset is missing").active);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_too_many_outputs() -> anyhow::Result<()> {
+        // the mock database has no expectations, so the request must be rejected before the
+        // proofs are checked or any output is signed
+        let mint = Mint {
+            max_outputs: 2,
+            ..create_mock_mint(Default::default())
+        };
+        let output = serde_json::json!({
+            "amount": 8,
+            "B_": "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239"
+        });
+        let body = serde_json::json!({
+            "inputs": [{
+                "amount": 8,
+                "secret": "secret",
+                "C": "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
+                "id": "someid"
+            }],
+            "outputs": [output, output, output]
+        });
+        let response = app(mint, None, None)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/swap")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error = serde_json::from_slice::<serde_json::Value>(&body)?;
+        assert!(error["detail"]
+            .as_str()
+            .expect("detail is missing")
+            .starts_with("Too many outputs 3"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_split_invalid_pubkey() -> anyhow::Result<()> {
+        let app = app(create_mock_mint(Default::default()), None, None);
+        let body = serde_json::json!({
+            "proofs": [{
+                "amount": 8,
+                "secret": "secret",
+                "C": "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
+                "id": "someid"
+            }],
+            "outputs": [{ "amount": 8, "B_": "not a pubkey" }]
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/split")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error = serde_json::from_slice::<serde_json::Value>(&body)?;
+        assert!(error["detail"]
+            .as_str()
+            .expect("detail is missing")
+            .starts_with("Invalid request field body"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_legacy_api_disabled() -> anyhow::Result<()> {
+        let mint = Mint {
+            keyset_legacy: None,
+            ..create_mock_mint(Default::default())
+        };
+        let app = app(mint, None, None);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/keys").body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .oneshot(Request::builder().uri("/v1/keys").body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_with_auth_proof() -> anyhow::Result<()> {
+        let mint = create_auth_mint();
+        let auth_proof = create_auth_proof(&mint)?;
+        let app = app(mint.clone(), None, None);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/swap")
+                    .header("content-type", "application/json")
+                    .header("Blind-auth", auth_proof.serialize()?)
+                    .body(Body::from(create_swap_request(&mint)?))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let swap_response = serde_json::from_slice::<PostSwapResponse>(&body)?;
+        assert_eq!(1, swap_response.signatures.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_without_auth_proof() -> anyhow::Result<()> {
+        let mint = create_auth_mint();
+        let app = app(mint.clone(), None, None);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/swap")
+                    .header("content-type", "application/json")
+                    .body(Body::from(create_swap_request(&mint)?))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_returns_signatures_for_input_total() -> anyhow::Result<()> {
+        let mint = Mint::new(
+            "mytestsecret".to_string(),
+            "".to_string(),
+            Arc::new(MockLightning::new()),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(InMemoryDatabase::new()),
+            LightningFeeConfig::default(),
+            Default::default(),
+        );
+        let keyset = &mint.keyset;
+        let mut inputs = vec![];
+        for amount in [8u64, 2] {
+            let secret = format!("secret_{amount}");
+            let (b_, r) = mint.dhke.step1_alice(secret.clone(), None)?;
+            let c_ = mint.dhke.step2_bob(b_, &keyset.private_keys[&amount])?;
+            let c = mint.dhke.step3_alice(c_, r, keyset.public_keys[&amount])?;
+            inputs.push(serde_json::json!({
+                "amount": amount,
+                "id": keyset.keyset_id,
+                "secret": secret,
+                "C": c.to_string(),
+            }));
+        }
+        let outputs = [4u64, 4, 2]
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| {
+                let (b_, _) = mint.dhke.step1_alice(format!("output_{i}"), None)?;
+                Ok(serde_json::json!({ "amount": amount, "B_": b_.to_string() }))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let body = serde_json::json!({ "inputs": inputs, "outputs": outputs });
+
+        let response = app(mint.clone(), None, None)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/swap")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let swap_response = serde_json::from_slice::<PostSwapResponse>(&body)?;
+        assert_eq!(3, swap_response.signatures.len());
+        assert_eq!(
+            10,
+            swap_response
+                .signatures
+                .iter()
+                .map(|signature| signature.amount)
+                .sum::<u64>()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_swap_body_too_large() -> anyhow::Result<()> {
+        let mint = create_mock_mint(Default::default());
+        let body = format!(
+            r#"{{"inputs":[],"outputs":[],"padding":"{}"}}"#,
+            "a".repeat(DEFAULT_MAX_BODY_BYTES)
+        );
+
+        let response = app(mint, None, None)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/swap")
+                    .header("content-type", "application/json")
+                    .header("content-length", body.len())
+                    .body(Body::from(body))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_with_blacklisted_secret() -> anyhow::Result<()> {
+        let mint = Mint {
+            admin_token: Some("admin_secret".to_string()),
+            ..Mint::new(
+                "mytestsecret".to_string(),
+                "".to_string(),
+                Arc::new(MockLightning::new()),
+                LightningType::Lnbits(Default::default()),
+                Arc::new(InMemoryDatabase::new()),
+                LightningFeeConfig::default(),
+                Default::default(),
+            )
+        };
+        let keyset = &mint.keyset;
+        let secret = "stolen_secret".to_string();
+        let (b_, r) = mint.dhke.step1_alice(secret.clone(), None)?;
+        let c_ = mint.dhke.step2_bob(b_, &keyset.private_keys[&8])?;
+        let c = mint.dhke.step3_alice(c_, r, keyset.public_keys[&8])?;
+        let (output, _) = mint.dhke.step1_alice("output", None)?;
+        let body = serde_json::json!({
+            "inputs": [{ "amount": 8, "id": keyset.keyset_id, "secret": secret, "C": c.to_string() }],
+            "outputs": [{ "amount": 8, "B_": output.to_string() }],
+        });
+
+        let response = app(mint.clone(), None, None)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/admin/blacklist")
+                    .header("Authorization", "Bearer admin_secret")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({ "secret": "stolen_secret" }).to_string(),
+                    ))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app(mint.clone(), None, None)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/swap")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await?.to_bytes();
+        assert!(String::from_utf8(body.to_vec())?.contains("blacklisted"));
+        // the proof is not marked as spent
+        assert!(mint.db.get_used_proofs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_swaps_share_used_proofs() -> anyhow::Result<()> {
+        let mint = Mint {
+            // the repeated request must not get the cached response
+            response_cache: Arc::new(ResponseCache::new(Duration::ZERO)),
+            ..Mint::new(
+                "mytestsecret".to_string(),
+                "".to_string(),
+                Arc::new(MockLightning::new()),
+                LightningType::Lnbits(Default::default()),
+                Arc::new(InMemoryDatabase::new()),
+                LightningFeeConfig::default(),
+                Default::default(),
+            )
+        };
+        let keyset = &mint.keyset;
+        let bodies = (0..8)
+            .map(|i| {
+                let secret = format!("secret_{i}");
+                let (b_, r) = mint.dhke.step1_alice(secret.clone(), None)?;
+                let c_ = mint.dhke.step2_bob(b_, &keyset.private_keys[&8])?;
+                let c = mint.dhke.step3_alice(c_, r, keyset.public_keys[&8])?;
+                let (output, _) = mint.dhke.step1_alice(format!("output_{i}"), None)?;
+                Ok(serde_json::json!({
+                    "inputs": [{ "amount": 8, "id": keyset.keyset_id, "secret": secret, "C": c.to_string() }],
+                    "outputs": [{ "amount": 8, "B_": output.to_string() }],
+                })
+                .to_string())
+            })
+            .collect::<anyhow::Result<Vec<String>>>()?;
+
+        let swap = |app: Router, body: String| async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/swap")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))?,
+            )
+            .await
+            .map_err(anyhow::Error::from)
+        };
+
+        let app = app(mint.clone(), None, None);
+        let requests = bodies
+            .iter()
+            .map(|body| tokio::spawn(swap(app.clone(), body.clone())))
+            .collect::<Vec<_>>();
+        for request in requests {
+            assert_eq!(request.await??.status(), StatusCode::OK);
+        }
+        assert_eq!(8, mint.db.get_used_proofs().await?.len());
+
+        // every request sees the proofs that were spent by the others
+        let response = swap(app, bodies[0].clone()).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_retry_returns_cached_response() -> anyhow::Result<()> {
+        let mut db = MockDatabase::new();
+        db.expect_get_used_proofs()
+            .returning(|| Ok(Proofs::empty()));
+        db.expect_is_blacklisted().returning(|_| Ok(false));
+        db.expect_add_used_proofs().times(1).returning(|_| Ok(()));
+        let mint = Mint::new(
+            "mytestsecret".to_string(),
+            "".to_string(),
+            Arc::new(MockLightning::new()),
+            LightningType::Lnbits(Default::default()),
+            Arc::new(db),
+            LightningFeeConfig::default(),
+            Default::default(),
+        );
+        let body = create_swap_request(&mint)?;
+        let app = app(mint, None, None);
+
+        let mut responses = vec![];
+        for _ in 0..2 {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/v1/swap")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.clone()))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            responses.push(response.into_body().collect().await?.to_bytes());
+        }
+        assert_eq!(responses[0], responses[1]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_info_advertises_cached_endpoints() -> anyhow::Result<()> {
+        let app = app(create_mock_mint(Default::default()), None, None);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/info").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let info = serde_json::from_slice::<MintInfoResponse>(&body)?;
+        assert_eq!(Some(DEFAULT_RESPONSE_CACHE_TTL_SECS), info.nuts.nut19.ttl);
+        assert!(info
+            .nuts
+            .nut19
+            .cached_endpoints
+            .iter()
+            .any(|endpoint| endpoint.method == "POST" && endpoint.path == "/v1/swap"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_info_advertises_configured_nuts() -> anyhow::Result<()> {
+        let mint = create_mock_mint(MintInfoSettings {
+            nuts: vec![4, 5, 7, 12],
+            ..Default::default()
+        });
+        let app = app(mint, None, None);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/info").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let info = serde_json::from_slice::<MintInfoResponse>(&body)?;
+        assert!(!info.nuts.nut4.disabled);
+        assert!(info.nuts.nut7.supported);
+        assert!(info.nuts.nut12.supported);
+        assert!(!info.nuts.nut6.supported);
+        assert!(!info.nuts.nut8.supported);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_info_advertises_disabled_minting() -> anyhow::Result<()> {
+        let mint = Mint {
+            minting_disabled: true,
+            ..create_mock_mint(Default::default())
+        };
+        let app = app(mint, None, None);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/info").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let info = serde_json::from_slice::<MintInfoResponse>(&body)?;
+        assert!(info.nuts.nut4.disabled);
+        Ok(())
+    }
+
+    fn create_auth_mint() -> Mint {
+        Mint {
+            auth_keyset: Some(MintKeyset::new_with_max_order(
+                "mytestsecret",
+                AUTH_DERIVATION_PATH,
+                1,
+            )),
+            ..Mint::new(
+                "mytestsecret".to_string(),
+                "".to_string(),
+                Arc::new(MockLightning::new()),
+                LightningType::Lnbits(Default::default()),
+                Arc::new(InMemoryDatabase::new()),
+                LightningFeeConfig::default(),
+                Default::default(),
+            )
+        }
+    }
+
+    fn create_auth_proof(mint: &Mint) -> anyhow::Result<AuthProof> {
+        let auth_keyset = mint.auth_keyset.as_ref().expect("auth keyset not set");
+        let secret = "auth_secret".to_string();
+        let (b_, r) = mint.dhke.step1_alice(secret.clone(), None)?;
+        let signatures = mint.create_auth_signatures(&[BlindedMessage {
+            amount: 1,
+            b_,
+            id: None,
+        }])?;
+        let c = mint
+            .dhke
+            .step3_alice(signatures[0].c_, r, auth_keyset.public_keys[&1])?;
+        Ok(AuthProof {
+            keyset_id: auth_keyset.keyset_id.clone(),
+            secret,
+            c,
+        })
+    }
+
+    /// Creates a swap request for a proof of 8 sats signed by the active keyset
+    fn create_swap_request(mint: &Mint) -> anyhow::Result<String> {
+        let keyset = &mint.keyset;
+        let secret = "secret_8".to_string();
+        let (b_, r) = mint.dhke.step1_alice(secret.clone(), None)?;
+        let c_ = mint.dhke.step2_bob(b_, &keyset.private_keys[&8])?;
+        let c = mint.dhke.step3_alice(c_, r, keyset.public_keys[&8])?;
+        let (output, _) = mint.dhke.step1_alice("output_secret", None)?;
+
+        Ok(serde_json::to_string(&PostSwapRequest {
+            inputs: Proof::new(8, secret, c, keyset.keyset_id.clone()).into(),
+            outputs: vec![BlindedMessage {
+                amount: 8,
+                b_: output,
+                id: None,
+            }],
+        })?)
+    }
+
     // ### v1 api tests
 
     #[tokio::test]
@@ -816,7 +2053,7 @@ mod tests {
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/v1/keys/00e777893f6faa27")
+                    .uri("/v1/keys/00f545318e4fad2b")
                     .body(Body::empty())?,
             )
             .await?;
@@ -830,12 +2067,62 @@ mod tests {
             keys.keysets.get(0).expect("keyset not found").keys.len()
         );
         assert_eq!(
-            "00e777893f6faa27",
+            "00f545318e4fad2b",
             keys.keysets.get(0).expect("keyset not found").id
         );
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_v1_keys_id_inactive_keyset() -> anyhow::Result<()> {
+        let mut mint = create_mock_mint(Default::default());
+        let old_keyset = mint.keyset.clone();
+        mint.rotate_keyset(MintKeyset::new("mynewtestsecret", ""));
+
+        let app = app(mint, None, None);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/v1/keys/{}", old_keyset.keyset_id))
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let keys: KeysResponse = serde_json::from_slice(&body)?;
+        // only the requested keyset is returned, not the active one
+        assert_eq!(1, keys.keysets.len());
+        let keyset = keys.keysets.first().expect("keyset not found");
+        assert_eq!(old_keyset.keyset_id, keyset.id);
+        assert_eq!(old_keyset.unit(), keyset.unit);
+        assert_eq!(
+            old_keyset
+                .public_keys
+                .into_iter()
+                .collect::<BTreeMap<_, _>>(),
+            keyset.keys
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_v1_keys_id_pre_nut02_keyset() -> anyhow::Result<()> {
+        let mint = create_mock_mint(Default::default());
+        let pre_nut02_keyset_id = mint.keyset.with_pre_nut02_id().keyset_id;
+
+        let response = app(mint, None, None)
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/v1/keys/{pre_nut02_keyset_id}"))
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_v1_keysets() -> anyhow::Result<()> {
         let app = app(create_mock_mint(Default::default()), None, None);
@@ -850,7 +2137,80 @@ mod tests {
         let keyset = keys.keysets.get(0).expect("keyset not found");
         assert!(keyset.active);
         assert_eq!(CurrencyUnit::Sat, keyset.unit);
-        assert_eq!("00e777893f6faa27", keyset.id);
+        assert_eq!("00f545318e4fad2b", keyset.id);
+        Ok(())
+    }
+
+    const ALBY_WEBHOOK_SECRET: &[u8] = b"alby_webhook_secret";
+
+    fn create_alby_mint(db: MockDatabase) -> Mint {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let webhook_secret = format!(
+            "whsec_{}",
+            general_purpose::STANDARD.encode(ALBY_WEBHOOK_SECRET)
+        );
+        Mint::new(
+            "mytestsecret".to_string(),
+            "".to_string(),
+            Arc::new(AlbyLightning::new(
+                "api_key".to_string(),
+                Some(webhook_secret),
+            )),
+            LightningType::Alby(Default::default()),
+            Arc::new(db),
+            LightningFeeConfig::default(),
+            Default::default(),
+        )
+    }
+
+    fn create_alby_webhook_request(body: &str, secret: &[u8]) -> anyhow::Result<Request<Body>> {
+        use base64::{engine::general_purpose, Engine as _};
+        use bitcoin_hashes::{hmac, sha256, Hash, HashEngine};
+
+        let msg_id = "msg_2ZFCvJaMbtTPvbTjWPY0VXuFEDV";
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(secret);
+        engine.input(format!("{msg_id}.{timestamp}.{body}").as_bytes());
+        let signature = general_purpose::STANDARD
+            .encode(hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array());
+
+        Ok(Request::builder()
+            .method("POST")
+            .uri("/webhook/alby")
+            .header("svix-id", msg_id)
+            .header("svix-timestamp", timestamp)
+            .header("svix-signature", format!("v1,{signature}"))
+            .body(Body::from(body.to_owned()))?)
+    }
+
+    #[tokio::test]
+    async fn test_post_webhook_alby() -> anyhow::Result<()> {
+        let mut db = MockDatabase::new();
+        db.expect_add_paid_invoice().times(1).returning(|_| Ok(()));
+        let app = app(create_alby_mint(db), None, None);
+
+        let body = r#"{"payment_hash":"e1e2c8dbd5b4c6e5a1d1b6c3e0f2a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1","settled":true}"#;
+        let response = app
+            .oneshot(create_alby_webhook_request(body, ALBY_WEBHOOK_SECRET)?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_webhook_alby_invalid_signature() -> anyhow::Result<()> {
+        let mut db = MockDatabase::new();
+        db.expect_add_paid_invoice().never();
+        let app = app(create_alby_mint(db), None, None);
+
+        let body = r#"{"payment_hash":"e1e2c8dbd5b4c6e5a1d1b6c3e0f2a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1","settled":true}"#;
+        let response = app
+            .oneshot(create_alby_webhook_request(body, b"wrong_secret")?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         Ok(())
     }
 
@@ -864,4 +2224,22 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_get_version() -> anyhow::Result<()> {
+        let app = app(create_mock_mint(Default::default()), None, None);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/version").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let version = serde_json::from_slice::<serde_json::Value>(&body)?;
+        assert!(!version["version"]
+            .as_str()
+            .expect("version missing")
+            .is_empty());
+        assert!(version["uptime_secs"].as_u64().is_some());
+        Ok(())
+    }
 }
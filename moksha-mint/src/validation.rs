@@ -0,0 +1,150 @@
+//! Validation of the request bodies at the route boundary, so malformed requests are rejected
+//! with a descriptive error before they reach the mint.
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    Json,
+};
+use moksha_core::{
+    blind::BlindedMessage,
+    primitives::{PostMeltBolt11Request, PostMeltRequest, PostSplitRequest, PostSwapRequest},
+    proof::Proofs,
+};
+use serde::de::DeserializeOwned;
+
+use crate::error::MokshaMintError;
+
+pub trait Validate {
+    fn validate(&self) -> Result<(), MokshaMintError>;
+}
+
+impl Validate for PostSplitRequest {
+    fn validate(&self) -> Result<(), MokshaMintError> {
+        validate_proofs("proofs", &self.proofs)?;
+        validate_outputs(&self.outputs)
+    }
+}
+
+impl Validate for PostSwapRequest {
+    fn validate(&self) -> Result<(), MokshaMintError> {
+        validate_proofs("inputs", &self.inputs)?;
+        validate_outputs(&self.outputs)
+    }
+}
+
+impl Validate for PostMeltRequest {
+    fn validate(&self) -> Result<(), MokshaMintError> {
+        if self.pr.trim().is_empty() {
+            return Err(invalid_request("pr", "must not be empty"));
+        }
+        // the outputs are blank outputs for the change (NUT-08), so their amount is 0
+        validate_proofs("proofs", &self.proofs)
+    }
+}
+
+impl Validate for PostMeltBolt11Request {
+    fn validate(&self) -> Result<(), MokshaMintError> {
+        if self.quote.trim().is_empty() {
+            return Err(invalid_request("quote", "must not be empty"));
+        }
+        // the outputs are blank outputs for the change (NUT-08), so their amount is 0
+        validate_proofs("inputs", &self.inputs)
+    }
+}
+
+fn invalid_request(field: &str, reason: &str) -> MokshaMintError {
+    MokshaMintError::InvalidRequest(field.to_owned(), reason.to_owned())
+}
+
+fn validate_proofs(field: &str, proofs: &Proofs) -> Result<(), MokshaMintError> {
+    if proofs.is_empty() {
+        return Err(invalid_request(field, "must not be empty"));
+    }
+    if proofs.proofs().iter().any(|proof| proof.amount == 0) {
+        return Err(invalid_request(field, "amount must be greater than 0"));
+    }
+    Ok(())
+}
+
+fn validate_outputs(outputs: &[BlindedMessage]) -> Result<(), MokshaMintError> {
+    if outputs.iter().any(|output| output.amount == 0) {
+        return Err(invalid_request("outputs", "amount must be greater than 0"));
+    }
+    Ok(())
+}
+
+/// Json extractor that rejects bodies which can't be deserialized or fail validation with
+/// `MokshaMintError::InvalidRequest`
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = MokshaMintError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        // invalid pubkeys are already rejected while deserializing
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection| {
+                MokshaMintError::InvalidRequest("body".to_owned(), rejection.body_text())
+            })?;
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::{
+        fixture::read_fixture_as,
+        primitives::{PostMeltRequest, PostSplitRequest},
+        proof::Proofs,
+    };
+
+    use super::Validate;
+    use crate::error::MokshaMintError;
+
+    #[test]
+    fn test_split_request_without_proofs() -> anyhow::Result<()> {
+        let request = PostSplitRequest {
+            proofs: Proofs::empty(),
+            outputs: vec![],
+        };
+
+        let result = request.validate();
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvalidRequest(field, _)) if field == "proofs"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_melt_request_without_payment_request() -> anyhow::Result<()> {
+        let split_request = read_fixture_as::<PostSplitRequest>("post_split_request_64_20.json")?;
+        let request = PostMeltRequest {
+            proofs: split_request.proofs,
+            pr: "".to_string(),
+            outputs: vec![],
+        };
+
+        let result = request.validate();
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvalidRequest(field, _)) if field == "pr"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_valid_split_request() -> anyhow::Result<()> {
+        let request = read_fixture_as::<PostSplitRequest>("post_split_request_64_20.json")?;
+        assert!(request.validate().is_ok());
+        Ok(())
+    }
+}
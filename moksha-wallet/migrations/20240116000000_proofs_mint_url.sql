@@ -0,0 +1,4 @@
+ALTER TABLE proofs ADD COLUMN mint_url TEXT;
+
+-- proofs that were stored before the mint url was tracked belong to the mint of their keyset
+UPDATE proofs SET mint_url = (SELECT mint_url FROM keysets WHERE keysets.id = proofs.keyset_id);
@@ -0,0 +1,2 @@
+-- frozen proofs are excluded from the automatic selection of proofs (coin control)
+ALTER TABLE proofs ADD COLUMN frozen BOOL NOT NULL DEFAULT FALSE;
@@ -5,10 +5,10 @@ use moksha_core::{
     blind::BlindedMessage,
     keyset::{Keysets, V1Keysets},
     primitives::{
-        CheckFeesResponse, CurrencyUnit, KeysResponse, MintInfoResponse, MintLegacyInfoResponse,
-        PaymentRequest, PostMeltBolt11Response, PostMeltQuoteBolt11Response, PostMeltResponse,
-        PostMintBolt11Response, PostMintQuoteBolt11Response, PostMintResponse, PostSplitResponse,
-        PostSwapResponse,
+        CheckFeesResponse, CheckSpendableResponse, CurrencyUnit, KeysResponse, MintInfoResponse,
+        MintLegacyInfoResponse, PaymentRequest, PostMeltBolt11Response,
+        PostMeltQuoteBolt11Response, PostMeltResponse, PostMintBolt11Response,
+        PostMintQuoteBolt11Response, PostMintResponse, PostSplitResponse, PostSwapResponse,
     },
     proof::Proofs,
 };
@@ -50,6 +50,12 @@ pub trait LegacyClient {
         pr: String,
     ) -> Result<CheckFeesResponse, MokshaWalletError>;
 
+    async fn post_check_spendable(
+        &self,
+        mint_url: &Url,
+        proofs: Proofs,
+    ) -> Result<CheckSpendableResponse, MokshaWalletError>;
+
     async fn get_mint_keys(
         &self,
         mint_url: &Url,
@@ -5,12 +5,12 @@ use moksha_core::{
     blind::BlindedMessage,
     keyset::{Keysets, V1Keysets},
     primitives::{
-        CashuErrorResponse, CheckFeesRequest, CheckFeesResponse, CurrencyUnit, KeysResponse,
-        MintInfoResponse, MintLegacyInfoResponse, PaymentRequest, PostMeltBolt11Response,
-        PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response, PostMeltRequest, PostMeltResponse,
-        PostMintBolt11Request, PostMintBolt11Response, PostMintQuoteBolt11Request,
-        PostMintQuoteBolt11Response, PostMintRequest, PostMintResponse, PostSplitRequest,
-        PostSplitResponse, PostSwapResponse,
+        CashuErrorResponse, CheckFeesRequest, CheckFeesResponse, CheckSpendableRequest,
+        CheckSpendableResponse, CurrencyUnit, KeysResponse, MintInfoResponse,
+        MintLegacyInfoResponse, PaymentRequest, PostMeltBolt11Response, PostMeltQuoteBolt11Request,
+        PostMeltQuoteBolt11Response, PostMeltRequest, PostMeltResponse, PostMintBolt11Request,
+        PostMintBolt11Response, PostMintQuoteBolt11Request, PostMintQuoteBolt11Response,
+        PostMintRequest, PostMintResponse, PostSplitRequest, PostSplitResponse, PostSwapResponse,
     },
     proof::Proofs,
 };
@@ -126,6 +126,24 @@ impl LegacyClient for HttpClient {
         extract_response_data::<CheckFeesResponse>(resp).await
     }
 
+    async fn post_check_spendable(
+        &self,
+        mint_url: &Url,
+        proofs: Proofs,
+    ) -> Result<CheckSpendableResponse, MokshaWalletError> {
+        let body = serde_json::to_string(&CheckSpendableRequest { proofs })?;
+
+        let resp = self
+            .request_client
+            .post(mint_url.join("check")?)
+            .header(CONTENT_TYPE, HeaderValue::from_str("application/json")?)
+            .body(body)
+            .send()
+            .await?;
+
+        extract_response_data::<CheckSpendableResponse>(resp).await
+    }
+
     async fn get_mint_keys(
         &self,
         mint_url: &Url,
@@ -175,7 +193,10 @@ impl Client for HttpClient {
             .get(mint_url.join("v1/keys")?)
             .send()
             .await?;
-        extract_response_data::<KeysResponse>(resp).await
+        let keys = extract_response_data::<KeysResponse>(resp).await?;
+        // detects keys that have been swapped by a man in the middle
+        keys.verify_keyset_ids()?;
+        Ok(keys)
     }
 
     async fn get_keys_by_id(
@@ -185,7 +206,9 @@ impl Client for HttpClient {
     ) -> Result<KeysResponse, MokshaWalletError> {
         let url = mint_url.join(&format!("v1/keys/{}", keyset_id))?;
         let resp = self.request_client.get(url).send().await?;
-        extract_response_data::<KeysResponse>(resp).await
+        let keys = extract_response_data::<KeysResponse>(resp).await?;
+        keys.verify_keyset_ids()?;
+        Ok(keys)
     }
 
     async fn get_keysets(&self, mint_url: &Url) -> Result<V1Keysets, MokshaWalletError> {
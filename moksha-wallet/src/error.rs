@@ -44,15 +44,36 @@ pub enum MokshaWalletError {
     #[error("Invalid Proofs")]
     InvalidProofs,
 
+    #[error("Invalid change - {0}")]
+    InvalidChange(String),
+
+    #[error("Invalid DLEQ proof - the mint didn't sign with its advertised key")]
+    InvalidDleq,
+
     #[error("Not enough tokens")]
     NotEnoughTokens,
 
+    #[error("No stored proof with id {0}")]
+    ProofNotFound(String),
+
     #[error("Failed to decode payment request {0} - Error {1}")]
     DecodeInvoice(String, ParseOrSemanticError),
 
     #[error("Invalid invoice {0}")]
     InvalidInvoice(String),
 
+    #[error("Invoice amount mismatch - requested {0} sats, but the invoice is for {1} sats")]
+    InvoiceAmountMismatch(u64, u64),
+
+    #[error("Amount {0} can't be split into the denominations of the mint")]
+    UnsupportedAmount(u64),
+
+    #[error("Amount {0} must be less than the token amount of {1} sats to leave a remainder")]
+    InvalidPartialAmount(u64, u64),
+
+    #[error("Invalid history entry kind {0}")]
+    InvalidHistoryKind(String),
+
     #[error("URLParseError - {0}")]
     Url(#[from] url::ParseError),
 }
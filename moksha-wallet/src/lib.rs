@@ -2,6 +2,7 @@ pub mod client;
 pub mod error;
 
 pub mod localstore;
+pub mod mint_info;
 pub mod wallet;
 
 pub mod btcprice;
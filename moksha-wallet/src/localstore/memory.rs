@@ -1,38 +1,94 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use moksha_core::proof::{Proof, Proofs};
 use tokio::sync::Mutex;
+use url::Url;
 
 use crate::error::MokshaWalletError;
 
-use super::{LocalStore, WalletKeyset};
+use super::{HistoryEntry, LocalStore, WalletKeyset};
 
 #[derive(Default, Debug, Clone)]
 pub struct MemoryLocalStore {
-    proofs: Arc<Mutex<Vec<Proof>>>,
+    proofs: Arc<Mutex<Vec<(Url, Proof)>>>,
+    frozen_secrets: Arc<Mutex<HashSet<String>>>,
+    history: Arc<Mutex<Vec<HistoryEntry>>>,
 }
 
 #[async_trait(?Send)]
 impl LocalStore for MemoryLocalStore {
-    async fn add_proofs(&self, proofs: &Proofs) -> Result<(), MokshaWalletError> {
+    async fn add_proofs(&self, mint_url: &Url, proofs: &Proofs) -> Result<(), MokshaWalletError> {
         for proof in proofs.proofs() {
-            self.proofs.lock().await.push(proof.clone());
+            self.proofs.lock().await.push((mint_url.clone(), proof));
         }
         Ok(())
     }
 
-    async fn get_proofs(&self) -> Result<moksha_core::proof::Proofs, MokshaWalletError> {
-        Ok(Proofs::new(self.proofs.lock().await.clone()))
+    async fn get_proofs(&self, mint_url: &Url) -> Result<Proofs, MokshaWalletError> {
+        Ok(Proofs::new(
+            self.proofs
+                .lock()
+                .await
+                .iter()
+                .filter(|(url, _)| url == mint_url)
+                .map(|(_, proof)| proof.clone())
+                .collect(),
+        ))
+    }
+
+    async fn get_all_proofs(&self) -> Result<BTreeMap<Url, Proofs>, MokshaWalletError> {
+        let mut all_proofs = BTreeMap::<Url, Vec<Proof>>::new();
+        for (mint_url, proof) in self.proofs.lock().await.iter() {
+            all_proofs
+                .entry(mint_url.clone())
+                .or_default()
+                .push(proof.clone());
+        }
+        Ok(all_proofs
+            .into_iter()
+            .map(|(mint_url, proofs)| (mint_url, Proofs::new(proofs)))
+            .collect())
+    }
+
+    async fn set_missing_mint_url(&self, _mint_url: &Url) -> Result<(), MokshaWalletError> {
+        // every proof is stored with its mint url
+        Ok(())
     }
 
     async fn delete_proofs(&self, proofs_to_delete: &Proofs) -> Result<(), MokshaWalletError> {
         for proof in proofs_to_delete.proofs() {
-            self.proofs.lock().await.retain(|p| p != &proof);
+            self.proofs.lock().await.retain(|(_, p)| p != &proof);
         }
         Ok(())
     }
 
+    async fn set_proof_frozen(&self, secret: &str, frozen: bool) -> Result<(), MokshaWalletError> {
+        let mut frozen_secrets = self.frozen_secrets.lock().await;
+        if frozen {
+            frozen_secrets.insert(secret.to_owned());
+        } else {
+            frozen_secrets.remove(secret);
+        }
+        Ok(())
+    }
+
+    async fn get_frozen_secrets(&self) -> Result<HashSet<String>, MokshaWalletError> {
+        let proofs = self.proofs.lock().await;
+        // proofs that have been deleted are no longer frozen
+        Ok(self
+            .frozen_secrets
+            .lock()
+            .await
+            .iter()
+            .filter(|secret| proofs.iter().any(|(_, proof)| &proof.secret == *secret))
+            .cloned()
+            .collect())
+    }
+
     async fn get_keysets(&self) -> Result<Vec<WalletKeyset>, MokshaWalletError> {
         // FIXME todo implement
         Ok(vec![WalletKeyset {
@@ -45,14 +101,31 @@ impl LocalStore for MemoryLocalStore {
         // FIXME todo implement
         Ok(())
     }
+
+    async fn add_history_entry(&self, entry: &HistoryEntry) -> Result<(), MokshaWalletError> {
+        self.history.lock().await.push(entry.clone());
+        Ok(())
+    }
+
+    async fn get_history(&self) -> Result<Vec<HistoryEntry>, MokshaWalletError> {
+        Ok(self.history.lock().await.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use moksha_core::proof::{Proof, Proofs};
+    use moksha_core::{
+        fixture::read_fixture,
+        proof::{Proof, Proofs},
+        token::TokenV3,
+    };
     use serde_json::json;
 
+    fn mint_url() -> Url {
+        Url::parse("http://127.0.0.1:3338").expect("invalid url")
+    }
+
     #[tokio::test]
     async fn test_add_proofs() -> anyhow::Result<()> {
         let local_store = MemoryLocalStore::default();
@@ -76,9 +149,9 @@ mod tests {
         ))?;
 
         let proofs = Proofs::new(vec![proof1, proof2]);
-        assert_eq!(local_store.get_proofs().await?.len(), 0);
-        local_store.add_proofs(&proofs).await?;
-        let stored_proofs = local_store.get_proofs().await?;
+        assert_eq!(local_store.get_proofs(&mint_url()).await?.len(), 0);
+        local_store.add_proofs(&mint_url(), &proofs).await?;
+        let stored_proofs = local_store.get_proofs(&mint_url()).await?;
         assert_eq!(stored_proofs.proofs().len(), 2);
         assert_eq!(stored_proofs.total_amount(), 10);
 
@@ -117,19 +190,52 @@ mod tests {
             ))?;
 
         let proofs = Proofs::new(vec![proof1, proof2, proof3.clone()]);
-        assert_eq!(local_store.get_proofs().await?.len(), 0);
-        local_store.add_proofs(&proofs).await?;
-        let stored_proofs = local_store.get_proofs().await?;
+        assert_eq!(local_store.get_proofs(&mint_url()).await?.len(), 0);
+        local_store.add_proofs(&mint_url(), &proofs).await?;
+        let stored_proofs = local_store.get_proofs(&mint_url()).await?;
         assert_eq!(stored_proofs.proofs().len(), 3);
         assert_eq!(stored_proofs.total_amount(), 74);
 
         local_store
             .delete_proofs(&Proofs::new(vec![proof3]))
             .await?;
-        let proofs_after_delete = local_store.get_proofs().await?;
+        let proofs_after_delete = local_store.get_proofs(&mint_url()).await?;
         assert_eq!(proofs_after_delete.proofs().len(), 2);
         assert_eq!(proofs_after_delete.total_amount(), 10);
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_proofs_of_multiple_mints() -> anyhow::Result<()> {
+        let local_store = MemoryLocalStore::default();
+        let other_mint_url = Url::parse("https://mint.example.com")?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let proofs = tokens.proofs().proofs();
+        local_store
+            .add_proofs(&mint_url(), &Proofs::new(proofs[0..2].to_vec()))
+            .await?;
+        local_store
+            .add_proofs(&other_mint_url, &Proofs::new(proofs[2..].to_vec()))
+            .await?;
+
+        assert_eq!(
+            12,
+            local_store.get_proofs(&mint_url()).await?.total_amount()
+        );
+        assert_eq!(
+            48,
+            local_store
+                .get_proofs(&other_mint_url)
+                .await?
+                .total_amount()
+        );
+
+        let all_proofs = local_store.get_all_proofs().await?;
+        assert_eq!(2, all_proofs.len());
+        assert_eq!(12, all_proofs[&mint_url()].total_amount());
+        assert_eq!(48, all_proofs[&other_mint_url].total_amount());
+        Ok(())
+    }
 }
@@ -1,5 +1,12 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
 use async_trait::async_trait;
 use moksha_core::proof::Proofs;
+use url::Url;
 
 use crate::error::MokshaWalletError;
 
@@ -17,12 +24,127 @@ pub struct WalletKeyset {
     pub mint_url: String,
 }
 
+/// the kind of operation that is recorded in the transaction history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryKind {
+    Mint,
+    Send,
+    Receive,
+    Melt,
+}
+
+impl Display for HistoryKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::Mint => "mint",
+            Self::Send => "send",
+            Self::Receive => "receive",
+            Self::Melt => "melt",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+impl FromStr for HistoryKind {
+    type Err = MokshaWalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mint" => Ok(Self::Mint),
+            "send" => Ok(Self::Send),
+            "receive" => Ok(Self::Receive),
+            "melt" => Ok(Self::Melt),
+            _ => Err(MokshaWalletError::InvalidHistoryKind(s.to_owned())),
+        }
+    }
+}
+
+/// An operation of the wallet as recorded in the transaction history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// seconds since the unix epoch
+    pub timestamp: u64,
+    pub kind: HistoryKind,
+    /// amount in sats. For melts this includes the fees that were paid
+    pub amount: u64,
+    pub mint_url: Url,
+}
+
+impl HistoryEntry {
+    /// Creates an entry with the current time as timestamp
+    pub fn new(kind: HistoryKind, amount: u64, mint_url: Url) -> Self {
+        Self {
+            timestamp: unix_timestamp(),
+            kind,
+            amount,
+            mint_url,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+// std::time::SystemTime is not available in the browser
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn date_now() -> f64;
+}
+
+#[cfg(target_arch = "wasm32")]
+fn unix_timestamp() -> u64 {
+    (date_now() / 1000.0) as u64
+}
+
 #[async_trait(?Send)]
 pub trait LocalStore {
     async fn delete_proofs(&self, proofs: &Proofs) -> Result<(), MokshaWalletError>;
-    async fn add_proofs(&self, proofs: &Proofs) -> Result<(), MokshaWalletError>;
-    async fn get_proofs(&self) -> Result<Proofs, MokshaWalletError>;
+    async fn add_proofs(&self, mint_url: &Url, proofs: &Proofs) -> Result<(), MokshaWalletError>;
+    /// Returns the proofs that have been issued by the given mint
+    async fn get_proofs(&self, mint_url: &Url) -> Result<Proofs, MokshaWalletError>;
+    /// Returns the proofs of all mints by mint url
+    async fn get_all_proofs(&self) -> Result<BTreeMap<Url, Proofs>, MokshaWalletError>;
+    /// Returns the balance of every mint the localstore holds proofs of. The balance is computed
+    /// from the local proofs, so the mints don't have to be reachable. Frozen proofs are not
+    /// part of the balance.
+    async fn get_balances(&self) -> Result<BTreeMap<Url, u64>, MokshaWalletError> {
+        let frozen_secrets = self.get_frozen_secrets().await?;
+        Ok(self
+            .get_all_proofs()
+            .await?
+            .into_iter()
+            .map(|(mint_url, proofs)| {
+                let balance = proofs
+                    .proofs()
+                    .iter()
+                    .filter(|proof| !frozen_secrets.contains(&proof.secret))
+                    .map(|proof| proof.amount)
+                    .sum();
+                (mint_url, balance)
+            })
+            .collect())
+    }
+    /// Assigns the mint url to the proofs that were stored before the mint url was tracked and
+    /// whose mint couldn't be derived from their keyset. Otherwise these proofs don't belong to
+    /// any mint and are missing from the balance.
+    async fn set_missing_mint_url(&self, mint_url: &Url) -> Result<(), MokshaWalletError>;
+
+    /// Freezes or unfreezes the proof with the given secret. Frozen proofs are kept, but excluded
+    /// from the automatic selection of proofs for sending and paying.
+    async fn set_proof_frozen(&self, secret: &str, frozen: bool) -> Result<(), MokshaWalletError>;
+    /// Returns the secrets of all frozen proofs
+    async fn get_frozen_secrets(&self) -> Result<HashSet<String>, MokshaWalletError>;
 
     async fn get_keysets(&self) -> Result<Vec<WalletKeyset>, MokshaWalletError>;
     async fn add_keyset(&self, keyset: &WalletKeyset) -> Result<(), MokshaWalletError>;
+
+    async fn add_history_entry(&self, entry: &HistoryEntry) -> Result<(), MokshaWalletError>;
+    /// Returns the transaction history of all mints, oldest entry first
+    async fn get_history(&self) -> Result<Vec<HistoryEntry>, MokshaWalletError>;
 }
@@ -1,14 +1,39 @@
-use super::{LocalStore, WalletKeyset};
+use std::collections::{BTreeMap, HashSet};
+
+use super::{HistoryEntry, HistoryKind, LocalStore, WalletKeyset};
 use crate::error::MokshaWalletError;
 use async_trait::async_trait;
 use moksha_core::proof::{Proof, Proofs};
 use rexie::*;
+use serde::{Deserialize, Serialize};
+use url::Url;
 use wasm_bindgen::JsValue;
 
 #[derive(Clone, Default)]
 pub struct RexieLocalStore;
 
 const STORE_NAME: &str = "proofs";
+const HISTORY_STORE_NAME: &str = "history";
+
+/// proof together with the url of the mint that issued it. Proofs that were stored before the
+/// mint url was tracked don't have a mint url until `set_missing_mint_url` assigns one.
+#[derive(Serialize, Deserialize)]
+struct StoredProof {
+    mint_url: Option<String>,
+    #[serde(default)]
+    frozen: bool,
+    #[serde(flatten)]
+    proof: Proof,
+}
+
+/// history entry as stored in indexeddb
+#[derive(Serialize, Deserialize)]
+struct StoredHistoryEntry {
+    timestamp: u64,
+    kind: String,
+    amount: u64,
+    mint_url: String,
+}
 
 impl RexieLocalStore {
     pub async fn new() -> Self {
@@ -19,8 +44,9 @@ impl RexieLocalStore {
 impl RexieLocalStore {
     async fn get_rexie() -> Rexie {
         Rexie::builder("moksha")
-            .version(1)
+            .version(2)
             .add_object_store(ObjectStore::new(STORE_NAME))
+            .add_object_store(ObjectStore::new(HISTORY_STORE_NAME).auto_increment(true))
             .build()
             .await
             .unwrap()
@@ -33,11 +59,33 @@ impl RexieLocalStore {
         let key = serde_json::to_string(&key).unwrap();
         serde_wasm_bindgen::to_value(&key).unwrap()
     }
+
+    async fn get_stored_proofs() -> Vec<StoredProof> {
+        let db = Self::get_rexie().await;
+        let transaction = db
+            .transaction(&[STORE_NAME], rexie::TransactionMode::ReadOnly)
+            .expect("db error");
+        let store = transaction.store(STORE_NAME).expect("db error");
+        match store.get_all(None, None, None, None).await {
+            Ok(all) => all
+                .into_iter()
+                .map(|(_, proof)| {
+                    let proof: String = serde_wasm_bindgen::from_value(proof).unwrap();
+                    serde_json::from_str::<StoredProof>(&proof).unwrap()
+                })
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
 }
 
 #[async_trait(?Send)]
 impl LocalStore for RexieLocalStore {
-    async fn add_proofs(&self, proofs: &Proofs) -> std::result::Result<(), MokshaWalletError> {
+    async fn add_proofs(
+        &self,
+        mint_url: &Url,
+        proofs: &Proofs,
+    ) -> std::result::Result<(), MokshaWalletError> {
         let db = Self::get_rexie().await;
 
         for proof in proofs.proofs() {
@@ -45,7 +93,12 @@ impl LocalStore for RexieLocalStore {
                 .transaction(&[STORE_NAME], rexie::TransactionMode::ReadWrite)
                 .expect("db error");
             let store = transaction.store(STORE_NAME).expect("db error");
-            let json = serde_json::to_string(&proof).unwrap();
+            let json = serde_json::to_string(&StoredProof {
+                mint_url: Some(mint_url.to_string()),
+                frozen: false,
+                proof: proof.clone(),
+            })
+            .unwrap();
             let js_value = serde_wasm_bindgen::to_value(&json).unwrap();
 
             store
@@ -58,25 +111,64 @@ impl LocalStore for RexieLocalStore {
         Ok(())
     }
 
-    async fn get_proofs(&self) -> std::result::Result<Proofs, MokshaWalletError> {
+    async fn get_proofs(&self, mint_url: &Url) -> std::result::Result<Proofs, MokshaWalletError> {
+        Ok(Proofs::new(
+            Self::get_stored_proofs()
+                .await
+                .into_iter()
+                .filter(|stored| stored.mint_url.as_deref() == Some(mint_url.as_str()))
+                .map(|stored| stored.proof)
+                .collect(),
+        ))
+    }
+
+    async fn get_all_proofs(
+        &self,
+    ) -> std::result::Result<BTreeMap<Url, Proofs>, MokshaWalletError> {
+        let mut all_proofs = BTreeMap::<Url, Vec<Proof>>::new();
+        for stored in Self::get_stored_proofs().await {
+            if let Some(mint_url) = stored.mint_url {
+                all_proofs
+                    .entry(Url::parse(&mint_url)?)
+                    .or_default()
+                    .push(stored.proof);
+            }
+        }
+        Ok(all_proofs
+            .into_iter()
+            .map(|(mint_url, proofs)| (mint_url, Proofs::new(proofs)))
+            .collect())
+    }
+
+    async fn set_missing_mint_url(
+        &self,
+        mint_url: &Url,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        let missing = Self::get_stored_proofs()
+            .await
+            .into_iter()
+            .filter(|stored| stored.mint_url.is_none())
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
         let db = Self::get_rexie().await;
         let transaction = db
-            .transaction(&[STORE_NAME], rexie::TransactionMode::ReadOnly)
+            .transaction(&[STORE_NAME], rexie::TransactionMode::ReadWrite)
             .expect("db error");
         let store = transaction.store(STORE_NAME).expect("db error");
-        let all = store.get_all(None, None, None, None).await;
-        match all {
-            Ok(all) => {
-                let mut proofs = vec![];
-                for (_, proof) in all {
-                    let proof: String = serde_wasm_bindgen::from_value(proof).unwrap();
-                    let proof = serde_json::from_str::<Proof>(&proof).unwrap();
-                    proofs.push(proof);
-                }
-                Ok(Proofs::new(proofs))
-            }
-            Err(_) => Ok(Proofs::new(vec![])),
+        for mut stored in missing {
+            stored.mint_url = Some(mint_url.to_string());
+            let json = serde_json::to_string(&stored)?;
+            let js_value = serde_wasm_bindgen::to_value(&json).unwrap();
+            store
+                .put(&js_value, Some(&Self::get_key(&stored.proof)))
+                .await
+                .expect("db store error");
         }
+        transaction.done().await.expect("db error");
+        Ok(())
     }
 
     async fn delete_proofs(
@@ -101,6 +193,44 @@ impl LocalStore for RexieLocalStore {
         Ok(())
     }
 
+    async fn set_proof_frozen(
+        &self,
+        secret: &str,
+        frozen: bool,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        let Some(mut stored) = Self::get_stored_proofs()
+            .await
+            .into_iter()
+            .find(|stored| stored.proof.secret == secret)
+        else {
+            return Ok(());
+        };
+        stored.frozen = frozen;
+
+        let db = Self::get_rexie().await;
+        let transaction = db
+            .transaction(&[STORE_NAME], rexie::TransactionMode::ReadWrite)
+            .expect("db error");
+        let store = transaction.store(STORE_NAME).expect("db error");
+        let json = serde_json::to_string(&stored)?;
+        let js_value = serde_wasm_bindgen::to_value(&json).unwrap();
+        store
+            .put(&js_value, Some(&Self::get_key(&stored.proof)))
+            .await
+            .expect("db store error");
+        transaction.done().await.expect("db error");
+        Ok(())
+    }
+
+    async fn get_frozen_secrets(&self) -> std::result::Result<HashSet<String>, MokshaWalletError> {
+        Ok(Self::get_stored_proofs()
+            .await
+            .into_iter()
+            .filter(|stored| stored.frozen)
+            .map(|stored| stored.proof.secret)
+            .collect())
+    }
+
     async fn get_keysets(&self) -> std::result::Result<Vec<WalletKeyset>, MokshaWalletError> {
         // FIXME todo implement
         Ok(vec![WalletKeyset {
@@ -116,4 +246,50 @@ impl LocalStore for RexieLocalStore {
         // FIXME todo implement
         Ok(())
     }
+    async fn add_history_entry(
+        &self,
+        entry: &HistoryEntry,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        let db = Self::get_rexie().await;
+        let transaction = db
+            .transaction(&[HISTORY_STORE_NAME], rexie::TransactionMode::ReadWrite)
+            .expect("db error");
+        let store = transaction.store(HISTORY_STORE_NAME).expect("db error");
+        let json = serde_json::to_string(&StoredHistoryEntry {
+            timestamp: entry.timestamp,
+            kind: entry.kind.to_string(),
+            amount: entry.amount,
+            mint_url: entry.mint_url.to_string(),
+        })?;
+        let js_value = serde_wasm_bindgen::to_value(&json).unwrap();
+        store.add(&js_value, None).await.expect("db store error");
+        transaction.done().await.expect("db error");
+        Ok(())
+    }
+
+    async fn get_history(&self) -> std::result::Result<Vec<HistoryEntry>, MokshaWalletError> {
+        let db = Self::get_rexie().await;
+        let transaction = db
+            .transaction(&[HISTORY_STORE_NAME], rexie::TransactionMode::ReadOnly)
+            .expect("db error");
+        let store = transaction.store(HISTORY_STORE_NAME).expect("db error");
+        // the auto incremented keys keep the entries in insertion order
+        let Ok(all) = store.get_all(None, None, None, None).await else {
+            return Ok(vec![]);
+        };
+        all.into_iter()
+            .map(
+                |(_, entry)| -> std::result::Result<HistoryEntry, MokshaWalletError> {
+                    let entry: String = serde_wasm_bindgen::from_value(entry).unwrap();
+                    let stored = serde_json::from_str::<StoredHistoryEntry>(&entry)?;
+                    Ok(HistoryEntry {
+                        timestamp: stored.timestamp,
+                        kind: stored.kind.parse::<HistoryKind>()?,
+                        amount: stored.amount,
+                        mint_url: Url::parse(&stored.mint_url)?,
+                    })
+                },
+            )
+            .collect()
+    }
 }
@@ -1,10 +1,13 @@
+use std::collections::{BTreeMap, HashSet};
+
 use async_trait::async_trait;
 use moksha_core::proof::{Proof, Proofs};
+use url::Url;
 
 use crate::error::MokshaWalletError;
-use crate::localstore::{LocalStore, WalletKeyset};
+use crate::localstore::{HistoryEntry, LocalStore, WalletKeyset};
 
-use sqlx::sqlite::SqliteError;
+use sqlx::sqlite::{SqliteError, SqliteRow};
 
 use sqlx::Row;
 
@@ -30,17 +33,18 @@ impl LocalStore for SqliteLocalStore {
         Ok(())
     }
 
-    async fn add_proofs(&self, proofs: &Proofs) -> Result<(), MokshaWalletError> {
+    async fn add_proofs(&self, mint_url: &Url, proofs: &Proofs) -> Result<(), MokshaWalletError> {
         let tx = self.start_transaction().await?;
         for proof in proofs.proofs() {
             sqlx::query(
-                r#"INSERT INTO proofs (keyset_id, amount, C, secret, time_created) VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP);
+                r#"INSERT INTO proofs (keyset_id, amount, C, secret, time_created, mint_url) VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, $5);
                 "#,
             )
             .bind(proof.keyset_id)
             .bind(proof.amount as i64) // FIXME use u64
             .bind(proof.c.to_string())
             .bind(proof.secret)
+            .bind(mint_url.to_string())
             .execute(&self.pool)
             .await?;
         }
@@ -48,31 +52,66 @@ impl LocalStore for SqliteLocalStore {
         Ok(())
     }
 
-    async fn get_proofs(&self) -> Result<Proofs, MokshaWalletError> {
-        let rows = sqlx::query("SELECT * FROM proofs;")
-            .fetch_all(&self.pool)
-            .await?;
+    async fn get_proofs(&self, mint_url: &Url) -> Result<Proofs, MokshaWalletError> {
+        let rows = sqlx::query(
+            "SELECT keyset_id, amount, C, secret, mint_url FROM proofs WHERE mint_url = $1;",
+        )
+        .bind(mint_url.to_string())
+        .fetch_all(&self.pool)
+        .await?;
 
         Ok(rows
             .iter()
-            .map(|row| {
-                let id = row.get(0);
-                let amount: i64 = row.get(1);
-                let c: String = row.get(2);
-                let secret: String = row.get(3);
-                let _time_created: String = row.get(4); // TODO use time_created
-                Ok(Proof {
-                    keyset_id: id,
-                    amount: amount as u64,
-                    c: c.parse().unwrap(),
-                    secret,
-                    script: None,
-                })
-            })
+            .map(Self::proof_from_row)
             .collect::<Result<Vec<Proof>, SqliteError>>()?
             .into())
     }
 
+    async fn get_all_proofs(&self) -> Result<BTreeMap<Url, Proofs>, MokshaWalletError> {
+        let rows = sqlx::query(
+            "SELECT keyset_id, amount, C, secret, mint_url FROM proofs WHERE mint_url IS NOT NULL;",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut all_proofs = BTreeMap::<Url, Vec<Proof>>::new();
+        for row in rows.iter() {
+            let mint_url: String = row.get(4);
+            all_proofs
+                .entry(Url::parse(&mint_url)?)
+                .or_default()
+                .push(Self::proof_from_row(row)?);
+        }
+        Ok(all_proofs
+            .into_iter()
+            .map(|(mint_url, proofs)| (mint_url, proofs.into()))
+            .collect())
+    }
+
+    async fn set_missing_mint_url(&self, mint_url: &Url) -> Result<(), MokshaWalletError> {
+        sqlx::query("UPDATE proofs SET mint_url = $1 WHERE mint_url IS NULL;")
+            .bind(mint_url.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_proof_frozen(&self, secret: &str, frozen: bool) -> Result<(), MokshaWalletError> {
+        sqlx::query("UPDATE proofs SET frozen = $1 WHERE secret = $2;")
+            .bind(frozen)
+            .bind(secret)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_frozen_secrets(&self) -> Result<HashSet<String>, MokshaWalletError> {
+        let rows = sqlx::query("SELECT secret FROM proofs WHERE frozen;")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| row.get::<String, _>(0)).collect())
+    }
+
     async fn add_keyset(&self, keyset: &WalletKeyset) -> Result<(), MokshaWalletError> {
         sqlx::query(
             r#"INSERT INTO keysets (id, mint_url) VALUES ($1, $2);
@@ -99,6 +138,41 @@ impl LocalStore for SqliteLocalStore {
             })
             .collect::<Result<Vec<WalletKeyset>, SqliteError>>()?)
     }
+
+    async fn add_history_entry(&self, entry: &HistoryEntry) -> Result<(), MokshaWalletError> {
+        sqlx::query(
+            "INSERT INTO history (timestamp, kind, amount, mint_url) VALUES ($1, $2, $3, $4);",
+        )
+        .bind(entry.timestamp as i64)
+        .bind(entry.kind.to_string())
+        .bind(entry.amount as i64)
+        .bind(entry.mint_url.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_history(&self) -> Result<Vec<HistoryEntry>, MokshaWalletError> {
+        let rows =
+            sqlx::query("SELECT timestamp, kind, amount, mint_url FROM history ORDER BY id;")
+                .fetch_all(&self.pool)
+                .await?;
+
+        rows.iter()
+            .map(|row| -> Result<HistoryEntry, MokshaWalletError> {
+                let timestamp: i64 = row.get(0);
+                let kind: String = row.get(1);
+                let amount: i64 = row.get(2);
+                let mint_url: String = row.get(3);
+                Ok(HistoryEntry {
+                    timestamp: timestamp as u64,
+                    kind: kind.parse()?,
+                    amount: amount as u64,
+                    mint_url: Url::parse(&mint_url)?,
+                })
+            })
+            .collect()
+    }
 }
 
 impl SqliteLocalStore {
@@ -110,6 +184,14 @@ impl SqliteLocalStore {
         Ok(store)
     }
 
+    fn proof_from_row(row: &SqliteRow) -> Result<Proof, SqliteError> {
+        let id = row.get(0);
+        let amount: i64 = row.get(1);
+        let c: String = row.get(2);
+        let secret: String = row.get(3);
+        Ok(Proof::new(amount as u64, secret, c.parse().unwrap(), id))
+    }
+
     async fn migrate(&self) {
         sqlx::migrate!("./migrations")
             .run(&self.pool)
@@ -133,12 +215,13 @@ impl SqliteLocalStore {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{collections::HashSet, sync::Arc};
 
-    use moksha_core::{fixture::read_fixture, token::TokenV3};
+    use moksha_core::{fixture::read_fixture, proof::Proofs, token::TokenV3};
+    use url::Url;
 
     use super::SqliteLocalStore;
-    use crate::localstore::LocalStore;
+    use crate::localstore::{HistoryEntry, HistoryKind, LocalStore};
 
     #[tokio::test]
     async fn test_sqlite() -> anyhow::Result<()> {
@@ -153,15 +236,84 @@ mod tests {
             .trim()
             .to_string()
             .try_into()?;
+        let mint_url = tokens.mint().expect("mint is empty");
         let store: Arc<dyn LocalStore> = Arc::new(db.clone());
-        store.add_proofs(&tokens.proofs()).await?;
+        store.add_proofs(&mint_url, &tokens.proofs()).await?;
         db.commit_transaction(tx).await?;
 
-        let loaded_proofs = store.get_proofs().await?;
+        let loaded_proofs = store.get_proofs(&mint_url).await?;
         assert_eq!(tokens.proofs(), loaded_proofs);
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_set_missing_mint_url() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let tmp_dir = tmp.path().to_str().expect("Could not create tmp dir");
+        let db = SqliteLocalStore::with_path(format!("{tmp_dir}/test_wallet.db")).await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mint_url = tokens.mint().expect("mint is empty");
+        let other_mint_url = Url::parse("https://mint.example.com")?;
+        let proofs = tokens.proofs().proofs();
+        db.add_proofs(&mint_url, &Proofs::new(proofs[0..2].to_vec()))
+            .await?;
+        db.add_proofs(&other_mint_url, &Proofs::new(proofs[2..].to_vec()))
+            .await?;
+        // proofs stored before the mint url was tracked
+        sqlx::query("UPDATE proofs SET mint_url = NULL WHERE mint_url = $1;")
+            .bind(mint_url.to_string())
+            .execute(&db.pool)
+            .await?;
+        assert_eq!(0, db.get_proofs(&mint_url).await?.total_amount());
+
+        db.set_missing_mint_url(&mint_url).await?;
+        assert_eq!(12, db.get_proofs(&mint_url).await?.total_amount());
+        assert_eq!(48, db.get_proofs(&other_mint_url).await?.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frozen_proofs() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let tmp_dir = tmp.path().to_str().expect("Could not create tmp dir");
+        let db = SqliteLocalStore::with_path(format!("{tmp_dir}/test_wallet.db")).await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mint_url = tokens.mint().expect("mint is empty");
+        db.add_proofs(&mint_url, &tokens.proofs()).await?;
+        let secret = tokens.proofs().proofs()[0].secret.clone();
+
+        db.set_proof_frozen(&secret, true).await?;
+        assert_eq!(
+            HashSet::from([secret.clone()]),
+            db.get_frozen_secrets().await?
+        );
+
+        db.set_proof_frozen(&secret, false).await?;
+        assert!(db.get_frozen_secrets().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_history() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let tmp_dir = tmp.path().to_str().expect("Could not create tmp dir");
+        let db = SqliteLocalStore::with_path(format!("{tmp_dir}/test_wallet.db")).await?;
+        assert!(db.get_history().await?.is_empty());
+
+        let mint_url = Url::parse("http://127.0.0.1:3338")?;
+        let entries = vec![
+            HistoryEntry::new(HistoryKind::Mint, 64, mint_url.clone()),
+            HistoryEntry::new(HistoryKind::Melt, 21, mint_url),
+        ];
+        for entry in entries.iter() {
+            db.add_history_entry(entry).await?;
+        }
+        assert_eq!(entries, db.get_history().await?);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_delete_tokens() -> anyhow::Result<()> {
         let tmp = tempfile::tempdir()?;
@@ -174,9 +326,10 @@ mod tests {
             .trim()
             .to_string()
             .try_into()?;
-        localstore.add_proofs(&tokens.proofs()).await?;
+        let mint_url = tokens.mint().expect("mint is empty");
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
 
-        let loaded_tokens = localstore.get_proofs().await?;
+        let loaded_tokens = localstore.get_proofs(&mint_url).await?;
 
         assert_eq!(tokens.proofs(), loaded_tokens);
 
@@ -191,9 +344,39 @@ mod tests {
 
         localstore.delete_proofs(&proof_4.into()).await?;
 
-        let result_tokens = localstore.get_proofs().await?;
+        let result_tokens = localstore.get_proofs(&mint_url).await?;
         assert_eq!(56, result_tokens.total_amount());
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_proofs_of_multiple_mints() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let tmp_dir = tmp.path().to_str().expect("Could not create tmp dir");
+        let localstore = SqliteLocalStore::with_path(format!("{tmp_dir}/test_wallet.db")).await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mint_url = tokens.mint().expect("mint is empty");
+        let other_mint_url = Url::parse("https://mint.example.com")?;
+        let proofs = tokens.proofs().proofs();
+        localstore
+            .add_proofs(&mint_url, &Proofs::new(proofs[0..2].to_vec()))
+            .await?;
+        localstore
+            .add_proofs(&other_mint_url, &Proofs::new(proofs[2..].to_vec()))
+            .await?;
+
+        assert_eq!(12, localstore.get_proofs(&mint_url).await?.total_amount());
+        assert_eq!(
+            48,
+            localstore.get_proofs(&other_mint_url).await?.total_amount()
+        );
+
+        let all_proofs = localstore.get_all_proofs().await?;
+        assert_eq!(2, all_proofs.len());
+        assert_eq!(12, all_proofs[&mint_url].total_amount());
+        assert_eq!(48, all_proofs[&other_mint_url].total_amount());
+        Ok(())
+    }
 }
@@ -0,0 +1,109 @@
+use std::fmt::{self, Display, Formatter};
+
+use moksha_core::{
+    keyset::V1Keysets,
+    primitives::{CurrencyUnit, MintInfoResponse, MintLegacyInfoResponse, Nuts},
+};
+
+/// Info about a mint, fetched from `/v1/info` or from the legacy `/info` endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MintInfo {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub contact: Vec<String>,
+    /// supported NUTs, e.g. `NUT-04`
+    pub nuts: Vec<String>,
+    pub active_keyset_id: Option<String>,
+    /// true if the mint only supports the legacy api
+    pub legacy: bool,
+}
+
+impl MintInfo {
+    pub fn from_v1(info: MintInfoResponse, keysets: &V1Keysets) -> Self {
+        Self {
+            name: info.name,
+            version: info.version,
+            description: info.description,
+            contact: contact_lines(info.contact),
+            nuts: supported_nuts(&info.nuts),
+            active_keyset_id: keysets
+                .keysets
+                .iter()
+                .find(|keyset| keyset.active && keyset.unit == CurrencyUnit::Sat)
+                .map(|keyset| keyset.id.clone()),
+            legacy: false,
+        }
+    }
+
+    pub fn from_legacy(info: MintLegacyInfoResponse, active_keyset_id: Option<String>) -> Self {
+        Self {
+            name: info.name,
+            version: info.version,
+            description: info.description,
+            contact: contact_lines(info.contact),
+            nuts: info.nuts,
+            active_keyset_id,
+            legacy: true,
+        }
+    }
+}
+
+impl Display for MintInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let or_dash = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_owned());
+        writeln!(f, "Name: {}", or_dash(&self.name))?;
+        writeln!(f, "Version: {}", or_dash(&self.version))?;
+        writeln!(f, "Description: {}", or_dash(&self.description))?;
+        match self.contact.is_empty() {
+            true => writeln!(f, "Contact: -")?,
+            false => writeln!(f, "Contact: {}", self.contact.join(", "))?,
+        }
+        writeln!(f, "Supported NUTs: {}", self.nuts.join(", "))?;
+        write!(f, "Active keyset: {}", or_dash(&self.active_keyset_id))?;
+        if self.legacy {
+            write!(f, "\nApi: legacy")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats contacts like `[["email", "mint@example.com"]]` as `email: mint@example.com`
+fn contact_lines(contact: Option<Vec<Vec<String>>>) -> Vec<String> {
+    contact
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| entry.join(": "))
+        .collect()
+}
+
+fn supported_nuts(nuts: &Nuts) -> Vec<String> {
+    // NUT-00 to NUT-03 and melting are required for every mint
+    let mut supported = vec!["NUT-00", "NUT-01", "NUT-02", "NUT-03"]
+        .into_iter()
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    supported.push(match nuts.nut4.disabled {
+        true => "NUT-04 (disabled)".to_owned(),
+        false => "NUT-04".to_owned(),
+    });
+    supported.push("NUT-05".to_owned());
+
+    let optional = [
+        (6, nuts.nut6.supported),
+        (7, nuts.nut7.supported),
+        (8, nuts.nut8.supported),
+        (9, nuts.nut9.supported),
+        (10, nuts.nut10.supported),
+        (11, nuts.nut11.supported),
+        (12, nuts.nut12.supported),
+        (19, !nuts.nut19.cached_endpoints.is_empty()),
+    ];
+    supported.extend(
+        optional
+            .into_iter()
+            .filter(|(_, is_supported)| *is_supported)
+            .map(|(nut, _)| format!("NUT-{nut:02}")),
+    );
+    supported
+}
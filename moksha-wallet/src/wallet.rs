@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use moksha_core::{
-    amount::Amount,
+    amount::{generate_random_string, Amount, SplitAmount},
     blind::{BlindedMessage, BlindedSignature, TotalAmount},
     dhke::Dhke,
     keyset::Keysets,
@@ -11,16 +11,34 @@ use moksha_core::{
 };
 
 use secp256k1::{PublicKey, SecretKey};
+use tracing::{event, Level};
 use url::Url;
 
 use crate::{
-    client::LegacyClient,
+    client::{Client, LegacyClient},
     error::MokshaWalletError,
-    localstore::{LocalStore, WalletKeyset},
+    localstore::{HistoryEntry, HistoryKind, LocalStore, WalletKeyset},
+    mint_info::MintInfo,
 };
 use lightning_invoice::Bolt11Invoice as LNInvoice;
 use std::str::FromStr;
 
+/// maximum number of proofs sent to the mint in a single check-spendable request
+const CHECK_SPENDABLE_BATCH_SIZE: usize = 100;
+
+/// number of hex characters of the `Y` point that are shown as id of a proof
+const PROOF_ID_LEN: usize = 8;
+
+/// A stored proof as shown for coin control
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofInfo {
+    /// short id derived from the `Y` point of the proof, which doesn't reveal the secret
+    pub id: String,
+    pub amount: u64,
+    pub keyset_id: String,
+    pub frozen: bool,
+}
+
 #[derive(Clone)]
 pub struct Wallet<C: LegacyClient, L: LocalStore> {
     client: C,
@@ -66,10 +84,16 @@ impl<C: LegacyClient, L: LocalStore> WalletBuilder<C, L> {
         let localstore = self.localstore.expect("localstore is required");
         let mint_url = self.mint_url.expect("mint_url is required");
 
+        // proofs without a mint url were stored when the wallet only supported a single mint
+        localstore.set_missing_mint_url(&mint_url).await?;
         let load_keysets = localstore.get_keysets().await?;
 
         let mint_keysets = client.get_mint_keysets(&mint_url).await?;
-        if load_keysets.is_empty() {
+        // the localstore is shared by all mints, so the keysets are stored for every new mint
+        if !load_keysets
+            .iter()
+            .any(|keyset| keyset.mint_url == mint_url.to_string())
+        {
             let wallet_keysets = mint_keysets
                 .keysets
                 .iter()
@@ -85,6 +109,8 @@ impl<C: LegacyClient, L: LocalStore> WalletBuilder<C, L> {
         }
 
         let keys = client.get_mint_keys(&mint_url).await?;
+        // the keys must belong to one of the keysets the mint advertises
+        mint_keysets.current_keyset(&keys)?;
 
         Ok(Wallet::new(
             client as C,
@@ -120,61 +146,303 @@ impl<C: LegacyClient, L: LocalStore> Wallet<C, L> {
         }
     }
 
+    /// Requests an invoice for minting `amount` sats. Fails if the invoice of the mint requests a
+    /// different amount.
     pub async fn get_mint_payment_request(
         &self,
         amount: u64,
     ) -> Result<PaymentRequest, MokshaWalletError> {
-        self.client
+        let payment_request = self
+            .client
             .get_mint_payment_request(&self.mint_url, amount)
-            .await
+            .await?;
+
+        let invoice_amount = self.get_invoice_amount(&payment_request.pr)?;
+        if invoice_amount != amount {
+            return Err(MokshaWalletError::InvoiceAmountMismatch(
+                amount,
+                invoice_amount,
+            ));
+        }
+        Ok(payment_request)
     }
 
+    /// Returns the balance at the mint of this wallet. Frozen proofs can't be spent by the
+    /// wallet, so they don't count towards the balance.
     pub async fn get_balance(&self) -> Result<u64, MokshaWalletError> {
-        Ok(self.localstore.get_proofs().await?.total_amount())
+        Ok(self.get_spendable_proofs().await?.total_amount())
+    }
+
+    /// Returns the balance of every mint the localstore holds proofs of, without frozen proofs
+    pub async fn get_balances(&self) -> Result<BTreeMap<Url, u64>, MokshaWalletError> {
+        self.localstore.get_balances().await
+    }
+
+    /// Returns the mint with the lowest fee reserve for paying the invoice among the mints that
+    /// hold enough proofs to cover the invoice amount plus the fee. Mints whose fees can't be
+    /// checked are skipped. Returns `None` if no mint has enough proofs.
+    pub async fn cheapest_mint_for_invoice(
+        &self,
+        invoice: &str,
+    ) -> Result<Option<Url>, MokshaWalletError> {
+        let invoice_amount = self.get_invoice_amount(invoice)?;
+
+        let mut cheapest: Option<(Url, u64)> = None;
+        for (mint_url, balance) in self.get_balances().await? {
+            if balance < invoice_amount {
+                continue;
+            }
+            let Ok(fees) = self
+                .client
+                .post_checkfees(&mint_url, invoice.to_owned())
+                .await
+            else {
+                continue;
+            };
+            if invoice_amount + fees.fee > balance {
+                continue;
+            }
+            if cheapest.as_ref().map_or(true, |(_, fee)| fees.fee < *fee) {
+                cheapest = Some((mint_url, fees.fee));
+            }
+        }
+        Ok(cheapest.map(|(mint_url, _)| mint_url))
+    }
+
+    /// Returns the stored proofs of the mint of this wallet
+    pub async fn list_proofs(&self) -> Result<Vec<ProofInfo>, MokshaWalletError> {
+        let frozen_secrets = self.localstore.get_frozen_secrets().await?;
+        Ok(self
+            .localstore
+            .get_proofs(&self.mint_url)
+            .await?
+            .proofs()
+            .iter()
+            .map(|proof| ProofInfo {
+                id: Self::proof_id(proof),
+                amount: proof.amount,
+                keyset_id: proof.keyset_id.clone(),
+                frozen: frozen_secrets.contains(&proof.secret),
+            })
+            .collect())
+    }
+
+    /// Excludes the proof with the given id from the automatic selection of proofs
+    pub async fn freeze_proof(&self, id: &str) -> Result<(), MokshaWalletError> {
+        let proof = self.find_proof(id).await?;
+        self.localstore.set_proof_frozen(&proof.secret, true).await
+    }
+
+    pub async fn unfreeze_proof(&self, id: &str) -> Result<(), MokshaWalletError> {
+        let proof = self.find_proof(id).await?;
+        self.localstore.set_proof_frozen(&proof.secret, false).await
+    }
+
+    fn proof_id(proof: &Proof) -> String {
+        // skip the parity byte of the compressed point
+        proof.y().to_string()[2..2 + PROOF_ID_LEN].to_owned()
+    }
+
+    async fn find_proof(&self, id: &str) -> Result<Proof, MokshaWalletError> {
+        self.localstore
+            .get_proofs(&self.mint_url)
+            .await?
+            .proofs()
+            .into_iter()
+            .find(|proof| Self::proof_id(proof) == id)
+            .ok_or_else(|| MokshaWalletError::ProofNotFound(id.to_owned()))
+    }
+
+    /// Returns the proofs of the mint of this wallet that can be selected automatically, i.e.
+    /// all proofs that aren't frozen
+    async fn get_spendable_proofs(&self) -> Result<Proofs, MokshaWalletError> {
+        let frozen_secrets = self.localstore.get_frozen_secrets().await?;
+        Ok(Proofs::new(
+            self.localstore
+                .get_proofs(&self.mint_url)
+                .await?
+                .proofs()
+                .into_iter()
+                .filter(|proof| !frozen_secrets.contains(&proof.secret))
+                .collect(),
+        ))
     }
 
-    pub async fn send_tokens(&self, amount: u64) -> Result<TokenV3, MokshaWalletError> {
-        let balance = self.get_balance().await?;
-        if amount > balance {
+    /// Splits off exactly `amount` from the stored proofs and returns them as a token that can be
+    /// sent offline. The change is kept in the localstore.
+    pub async fn send_tokens(
+        &self,
+        amount: u64,
+        memo: Option<String>,
+    ) -> Result<TokenV3, MokshaWalletError> {
+        let all_proofs = self.get_spendable_proofs().await?;
+        if amount > all_proofs.total_amount() {
             return Err(MokshaWalletError::NotEnoughTokens);
         }
 
-        let all_proofs = self.localstore.get_proofs().await?;
         let selected_proofs = all_proofs.proofs_for_amount(amount)?;
-        let selected_tokens = (self.mint_url.to_owned(), selected_proofs.clone()).into();
+        let selected_tokens: TokenV3 = (self.mint_url.to_owned(), selected_proofs.clone()).into();
+        selected_tokens.validate()?;
 
         let (remaining_tokens, result) = self.split_tokens(&selected_tokens, amount.into()).await?;
 
         // FIXME create transaction
         self.localstore.delete_proofs(&selected_proofs).await?;
         self.localstore
-            .add_proofs(&remaining_tokens.proofs())
+            .add_proofs(&self.mint_url, &remaining_tokens.proofs())
             .await?;
 
-        Ok(result)
+        let token = TokenV3 { memo, ..result };
+        if let Err(err) = token.validate() {
+            // the selected proofs are spent, so the new proofs are kept instead of being lost
+            self.localstore
+                .add_proofs(&self.mint_url, &token.proofs())
+                .await?;
+            return Err(err.into());
+        }
+        self.add_history_entry(HistoryKind::Send, amount).await;
+        Ok(token)
     }
 
+    /// Swaps the received proofs for new proofs and stores them. The new proofs are always
+    /// signed with the current keyset of the mint, so proofs of old keysets are upgraded on
+    /// receive.
     pub async fn receive_tokens(&self, tokens: &TokenV3) -> Result<(), MokshaWalletError> {
         let total_amount = tokens.total_amount();
         let (_, redeemed_tokens) = self.split_tokens(tokens, total_amount.into()).await?;
         self.localstore
-            .add_proofs(&redeemed_tokens.proofs())
+            .add_proofs(&self.mint_url, &redeemed_tokens.proofs())
             .await?;
+        self.add_history_entry(HistoryKind::Receive, redeemed_tokens.total_amount())
+            .await;
         Ok(())
     }
 
+    /// Swaps the stored proofs of old keysets for proofs of the current keyset of the mint, so
+    /// the wallet doesn't keep proofs of keysets the mint retires. Frozen proofs are left as they
+    /// are. Returns the upgraded amount.
+    pub async fn upgrade_keyset(&self) -> Result<u64, MokshaWalletError> {
+        let current_keyset = self.keysets.current_keyset(&self.mint_keys)?;
+        let old_proofs = Proofs::new(
+            self.get_spendable_proofs()
+                .await?
+                .proofs()
+                .into_iter()
+                .filter(|proof| proof.keyset_id != current_keyset)
+                .collect(),
+        );
+        if old_proofs.is_empty() {
+            return Ok(0);
+        }
+
+        let old_tokens: TokenV3 = (self.mint_url.to_owned(), old_proofs.clone()).into();
+        let (_, upgraded_tokens) = self
+            .split_tokens(&old_tokens, old_proofs.total_amount().into())
+            .await?;
+        self.localstore.delete_proofs(&old_proofs).await?;
+        self.localstore
+            .add_proofs(&self.mint_url, &upgraded_tokens.proofs())
+            .await?;
+        Ok(upgraded_tokens.total_amount())
+    }
+
+    /// Claims only `amount` of the received token. The claimed part is swapped for new proofs
+    /// and stored, the remainder is swapped as well and returned as a new token, which can be
+    /// handed back to the sender. The remainder is also stored as frozen proofs, so it isn't lost
+    /// if the token isn't handed back; `cleanup` removes them once they are spent. Fails with
+    /// `NotEnoughTokens` if the token is worth less than `amount` and with
+    /// `InvalidPartialAmount` if nothing would remain.
+    pub async fn receive_tokens_partial(
+        &self,
+        tokens: &TokenV3,
+        amount: u64,
+    ) -> Result<TokenV3, MokshaWalletError> {
+        let total_amount = tokens.total_amount();
+        if amount == total_amount {
+            return Err(MokshaWalletError::InvalidPartialAmount(
+                amount,
+                total_amount,
+            ));
+        }
+
+        let (remainder, redeemed_tokens) = self.split_tokens(tokens, amount.into()).await?;
+        let remainder_proofs = remainder.proofs();
+        self.localstore
+            .add_proofs(
+                &self.mint_url,
+                &Proofs::new(
+                    [redeemed_tokens.proofs().proofs(), remainder_proofs.proofs()].concat(),
+                ),
+            )
+            .await?;
+        for proof in remainder_proofs.proofs() {
+            self.localstore
+                .set_proof_frozen(&proof.secret, true)
+                .await?;
+        }
+        self.add_history_entry(HistoryKind::Receive, redeemed_tokens.total_amount())
+            .await;
+        Ok(remainder)
+    }
+
+    /// Returns the transaction history of all mints, oldest entry first
+    pub async fn get_history(&self) -> Result<Vec<HistoryEntry>, MokshaWalletError> {
+        self.localstore.get_history().await
+    }
+
+    /// Appends an entry to the history. The entry is written after the proofs have changed, so
+    /// a failed write is only logged instead of failing the finished operation.
+    async fn add_history_entry(&self, kind: HistoryKind, amount: u64) {
+        if let Err(err) = self
+            .localstore
+            .add_history_entry(&HistoryEntry::new(kind, amount, self.mint_url.clone()))
+            .await
+        {
+            event!(Level::WARN, "failed to add {kind:?} history entry: {err}");
+        }
+    }
+
+    /// Asks the mint which of the stored proofs are already spent and removes those from the
+    /// localstore. Returns the number of removed proofs.
+    pub async fn cleanup(&self) -> Result<usize, MokshaWalletError> {
+        let proofs = self.localstore.get_proofs(&self.mint_url).await?.proofs();
+
+        let mut spent_proofs = vec![];
+        for batch in proofs.chunks(CHECK_SPENDABLE_BATCH_SIZE) {
+            let response = self
+                .client
+                .post_check_spendable(&self.mint_url, Proofs::new(batch.to_vec()))
+                .await?;
+            spent_proofs.extend(
+                batch
+                    .iter()
+                    .zip(response.spendable)
+                    .filter(|(_, spendable)| !spendable)
+                    .map(|(proof, _)| proof.clone()),
+            );
+        }
+
+        let pruned = spent_proofs.len();
+        if pruned > 0 {
+            self.localstore
+                .delete_proofs(&Proofs::new(spent_proofs))
+                .await?;
+        }
+        Ok(pruned)
+    }
+
     pub async fn pay_invoice(
         &self,
         invoice: String,
     ) -> Result<PostMeltResponse, MokshaWalletError> {
-        let all_proofs = self.localstore.get_proofs().await?;
+        let all_proofs = self.get_spendable_proofs().await?;
 
         let fees = self
             .client
             .post_checkfees(&self.mint_url, invoice.clone())
             .await?;
 
-        let ln_amount = Self::get_invoice_amount(&invoice)? + fees.fee;
+        let ln_amount = self.get_invoice_amount(&invoice)? + fees.fee;
 
         if ln_amount > all_proofs.total_amount() {
             return Err(MokshaWalletError::NotEnoughTokens);
@@ -189,7 +457,9 @@ impl<C: LegacyClient, L: LocalStore> Wallet<C, L> {
 
             // FIXME create transaction
             self.localstore.delete_proofs(&selected_proofs).await?;
-            self.localstore.add_proofs(&split_result.0.proofs()).await?;
+            self.localstore
+                .add_proofs(&self.mint_url, &split_result.0.proofs())
+                .await?;
 
             split_result.1.proofs()
         };
@@ -217,39 +487,97 @@ impl<C: LegacyClient, L: LocalStore> Wallet<C, L> {
         {
             Ok(response) => {
                 if !response.paid {
-                    self.localstore.add_proofs(&total_proofs).await?;
+                    self.localstore
+                        .add_proofs(&self.mint_url, &total_proofs)
+                        .await?;
+                    return Ok(response);
+                }
+
+                // the invoice is paid at this point, so invalid change must not fail the payment
+                let change_proofs = self
+                    .recover_change(&response.change, fees.fee, secrets, outputs)
+                    .unwrap_or_else(|err| {
+                        event!(Level::WARN, "ignoring the change of the melt: {err}");
+                        Proofs::empty()
+                    });
+                if !change_proofs.is_empty() {
+                    self.localstore
+                        .add_proofs(&self.mint_url, &change_proofs)
+                        .await?;
                 }
-                let change_proofs = self.create_proofs_from_blinded_signatures(
-                    response.clone().change,
-                    secrets,
-                    outputs,
-                )?;
-                self.localstore.add_proofs(&change_proofs).await?;
+                self.add_history_entry(
+                    HistoryKind::Melt,
+                    total_proofs.total_amount() - change_proofs.total_amount(),
+                )
+                .await;
 
                 Ok(response)
             }
             Err(e) => {
-                self.localstore.add_proofs(&total_proofs).await?;
+                self.localstore
+                    .add_proofs(&self.mint_url, &total_proofs)
+                    .await?;
                 Err(e)
             }
         }
     }
 
+    /// Unblinds the change the mint returned for the blank outputs (NUT-08). The mint may return
+    /// fewer signatures than blank outputs, in this case the signatures belong to the first
+    /// outputs. The change must not exceed the fee reserve.
+    fn recover_change(
+        &self,
+        change: &[BlindedSignature],
+        fee_reserve: u64,
+        secrets: Vec<String>,
+        outputs: Vec<(BlindedMessage, SecretKey)>,
+    ) -> Result<Proofs, MokshaWalletError> {
+        if change.len() > outputs.len() {
+            return Err(MokshaWalletError::InvalidChange(format!(
+                "{} signatures for {} blank outputs",
+                change.len(),
+                outputs.len()
+            )));
+        }
+
+        let change_amount = change
+            .iter()
+            .try_fold(0u64, |acc, signature| acc.checked_add(signature.amount));
+        if change_amount.map_or(true, |amount| amount > fee_reserve) {
+            return Err(MokshaWalletError::InvalidChange(format!(
+                "change exceeds the fee reserve of {fee_reserve} sats"
+            )));
+        }
+
+        if let Some(signature) = change
+            .iter()
+            .find(|signature| !self.mint_keys.contains_key(&signature.amount))
+        {
+            return Err(MokshaWalletError::InvalidChange(format!(
+                "no mint key for amount {}",
+                signature.amount
+            )));
+        }
+
+        self.create_proofs_from_blinded_signatures(change.to_vec(), secrets, outputs)
+    }
+
     pub async fn split_tokens(
         &self,
         tokens: &TokenV3,
         splt_amount: Amount,
     ) -> Result<(TokenV3, TokenV3), MokshaWalletError> {
-        let total_token_amount = tokens.total_amount();
-        let first_amount: Amount = (total_token_amount - splt_amount.0).into();
-        let first_secrets = first_amount.split().create_secrets();
-        let first_outputs = self.create_blinded_messages(first_amount, &first_secrets)?;
+        let first_amount = Amount(tokens.total_amount())
+            .checked_sub(&splt_amount)
+            .ok_or(MokshaWalletError::NotEnoughTokens)?;
+        let mut first_secrets = self.split_amount(&first_amount)?.create_secrets();
+        let first_outputs = self.create_blinded_messages(first_amount, &mut first_secrets)?;
 
         // ############################################################################
 
         let second_amount = splt_amount.clone();
-        let second_secrets = second_amount.split().create_secrets();
-        let second_outputs = self.create_blinded_messages(second_amount, &second_secrets)?;
+        let mut second_secrets = self.split_amount(&second_amount)?.create_secrets();
+        let second_outputs = self.create_blinded_messages(second_amount, &mut second_secrets)?;
 
         let mut total_outputs = vec![];
         total_outputs.extend(get_blinded_msg(first_outputs.clone()));
@@ -313,7 +641,8 @@ impl<C: LegacyClient, L: LocalStore> Wallet<C, L> {
             .map_err(|err| MokshaWalletError::DecodeInvoice(payment_request.to_owned(), err))
     }
 
-    fn get_invoice_amount(payment_request: &str) -> Result<u64, MokshaWalletError> {
+    /// Returns the amount of the bolt11 invoice in sats
+    pub fn get_invoice_amount(&self, payment_request: &str) -> Result<u64, MokshaWalletError> {
         let invoice = Self::decode_invoice(payment_request)?;
         Ok(invoice
             .amount_milli_satoshis()
@@ -326,17 +655,8 @@ impl<C: LegacyClient, L: LocalStore> Wallet<C, L> {
         amount: Amount,
         hash: String,
     ) -> Result<TokenV3, MokshaWalletError> {
-        let split_amount = amount.split();
-        let secrets = split_amount.create_secrets();
-
-        let blinded_messages = split_amount
-            .into_iter()
-            .zip(secrets.clone())
-            .map(|(amount, secret)| {
-                let (b_, alice_secret_key) = self.dhke.step1_alice(secret, None).unwrap(); // FIXME
-                (BlindedMessage { amount, b_ }, alice_secret_key)
-            })
-            .collect::<Vec<(BlindedMessage, SecretKey)>>();
+        let mut secrets = self.split_amount(&amount)?.create_secrets();
+        let blinded_messages = self.create_blinded_messages(amount, &mut secrets)?;
 
         let post_mint_resp = self
             .client
@@ -352,54 +672,67 @@ impl<C: LegacyClient, L: LocalStore> Wallet<C, L> {
             .await?;
 
         // step 3: unblind signatures
-        let current_keyset = self.keysets.current_keyset(&self.mint_keys)?;
-
-        let private_keys = blinded_messages
-            .clone()
-            .into_iter()
-            .map(|(_, secret)| secret)
-            .collect::<Vec<SecretKey>>();
-
-        let proofs = post_mint_resp
-            .promises
-            .iter()
-            .zip(private_keys)
-            .zip(secrets)
-            .map(|((p, priv_key), secret)| {
-                let key = self
-                    .mint_keys
-                    .get(&p.amount)
-                    .expect("msg amount not found in mint keys");
-                let pub_alice = self.dhke.step3_alice(p.c_, priv_key, *key).unwrap();
-                Proof::new(p.amount, secret, pub_alice, current_keyset.clone())
-            })
-            .collect::<Vec<Proof>>()
-            .into();
+        let proofs = self.create_proofs_from_blinded_signatures(
+            post_mint_resp.promises,
+            secrets,
+            blinded_messages,
+        )?;
 
         let tokens: TokenV3 = (self.mint_url.to_owned(), proofs).into();
-        self.localstore.add_proofs(&tokens.proofs()).await?;
+        self.localstore
+            .add_proofs(&self.mint_url, &tokens.proofs())
+            .await?;
+        self.add_history_entry(HistoryKind::Mint, tokens.total_amount())
+            .await;
 
         Ok(tokens)
     }
 
+    /// Splits the amount into the denominations the mint has keys for, e.g. powers of two or
+    /// 1-2-5 amounts
+    fn split_amount(&self, amount: &Amount) -> Result<SplitAmount, MokshaWalletError> {
+        let denominations = self.mint_keys.keys().copied().collect::<Vec<_>>();
+        amount
+            .split_with(&denominations)
+            .ok_or(MokshaWalletError::UnsupportedAmount(amount.0))
+    }
+
     // FIXME implement for Amount
+    /// Blinds the secrets for the split amounts. The mint rejects the whole batch if it contains
+    /// a secret or blinded message twice (e.g. a secret reused after a crash), so colliding
+    /// secrets are replaced with fresh ones in place.
     fn create_blinded_messages(
         &self,
         amount: Amount,
-        secrets: &[String],
+        secrets: &mut [String],
     ) -> Result<Vec<(BlindedMessage, SecretKey)>, MokshaWalletError> {
-        let split_amount = amount.split();
+        let mut used_secrets = HashSet::new();
+        let mut used_outputs = HashSet::new();
 
-        Ok(split_amount
+        self.split_amount(&amount)?
             .into_iter()
-            .zip(secrets)
-            .map(|(amount, secret)| {
-                let (b_, alice_secret_key) = self.dhke.step1_alice(secret, None).unwrap(); // FIXME
-                (BlindedMessage { amount, b_ }, alice_secret_key)
+            .zip(secrets.iter_mut())
+            .map(|(amount, secret)| loop {
+                let (b_, alice_secret_key) = self.dhke.step1_alice(secret.clone(), None)?;
+                if used_secrets.contains(secret.as_str()) || !used_outputs.insert(b_) {
+                    *secret = generate_random_string();
+                    continue;
+                }
+                used_secrets.insert(secret.clone());
+                return Ok((
+                    BlindedMessage {
+                        amount,
+                        b_,
+                        id: None,
+                    },
+                    alice_secret_key,
+                ));
             })
-            .collect::<Vec<(BlindedMessage, SecretKey)>>())
+            .collect()
     }
 
+    /// Unblinds the signatures of the mint. Fails with `InvalidDleq` before anything is stored
+    /// if the DLEQ proof of a signature shows that the mint didn't sign with its advertised key.
     fn create_proofs_from_blinded_signatures(
         &self,
         signatures: Vec<BlindedSignature>,
@@ -408,26 +741,61 @@ impl<C: LegacyClient, L: LocalStore> Wallet<C, L> {
     ) -> Result<Proofs, MokshaWalletError> {
         let current_keyset = self.keysets.current_keyset(&self.mint_keys)?;
 
-        let private_keys = outputs
-            .into_iter()
-            .map(|(_, secret)| secret)
-            .collect::<Vec<SecretKey>>();
-
         Ok(signatures
             .iter()
-            .zip(private_keys)
+            .zip(outputs)
             .zip(secrets)
-            .map(|((p, priv_key), secret)| {
+            .map(|((p, (output, priv_key)), secret)| {
                 let key = self
                     .mint_keys
                     .get(&p.amount)
                     .expect("msg amount not found in mint keys");
+                if !self.verify_signature_dleq(p, output.b_, *key) {
+                    return Err(MokshaWalletError::InvalidDleq);
+                }
                 let pub_alice = self.dhke.step3_alice(p.c_, priv_key, *key).unwrap();
-                Proof::new(p.amount, secret, pub_alice, current_keyset.clone())
+                Ok(Proof::new(
+                    p.amount,
+                    secret,
+                    pub_alice,
+                    current_keyset.clone(),
+                ))
             })
-            .collect::<Vec<Proof>>()
+            .collect::<Result<Vec<Proof>, MokshaWalletError>>()?
             .into())
     }
+
+    /// Checks the DLEQ proof (NUT-12) of a signature for the blinded message `b_` against the
+    /// key the mint advertises for the amount. Signatures of mints that don't send DLEQ proofs
+    /// are accepted.
+    fn verify_signature_dleq(
+        &self,
+        signature: &BlindedSignature,
+        b_: PublicKey,
+        mint_pubkey: PublicKey,
+    ) -> bool {
+        signature.dleq.as_ref().map_or(true, |dleq| {
+            self.dhke
+                .verify_dleq(dleq, b_, signature.c_, mint_pubkey)
+                .unwrap_or(false)
+        })
+    }
+}
+
+impl<C: LegacyClient + Client, L: LocalStore> Wallet<C, L> {
+    /// Fetches the info of the mint from `/v1/info`. Falls back to the legacy `/info` endpoint,
+    /// if the mint doesn't support the v1 api yet.
+    pub async fn get_mint_info(&self) -> Result<MintInfo, MokshaWalletError> {
+        if Client::is_v1_supported(&self.client, &self.mint_url).await? {
+            let info = Client::get_info(&self.client, &self.mint_url).await?;
+            let keysets = self.client.get_keysets(&self.mint_url).await?;
+            return Ok(MintInfo::from_v1(info, &keysets));
+        }
+
+        let info = LegacyClient::get_info(&self.client, &self.mint_url).await?;
+        let active_keyset_id = self.keysets.current_keyset(&self.mint_keys).ok();
+        Ok(MintInfo::from_legacy(info, active_keyset_id))
+    }
 }
 
 // FIXME implement for Vec<BlindedMessage, Secretkey>
@@ -440,24 +808,34 @@ fn get_blinded_msg(blinded_messages: Vec<(BlindedMessage, SecretKey)>) -> Vec<Bl
 
 #[cfg(test)]
 mod tests {
-    use crate::wallet::WalletBuilder;
+    use crate::wallet::{Wallet, WalletBuilder};
     use crate::{
-        client::LegacyClient,
+        client::{Client, LegacyClient},
         error::MokshaWalletError,
-        localstore::{LocalStore, WalletKeyset},
+        localstore::{
+            memory::MemoryLocalStore, HistoryEntry, HistoryKind, LocalStore, WalletKeyset,
+        },
     };
     use async_trait::async_trait;
-    use moksha_core::blind::BlindedMessage;
+    use moksha_core::amount::Denominations;
+    use moksha_core::blind::{BlindedMessage, BlindedSignature};
+    use moksha_core::dhke::Dhke;
     use moksha_core::fixture::{read_fixture, read_fixture_as};
-    use moksha_core::keyset::{Keysets, MintKeyset};
+    use moksha_core::keyset::{Keysets, MintKeyset, V1Keysets};
     use moksha_core::primitives::{
-        CheckFeesResponse, MintLegacyInfoResponse, PaymentRequest, PostMeltResponse,
-        PostMintResponse, PostSplitResponse,
+        CheckFeesResponse, CheckSpendableResponse, CurrencyUnit, KeysResponse, MintInfoResponse,
+        MintLegacyInfoResponse, PaymentRequest, PostMeltBolt11Response,
+        PostMeltQuoteBolt11Response, PostMeltResponse, PostMintBolt11Response,
+        PostMintQuoteBolt11Response, PostMintResponse, PostSplitResponse, PostSwapResponse,
     };
     use moksha_core::proof::Proofs;
     use moksha_core::token::{Token, TokenV3};
     use secp256k1::PublicKey;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
     use url::Url;
 
     #[derive(Clone)]
@@ -484,16 +862,34 @@ mod tests {
 
     #[async_trait(?Send)]
     impl LocalStore for MockLocalStore {
-        async fn add_proofs(&self, _: &Proofs) -> Result<(), crate::error::MokshaWalletError> {
+        async fn add_proofs(
+            &self,
+            _: &Url,
+            _: &Proofs,
+        ) -> Result<(), crate::error::MokshaWalletError> {
             Ok(())
         }
 
         async fn get_proofs(
             &self,
+            _: &Url,
         ) -> Result<moksha_core::proof::Proofs, crate::error::MokshaWalletError> {
             Ok(self.tokens.clone().proofs())
         }
 
+        async fn get_all_proofs(&self) -> Result<BTreeMap<Url, Proofs>, MokshaWalletError> {
+            Ok(self
+                .tokens
+                .mint()
+                .map(|mint_url| (mint_url, self.tokens.proofs()))
+                .into_iter()
+                .collect())
+        }
+
+        async fn set_missing_mint_url(&self, _mint_url: &Url) -> Result<(), MokshaWalletError> {
+            Ok(())
+        }
+
         async fn delete_proofs(
             &self,
             _proofs: &Proofs,
@@ -501,6 +897,18 @@ mod tests {
             Ok(())
         }
 
+        async fn set_proof_frozen(
+            &self,
+            _secret: &str,
+            _frozen: bool,
+        ) -> Result<(), MokshaWalletError> {
+            Ok(())
+        }
+
+        async fn get_frozen_secrets(&self) -> Result<HashSet<String>, MokshaWalletError> {
+            Ok(HashSet::new())
+        }
+
         async fn get_keysets(&self) -> Result<Vec<WalletKeyset>, MokshaWalletError> {
             Ok(vec![])
         }
@@ -508,6 +916,18 @@ mod tests {
         async fn add_keyset(&self, _keyset: &WalletKeyset) -> Result<(), MokshaWalletError> {
             Ok(())
         }
+
+        /// fails, so the tests using this store check that a failed history write doesn't fail
+        /// the operation
+        async fn add_history_entry(&self, _entry: &HistoryEntry) -> Result<(), MokshaWalletError> {
+            Err(MokshaWalletError::UnexpectedResponse(
+                "history is not supported by the MockLocalStore".to_owned(),
+            ))
+        }
+
+        async fn get_history(&self) -> Result<Vec<HistoryEntry>, MokshaWalletError> {
+            Ok(vec![])
+        }
     }
 
     #[derive(Clone)]
@@ -528,7 +948,23 @@ mod tests {
         split_response: PostSplitResponse,
         post_mint_response: PostMintResponse,
         post_melt_response: PostMeltResponse,
+        spent_secrets: Vec<String>,
+        sign_split_outputs: bool,
+        /// replaces the DLEQ proofs of signed split outputs with proofs for another key
+        tamper_dleq: bool,
         keyset: MockKeys,
+        /// keysets the mint advertises. Defaults to the id of the mock keyset
+        advertised_keysets: Option<Keysets>,
+        fee_reserve: u64,
+        /// amounts of the change the mint signs for the first blank outputs of a melt
+        melt_change: Vec<u64>,
+        payment_request: Option<PaymentRequest>,
+        /// fee reserve per mint. Mints that are missing use `fee_reserve`
+        mint_fees: HashMap<Url, u64>,
+        /// number of requests sent to the mint
+        calls: Arc<AtomicUsize>,
+        /// mint info is served from `/v1/info` instead of the legacy endpoint
+        v1_supported: bool,
     }
 
     impl MockClient {
@@ -539,6 +975,43 @@ mod tests {
             }
         }
 
+        /// signs the outputs of a split with the mock keyset instead of returning a fixture
+        fn with_signed_split() -> Self {
+            Self {
+                sign_split_outputs: true,
+                ..Default::default()
+            }
+        }
+
+        /// signs the outputs of a split, but attaches DLEQ proofs that don't match the mint key
+        fn with_tampered_dleq() -> Self {
+            Self {
+                sign_split_outputs: true,
+                tamper_dleq: true,
+                ..Default::default()
+            }
+        }
+
+        /// signs the blinded message with the mock keyset and attaches a DLEQ proof
+        fn sign(&self, amount: u64, b_: PublicKey) -> BlindedSignature {
+            let dhke = Dhke::new();
+            let key = self.keyset.mint_keyset.private_keys[&amount];
+            let c_ = dhke.step2_bob(b_, &key).expect("signing failed");
+            let dleq_key = match self.tamper_dleq {
+                true => self.keyset.mint_keyset.private_keys[&(amount * 2)],
+                false => key,
+            };
+            BlindedSignature {
+                amount,
+                c_,
+                id: Some(self.keyset.mint_keyset.keyset_id.clone()),
+                dleq: Some(
+                    dhke.step2_bob_dleq(b_, c_, &dleq_key)
+                        .expect("creating dleq failed"),
+                ),
+            }
+        }
+
         fn with_mint_response(post_mint_response: PostMintResponse) -> Self {
             Self {
                 post_mint_response,
@@ -546,6 +1019,55 @@ mod tests {
             }
         }
 
+        fn with_spent_secrets(spent_secrets: Vec<String>) -> Self {
+            Self {
+                spent_secrets,
+                ..Default::default()
+            }
+        }
+
+        fn with_advertised_keysets(advertised_keysets: Keysets) -> Self {
+            Self {
+                advertised_keysets: Some(advertised_keysets),
+                ..Default::default()
+            }
+        }
+
+        /// pays every invoice and returns change for the first blank outputs
+        fn with_melt_change(fee_reserve: u64, melt_change: Vec<u64>) -> Self {
+            Self {
+                post_melt_response: PostMeltResponse {
+                    paid: true,
+                    preimage: "preimage".to_string(),
+                    change: vec![],
+                },
+                sign_split_outputs: true,
+                fee_reserve,
+                melt_change,
+                ..Default::default()
+            }
+        }
+
+        /// returns the given invoice for every mint request, regardless of the amount
+        fn with_payment_request(pr: impl Into<String>) -> Self {
+            Self {
+                payment_request: Some(PaymentRequest {
+                    pr: pr.into(),
+                    hash: "hash".to_string(),
+                    amount: None,
+                    unit: None,
+                }),
+                ..Default::default()
+            }
+        }
+
+        fn with_mint_fees(mint_fees: HashMap<Url, u64>) -> Self {
+            Self {
+                mint_fees,
+                ..Default::default()
+            }
+        }
+
         fn with_melt_response(post_melt_response: PostMeltResponse) -> Self {
             Self {
                 post_melt_response,
@@ -561,9 +1083,18 @@ mod tests {
             &self,
             _mint_url: &Url,
             _proofs: Proofs,
-            _output: Vec<BlindedMessage>,
+            output: Vec<BlindedMessage>,
         ) -> Result<PostSplitResponse, MokshaWalletError> {
-            Ok(self.split_response.clone())
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if !self.sign_split_outputs {
+                return Ok(self.split_response.clone());
+            }
+
+            let promises = output
+                .into_iter()
+                .map(|msg| self.sign(msg.amount, msg.b_))
+                .collect();
+            Ok(PostSplitResponse::with_promises(promises))
         }
 
         async fn post_mint_payment_request(
@@ -572,6 +1103,7 @@ mod tests {
             _hash: String,
             _blinded_messages: Vec<BlindedMessage>,
         ) -> Result<PostMintResponse, MokshaWalletError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
             Ok(self.post_mint_response.clone())
         }
 
@@ -580,32 +1112,69 @@ mod tests {
             _mint_url: &Url,
             _proofs: Proofs,
             _pr: String,
-            _outputs: Vec<BlindedMessage>,
+            outputs: Vec<BlindedMessage>,
         ) -> Result<PostMeltResponse, MokshaWalletError> {
-            Ok(self.post_melt_response.clone())
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.melt_change.is_empty() {
+                return Ok(self.post_melt_response.clone());
+            }
+
+            let change = outputs
+                .into_iter()
+                .zip(self.melt_change.iter())
+                .map(|(msg, amount)| self.sign(*amount, msg.b_))
+                .collect();
+            Ok(PostMeltResponse {
+                change,
+                ..self.post_melt_response.clone()
+            })
         }
 
         async fn post_checkfees(
             &self,
-            _mint_url: &Url,
+            mint_url: &Url,
             _pr: String,
         ) -> Result<CheckFeesResponse, MokshaWalletError> {
-            Ok(CheckFeesResponse { fee: 0 })
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CheckFeesResponse {
+                fee: self
+                    .mint_fees
+                    .get(mint_url)
+                    .copied()
+                    .unwrap_or(self.fee_reserve),
+            })
+        }
+
+        async fn post_check_spendable(
+            &self,
+            _mint_url: &Url,
+            proofs: Proofs,
+        ) -> Result<CheckSpendableResponse, MokshaWalletError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CheckSpendableResponse {
+                spendable: proofs
+                    .proofs()
+                    .iter()
+                    .map(|proof| !self.spent_secrets.contains(&proof.secret))
+                    .collect(),
+                pending: vec![false; proofs.len()],
+            })
         }
 
         async fn get_mint_keys(
             &self,
             _mint_url: &Url,
         ) -> Result<HashMap<u64, PublicKey>, MokshaWalletError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
             Ok(self.keyset.mint_keyset.public_keys.clone())
         }
 
         async fn get_mint_keysets(&self, _mint_url: &Url) -> Result<Keysets, MokshaWalletError> {
-            Ok(Keysets::new(vec![self
-                .keyset
-                .mint_keyset
-                .keyset_id
-                .clone()]))
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self
+                .advertised_keysets
+                .clone()
+                .unwrap_or_else(|| Keysets::new(vec![self.keyset.mint_keyset.keyset_id.clone()])))
         }
 
         async fn get_mint_payment_request(
@@ -613,68 +1182,403 @@ mod tests {
             _mint_url: &Url,
             _amount: u64,
         ) -> Result<PaymentRequest, MokshaWalletError> {
-            unimplemented!()
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self
+                .payment_request
+                .clone()
+                .expect("no payment request configured"))
         }
 
         async fn get_info(
             &self,
             _mint_url: &Url,
         ) -> Result<MintLegacyInfoResponse, MokshaWalletError> {
-            unimplemented!()
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(read_fixture_as("mint_info_legacy.json").expect("invalid fixture"))
         }
     }
 
-    #[tokio::test]
-    async fn test_mint_tokens() -> anyhow::Result<()> {
-        let mint_response = read_fixture_as::<PostMintResponse>("post_mint_response_20.json")?;
-        let client = MockClient::with_mint_response(mint_response);
-        let localstore = MockLocalStore::default();
-        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
-
-        let wallet = WalletBuilder::new()
-            .with_client(client)
-            .with_localstore(localstore)
-            .with_mint_url(mint_url.clone())
-            .build()
-            .await?;
-
-        let result = wallet.mint_tokens(20.into(), "hash".to_string()).await?;
-        assert_eq!(20, result.total_amount());
-        result.tokens.into_iter().for_each(|t| {
-            assert_eq!(mint_url, t.mint.expect("mint is empty"));
-        });
-        Ok(())
+    fn unsupported_call() -> MokshaWalletError {
+        MokshaWalletError::UnexpectedResponse("call is not supported by the MockClient".to_owned())
     }
 
-    #[tokio::test]
-    async fn test_split() -> anyhow::Result<()> {
-        let split_response =
-            read_fixture_as::<PostSplitResponse>("post_split_response_24_40.json")?;
-        let client = MockClient::with_split_response(split_response);
-        let localstore = MockLocalStore::default();
-
-        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
-        let wallet = WalletBuilder::new()
-            .with_client(client)
-            .with_localstore(localstore)
-            .with_mint_url(mint_url)
-            .build()
-            .await?;
+    #[async_trait(?Send)]
+    impl Client for MockClient {
+        async fn get_keys(&self, _mint_url: &Url) -> Result<KeysResponse, MokshaWalletError> {
+            Err(unsupported_call())
+        }
 
-        let tokens = read_fixture("token_64.cashu")?.try_into()?;
-        let result = wallet.split_tokens(&tokens, 20.into()).await?;
-        assert_eq!(24, result.0.total_amount());
-        assert_eq!(40, result.1.total_amount());
-        Ok(())
-    }
+        async fn get_keys_by_id(
+            &self,
+            _mint_url: &Url,
+            _keyset_id: String,
+        ) -> Result<KeysResponse, MokshaWalletError> {
+            Err(unsupported_call())
+        }
 
-    #[tokio::test]
-    async fn test_get_balance() -> anyhow::Result<()> {
-        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
-        let local_store = MockLocalStore::with_tokens(fixture.try_into()?);
+        async fn get_keysets(&self, _mint_url: &Url) -> Result<V1Keysets, MokshaWalletError> {
+            Ok(V1Keysets::new(
+                "00f545318e4fad2b".to_string(),
+                CurrencyUnit::Sat,
+                true,
+            ))
+        }
 
-        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
-        let wallet = WalletBuilder::new()
+        async fn post_swap(
+            &self,
+            _mint_url: &Url,
+            _proofs: Proofs,
+            _output: Vec<BlindedMessage>,
+        ) -> Result<PostSwapResponse, MokshaWalletError> {
+            Err(unsupported_call())
+        }
+
+        async fn post_melt_bolt11(
+            &self,
+            _mint_url: &Url,
+            _proofs: Proofs,
+            _quote: String,
+            _outputs: Vec<BlindedMessage>,
+        ) -> Result<PostMeltBolt11Response, MokshaWalletError> {
+            Err(unsupported_call())
+        }
+
+        async fn post_melt_quote_bolt11(
+            &self,
+            _mint_url: &Url,
+            _payment_request: String,
+            _unit: CurrencyUnit,
+        ) -> Result<PostMeltQuoteBolt11Response, MokshaWalletError> {
+            Err(unsupported_call())
+        }
+
+        async fn get_melt_quote_bolt11(
+            &self,
+            _mint_url: &Url,
+            _quote: String,
+        ) -> Result<PostMeltQuoteBolt11Response, MokshaWalletError> {
+            Err(unsupported_call())
+        }
+
+        async fn post_mint_bolt11(
+            &self,
+            _mint_url: &Url,
+            _quote: String,
+            _blinded_messages: Vec<BlindedMessage>,
+        ) -> Result<PostMintBolt11Response, MokshaWalletError> {
+            Err(unsupported_call())
+        }
+
+        async fn post_mint_quote_bolt11(
+            &self,
+            _mint_url: &Url,
+            _amount: u64,
+            _unit: CurrencyUnit,
+        ) -> Result<PostMintQuoteBolt11Response, MokshaWalletError> {
+            Err(unsupported_call())
+        }
+
+        async fn get_mint_quote_bolt11(
+            &self,
+            _mint_url: &Url,
+            _quote: String,
+        ) -> Result<PostMintQuoteBolt11Response, MokshaWalletError> {
+            Err(unsupported_call())
+        }
+
+        async fn get_info(&self, _mint_url: &Url) -> Result<MintInfoResponse, MokshaWalletError> {
+            Ok(read_fixture_as("mint_info_v1.json").expect("invalid fixture"))
+        }
+
+        async fn is_v1_supported(&self, _mint_url: &Url) -> Result<bool, MokshaWalletError> {
+            Ok(self.v1_supported)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_blinded_messages_replaces_reused_secrets() -> anyhow::Result<()> {
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::default())
+            .with_localstore(MemoryLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+
+        // 24 sats are split into 8 and 16
+        let mut secrets = vec!["reused".to_string(), "reused".to_string()];
+        let outputs = wallet.create_blinded_messages(24.into(), &mut secrets)?;
+
+        assert_eq!(2, outputs.len());
+        assert_eq!("reused", secrets[0]);
+        assert_ne!(secrets[0], secrets[1]);
+        assert_ne!(outputs[0].0.b_, outputs[1].0.b_);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_tokens() -> anyhow::Result<()> {
+        let mint_response = read_fixture_as::<PostMintResponse>("post_mint_response_20.json")?;
+        let client = MockClient::with_mint_response(mint_response);
+        let localstore = MockLocalStore::default();
+        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        let result = wallet.mint_tokens(20.into(), "hash".to_string()).await?;
+        assert_eq!(20, result.total_amount());
+        result.tokens.into_iter().for_each(|t| {
+            assert_eq!(mint_url, t.mint.expect("mint is empty"));
+        });
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_tokens_stores_proofs() -> anyhow::Result<()> {
+        let mint_response = read_fixture_as::<PostMintResponse>("post_mint_response_20.json")?;
+        let localstore = MemoryLocalStore::default();
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_mint_response(mint_response))
+            .with_localstore(localstore.clone())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+        assert_eq!(0, wallet.get_balance().await?);
+
+        let tokens = wallet.mint_tokens(20.into(), "hash".to_string()).await?;
+        assert_eq!(20, wallet.get_balance().await?);
+
+        let mut stored_secrets = localstore
+            .get_proofs(&Url::parse("http://localhost:8080/")?)
+            .await?
+            .proofs()
+            .into_iter()
+            .map(|proof| proof.secret)
+            .collect::<Vec<_>>();
+        let mut minted_secrets = tokens
+            .proofs()
+            .proofs()
+            .into_iter()
+            .map(|proof| proof.secret)
+            .collect::<Vec<_>>();
+        stored_secrets.sort();
+        minted_secrets.sort();
+        assert_eq!(minted_secrets, stored_secrets);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_history_of_mint_and_send() -> anyhow::Result<()> {
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let client = MockClient {
+            post_mint_response: read_fixture_as("post_mint_response_20.json")?,
+            sign_split_outputs: true,
+            ..Default::default()
+        };
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(MemoryLocalStore::default())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        wallet.mint_tokens(20.into(), "hash".to_string()).await?;
+        wallet.send_tokens(5, None).await?;
+
+        let history = wallet.get_history().await?;
+        assert_eq!(
+            vec![(HistoryKind::Mint, 20), (HistoryKind::Send, 5)],
+            history
+                .iter()
+                .map(|entry| (entry.kind, entry.amount))
+                .collect::<Vec<_>>()
+        );
+        assert!(history.iter().all(|entry| entry.mint_url == mint_url));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_mint_info() -> anyhow::Result<()> {
+        let client = MockClient {
+            v1_supported: true,
+            ..Default::default()
+        };
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(MockLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+
+        let info = wallet.get_mint_info().await?;
+        assert!(!info.legacy);
+        let output = info.to_string();
+        assert!(output.contains("Name: Bob's Cashu mint"));
+        assert!(output.contains("Version: 0.1.2"));
+        assert!(output.contains("Description: The short mint description"));
+        assert!(output.contains("Contact: email: contact@me.com, twitter: @me"));
+        assert!(output.contains(
+            "Supported NUTs: NUT-00, NUT-01, NUT-02, NUT-03, NUT-04, NUT-05, NUT-06, NUT-08, NUT-19"
+        ));
+        assert!(output.contains("Active keyset: 00f545318e4fad2b"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_mint_info_legacy() -> anyhow::Result<()> {
+        let client = MockClient::default();
+        let keyset_id = client.keyset.mint_keyset.keyset_id.clone();
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(MockLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+
+        let info = wallet.get_mint_info().await?;
+        assert!(info.legacy);
+        assert_eq!(Some("Bob's legacy mint".to_string()), info.name);
+        assert_eq!(Some(keyset_id), info.active_keyset_id);
+        assert!(info
+            .to_string()
+            .contains("Supported NUTs: NUT-00, NUT-01, NUT-02, NUT-03, NUT-04, NUT-05"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_amount_uses_mint_denominations() -> anyhow::Result<()> {
+        let keyset =
+            MintKeyset::new_with_denominations("mysecret", "", 12, Denominations::OneTwoFive);
+        let wallet = Wallet::new(
+            MockClient::default(),
+            keyset.public_keys,
+            Keysets::default(),
+            MockLocalStore::default(),
+            Url::parse("http://localhost:8080/")?,
+        );
+
+        assert_eq!(
+            vec![2, 5, 10, 20],
+            wallet
+                .split_amount(&37.into())?
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_rejects_keys_of_other_keyset() -> anyhow::Result<()> {
+        let other_keyset = MintKeyset::legacy_new("othersecret", "");
+        let client =
+            MockClient::with_advertised_keysets(Keysets::new(vec![other_keyset.keyset_id]));
+
+        let result = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(MockLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::MokshaCore(
+                moksha_core::error::MokshaCoreError::InvalidKeysetid
+            ))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split() -> anyhow::Result<()> {
+        let split_response =
+            read_fixture_as::<PostSplitResponse>("post_split_response_24_40.json")?;
+        let client = MockClient::with_split_response(split_response);
+        let localstore = MockLocalStore::default();
+
+        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .with_mint_url(mint_url)
+            .build()
+            .await?;
+
+        let tokens = read_fixture("token_64.cashu")?.try_into()?;
+        let result = wallet.split_tokens(&tokens, 20.into()).await?;
+        assert_eq!(24, result.0.total_amount());
+        assert_eq!(40, result.1.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_tokens_with_memo() -> anyhow::Result<()> {
+        let localstore = MockLocalStore::with_tokens(read_fixture("token_64.cashu")?.try_into()?);
+        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_signed_split())
+            .with_localstore(localstore)
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        let token = wallet
+            .send_tokens(20, Some("for coffee".to_string()))
+            .await?;
+        assert_eq!(Some("for coffee".to_string()), token.memo);
+        assert_eq!(20, token.total_amount());
+        assert_eq!(Some(mint_url), token.mint());
+
+        let deserialized = TokenV3::deserialize(token.serialize()?)?;
+        assert_eq!(Some("for coffee".to_string()), deserialized.memo);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_tokens_rejects_invalid_proofs_before_swap() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 4,8,16,32
+        let mut proofs = tokens.proofs().proofs();
+        // 3 sats is not a denomination of any supported scheme
+        proofs[0].amount = 3;
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore
+            .add_proofs(&mint_url, &Proofs::new(proofs))
+            .await?;
+
+        let client = MockClient::with_signed_split();
+        let calls = client.calls.clone();
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .with_mint_url(mint_url)
+            .build()
+            .await?;
+        let calls_after_build = calls.load(Ordering::SeqCst);
+
+        let result = wallet.send_tokens(59, None).await;
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::MokshaCore(
+                moksha_core::error::MokshaCoreError::InvalidProof(_, _)
+            ))
+        ));
+        // the proofs are neither swapped nor removed from the localstore
+        assert_eq!(calls_after_build, calls.load(Ordering::SeqCst));
+        assert_eq!(59, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_balance() -> anyhow::Result<()> {
+        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
+        let local_store = MockLocalStore::with_tokens(fixture.try_into()?);
+
+        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
+        let wallet = WalletBuilder::new()
             .with_client(MockClient::default())
             .with_localstore(local_store)
             .with_mint_url(mint_url)
@@ -686,6 +1590,93 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_balances_of_multiple_mints() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let proofs = tokens.proofs().proofs();
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let other_mint_url = Url::parse("https://mint.example.com")?;
+
+        let localstore = MemoryLocalStore::default();
+        localstore
+            .add_proofs(&mint_url, &Proofs::new(proofs[0..2].to_vec()))
+            .await?;
+        localstore
+            .add_proofs(&other_mint_url, &Proofs::new(proofs[2..].to_vec()))
+            .await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::default())
+            .with_localstore(localstore)
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        assert_eq!(12, wallet.get_balance().await?);
+        let balances = wallet.get_balances().await?;
+        assert_eq!(
+            BTreeMap::from([(mint_url, 12), (other_mint_url, 48)]),
+            balances
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_balances_offline() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+
+        let client = MockClient::default();
+        let calls = client.calls.clone();
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        let calls_after_build = calls.load(Ordering::SeqCst);
+        assert_eq!(
+            BTreeMap::from([(mint_url, 60)]),
+            wallet.get_balances().await?
+        );
+        assert_eq!(calls_after_build, calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_removes_spent_proofs() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let proofs = tokens.proofs().proofs();
+        let (spent, unspent) = (proofs[0].clone(), proofs[1].clone());
+
+        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
+        let localstore = MemoryLocalStore::default();
+        localstore
+            .add_proofs(
+                &mint_url,
+                &Proofs::new(vec![spent.clone(), unspent.clone()]),
+            )
+            .await?;
+
+        let client = MockClient::with_spent_secrets(vec![spent.secret]);
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        assert_eq!(1, wallet.cleanup().await?);
+        assert_eq!(
+            vec![unspent],
+            localstore.get_proofs(&mint_url).await?.proofs()
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_pay_invoice() -> anyhow::Result<()> {
         let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
@@ -709,4 +1700,362 @@ mod tests {
         assert!(result.paid);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_get_mint_payment_request() -> anyhow::Result<()> {
+        // 21 sats
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_payment_request("lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm"))
+            .with_localstore(MemoryLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+
+        let payment_request = wallet.get_mint_payment_request(21).await?;
+        assert_eq!("hash", payment_request.hash);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_mint_payment_request_amount_mismatch() -> anyhow::Result<()> {
+        // 21 sats
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_payment_request("lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm"))
+            .with_localstore(MemoryLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+
+        let result = wallet.get_mint_payment_request(100).await;
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::InvoiceAmountMismatch(100, 21))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pay_invoice_with_partial_change() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+
+        // the fee reserve of 4 sats results in 2 blank outputs, the mint only signs the first one
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_melt_change(4, vec![2]))
+            .with_localstore(localstore)
+            .with_mint_url(mint_url)
+            .build()
+            .await?;
+
+        // 21 sats
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+
+        let result = wallet.pay_invoice(invoice).await?;
+        assert!(result.paid);
+        // 60 - 21 - 4 sats are left after the split, 2 sats come back as change
+        assert_eq!(37, wallet.get_balance().await?);
+        let history = wallet.get_history().await?;
+        assert_eq!(
+            vec![(HistoryKind::Melt, 23)],
+            history
+                .into_iter()
+                .map(|entry| (entry.kind, entry.amount))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pay_invoice_ignores_change_exceeding_fee_reserve() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_melt_change(4, vec![4, 2]))
+            .with_localstore(localstore)
+            .with_mint_url(mint_url)
+            .build()
+            .await?;
+
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+
+        // the invoice is paid, only the invalid change is lost
+        let result = wallet.pay_invoice(invoice).await?;
+        assert!(result.paid);
+        assert_eq!(35, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cheapest_mint_for_invoice() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let expensive_mint = Url::parse("http://localhost:8080/")?;
+        let cheap_mint = Url::parse("http://localhost:8081/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore
+            .add_proofs(&expensive_mint, &tokens.proofs())
+            .await?;
+        localstore.add_proofs(&cheap_mint, &tokens.proofs()).await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_mint_fees(HashMap::from([
+                (expensive_mint.clone(), 10),
+                (cheap_mint.clone(), 2),
+            ])))
+            .with_localstore(localstore)
+            .with_mint_url(expensive_mint)
+            .build()
+            .await?;
+
+        // 21 sats
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm";
+        assert_eq!(
+            Some(cheap_mint),
+            wallet.cheapest_mint_for_invoice(invoice).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cheapest_mint_for_invoice_not_enough_tokens() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+
+        // 21 sats plus a fee reserve of 40 sats exceed the balance of 60 sats
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_mint_fees(HashMap::from([(
+                mint_url.clone(),
+                40,
+            )])))
+            .with_localstore(localstore)
+            .with_mint_url(mint_url)
+            .build()
+            .await?;
+
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm";
+        assert_eq!(None, wallet.cheapest_mint_for_invoice(invoice).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_tokens_skips_frozen_proof() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 4,8,16,32
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_signed_split())
+            .with_localstore(localstore.clone())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        // the proof of 32 sats would be selected first to send 20 sats
+        let frozen = wallet
+            .list_proofs()
+            .await?
+            .into_iter()
+            .find(|proof| proof.amount == 32)
+            .expect("proof of 32 sats not found");
+        wallet.freeze_proof(&frozen.id).await?;
+
+        let token = wallet.send_tokens(20, None).await?;
+        assert_eq!(20, token.total_amount());
+
+        let frozen_secret = tokens
+            .proofs()
+            .proofs()
+            .into_iter()
+            .find(|proof| proof.amount == 32)
+            .expect("proof of 32 sats not found")
+            .secret;
+        assert!(localstore
+            .get_proofs(&mint_url)
+            .await?
+            .proofs()
+            .iter()
+            .any(|proof| proof.secret == frozen_secret));
+        assert!(wallet
+            .list_proofs()
+            .await?
+            .iter()
+            .any(|proof| proof.id == frozen.id && proof.frozen));
+        // the change of 8 sats, the frozen proof of 32 sats is not part of the balance
+        assert_eq!(8, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_tokens_rejects_invalid_dleq() -> anyhow::Result<()> {
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_tampered_dleq())
+            .with_localstore(localstore.clone())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let result = wallet.receive_tokens(&tokens).await;
+        assert!(matches!(result, Err(MokshaWalletError::InvalidDleq)));
+        assert!(localstore.get_proofs(&mint_url).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_tokens_upgrades_keyset() -> anyhow::Result<()> {
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        let client = MockClient::with_signed_split();
+        let current_keyset = client.keyset.mint_keyset.keyset_id.clone();
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        assert!(tokens
+            .proofs()
+            .proofs()
+            .iter()
+            .all(|proof| proof.keyset_id != current_keyset));
+
+        wallet.receive_tokens(&tokens).await?;
+        let stored = localstore.get_proofs(&mint_url).await?;
+        assert_eq!(60, stored.total_amount());
+        assert!(stored
+            .proofs()
+            .iter()
+            .all(|proof| proof.keyset_id == current_keyset));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_keyset() -> anyhow::Result<()> {
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+        let client = MockClient::with_signed_split();
+        let current_keyset = client.keyset.mint_keyset.keyset_id.clone();
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        assert_eq!(60, wallet.upgrade_keyset().await?);
+        let stored = localstore.get_proofs(&mint_url).await?;
+        assert_eq!(60, stored.total_amount());
+        assert!(stored
+            .proofs()
+            .iter()
+            .all(|proof| proof.keyset_id == current_keyset));
+
+        // the proofs are already on the current keyset
+        assert_eq!(0, wallet.upgrade_keyset().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_tokens_partial() -> anyhow::Result<()> {
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let localstore = MemoryLocalStore::default();
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_signed_split())
+            .with_localstore(localstore.clone())
+            .with_mint_url(mint_url.clone())
+            .build()
+            .await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let remainder = wallet.receive_tokens_partial(&tokens, 40).await?;
+
+        assert_eq!(20, remainder.total_amount());
+        assert_eq!(Some(mint_url), remainder.mint());
+        remainder.validate()?;
+
+        // the remainder is stored frozen, so it can't be spent by the wallet but isn't lost
+        assert_eq!(40, wallet.get_balance().await?);
+        assert_eq!(Some(&40), wallet.get_balances().await?.get(&mint_url));
+        assert_eq!(60, localstore.get_proofs(&mint_url).await?.total_amount());
+        let remainder_secrets = remainder
+            .proofs()
+            .proofs()
+            .into_iter()
+            .map(|proof| proof.secret)
+            .collect::<HashSet<_>>();
+        assert!(wallet
+            .get_spendable_proofs()
+            .await?
+            .proofs()
+            .iter()
+            .all(|proof| !remainder_secrets.contains(&proof.secret)));
+        assert_eq!(remainder_secrets, localstore.get_frozen_secrets().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_tokens_partial_whole_token() -> anyhow::Result<()> {
+        let client = MockClient::with_signed_split();
+        let calls = client.calls.clone();
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(MemoryLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+        let calls_after_build = calls.load(Ordering::SeqCst);
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let result = wallet.receive_tokens_partial(&tokens, 60).await;
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::InvalidPartialAmount(60, 60))
+        ));
+        assert_eq!(calls_after_build, calls.load(Ordering::SeqCst));
+        assert_eq!(0, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_tokens_partial_more_than_token() -> anyhow::Result<()> {
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::with_signed_split())
+            .with_localstore(MemoryLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let result = wallet.receive_tokens_partial(&tokens, 61).await;
+        assert!(matches!(result, Err(MokshaWalletError::NotEnoughTokens)));
+        assert_eq!(0, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_freeze_unknown_proof() -> anyhow::Result<()> {
+        let wallet = WalletBuilder::new()
+            .with_client(MockClient::default())
+            .with_localstore(MemoryLocalStore::default())
+            .with_mint_url(Url::parse("http://localhost:8080/")?)
+            .build()
+            .await?;
+
+        let result = wallet.freeze_proof("deadbeef").await;
+        assert!(matches!(result, Err(MokshaWalletError::ProofNotFound(_))));
+        Ok(())
+    }
 }
@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 
 use async_trait::async_trait;
-use moksha_core::blind::BlindedMessage;
+use moksha_core::blind::{BlindedMessage, BlindedSignature};
+use moksha_core::dhke::Dhke;
 use moksha_core::fixture::{read_fixture, read_fixture_as};
 use moksha_core::keyset::{Keysets, MintKeyset};
 use moksha_core::primitives::{
-    CheckFeesResponse, MintLegacyInfoResponse, PaymentRequest, PostMeltResponse, PostMintResponse,
-    PostSplitResponse,
+    CheckFeesResponse, CheckSpendableResponse, MintLegacyInfoResponse, PaymentRequest,
+    PostMeltResponse, PostMintResponse, PostSplitResponse,
 };
 use moksha_core::proof::Proofs;
 use moksha_core::token::TokenV3;
@@ -24,6 +25,11 @@ struct MockClient {
     post_melt_response: PostMeltResponse,
     mint_keys: HashMap<u64, PublicKey>,
     keysets: Keysets,
+    /// signs the outputs of splits and melts instead of returning the fixtures
+    signing_keyset: Option<MintKeyset>,
+    fee_reserve: u64,
+    /// amounts of the change the mint signs for the first blank outputs of a melt
+    melt_change: Vec<u64>,
 }
 
 impl MockClient {
@@ -41,6 +47,33 @@ impl MockClient {
             ..Default::default()
         }
     }
+
+    /// pays every invoice and returns change for the first blank outputs
+    fn with_signing_keyset(
+        mint_keyset: MintKeyset,
+        fee_reserve: u64,
+        melt_change: Vec<u64>,
+    ) -> Self {
+        Self {
+            mint_keys: mint_keyset.public_keys.clone(),
+            keysets: Keysets::new(vec![mint_keyset.keyset_id.clone()]),
+            signing_keyset: Some(mint_keyset),
+            fee_reserve,
+            melt_change,
+            ..Default::default()
+        }
+    }
+
+    fn sign(&self, keyset: &MintKeyset, amount: u64, output: &BlindedMessage) -> BlindedSignature {
+        BlindedSignature {
+            amount,
+            c_: Dhke::new()
+                .step2_bob(output.b_, &keyset.private_keys[&amount])
+                .expect("signing failed"),
+            id: Some(keyset.keyset_id.clone()),
+            dleq: None,
+        }
+    }
 }
 
 #[async_trait(?Send)]
@@ -49,9 +82,17 @@ impl LegacyClient for MockClient {
         &self,
         _mint_url: &Url,
         _proofs: Proofs,
-        _output: Vec<BlindedMessage>,
+        output: Vec<BlindedMessage>,
     ) -> Result<PostSplitResponse, MokshaWalletError> {
-        Ok(self.split_response.clone())
+        let Some(keyset) = &self.signing_keyset else {
+            return Ok(self.split_response.clone());
+        };
+        Ok(PostSplitResponse::with_promises(
+            output
+                .iter()
+                .map(|msg| self.sign(keyset, msg.amount, msg))
+                .collect(),
+        ))
     }
 
     async fn post_mint_payment_request(
@@ -68,9 +109,21 @@ impl LegacyClient for MockClient {
         _mint_url: &Url,
         _proofs: Proofs,
         _pr: String,
-        _outputs: Vec<BlindedMessage>,
+        outputs: Vec<BlindedMessage>,
     ) -> Result<PostMeltResponse, MokshaWalletError> {
-        Ok(self.post_melt_response.clone())
+        let Some(keyset) = &self.signing_keyset else {
+            return Ok(self.post_melt_response.clone());
+        };
+        Ok(PostMeltResponse {
+            paid: true,
+            preimage: "preimage".to_string(),
+            change: self
+                .melt_change
+                .iter()
+                .zip(outputs.iter())
+                .map(|(amount, msg)| self.sign(keyset, *amount, msg))
+                .collect(),
+        })
     }
 
     async fn post_checkfees(
@@ -78,7 +131,17 @@ impl LegacyClient for MockClient {
         _mint_url: &Url,
         _pr: String,
     ) -> Result<CheckFeesResponse, MokshaWalletError> {
-        Ok(CheckFeesResponse { fee: 0 })
+        Ok(CheckFeesResponse {
+            fee: self.fee_reserve,
+        })
+    }
+
+    async fn post_check_spendable(
+        &self,
+        _mint_url: &Url,
+        _proofs: Proofs,
+    ) -> Result<CheckSpendableResponse, MokshaWalletError> {
+        unimplemented!()
     }
 
     async fn get_mint_keys(
@@ -121,8 +184,9 @@ async fn test_pay_invoice_can_not_melt() -> anyhow::Result<()> {
         .await
         .expect("Could not create localstore");
 
-    localstore.add_proofs(&tokens.proofs()).await?;
-    assert_eq!(64, localstore.get_proofs().await?.total_amount());
+    let mint_url = Url::parse("http://localhost:8080").expect("invalid url");
+    localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+    assert_eq!(64, localstore.get_proofs(&mint_url).await?.total_amount());
 
     let melt_response = read_fixture_as::<PostMeltResponse>("post_melt_response_not_paid.json")?;
     let split_response = read_fixture_as::<PostSplitResponse>("post_split_response_24_40.json")?;
@@ -139,7 +203,7 @@ async fn test_pay_invoice_can_not_melt() -> anyhow::Result<()> {
     let wallet = WalletBuilder::default()
         .with_client(mock_client)
         .with_localstore(localstore.clone())
-        .with_mint_url(Url::parse("http://localhost:8080").expect("invalid url"))
+        .with_mint_url(mint_url.clone())
         .build()
         .await?;
 
@@ -148,7 +212,49 @@ async fn test_pay_invoice_can_not_melt() -> anyhow::Result<()> {
 
     let result = wallet.pay_invoice(invoice).await?;
     assert!(!result.paid);
-    assert_eq!(64, localstore.get_proofs().await?.total_amount());
+    assert_eq!(64, localstore.get_proofs(&mint_url).await?.total_amount());
     assert!(!result.paid);
     Ok(())
 }
+
+/// tests if the change for the unused fee reserve is stored after paying an invoice
+#[tokio::test]
+async fn test_pay_invoice_with_change() -> anyhow::Result<()> {
+    let fixture = read_fixture("token_64.cashu")?;
+    let tokens: TokenV3 = fixture.try_into()?;
+
+    let tmp = tempfile::tempdir().expect("Could not create tmp dir for wallet");
+    let tmp_dir = tmp
+        .path()
+        .to_str()
+        .expect("Could not create tmp dir for wallet");
+
+    let localstore = SqliteLocalStore::with_path(format!("{tmp_dir}/test_wallet.db"))
+        .await
+        .expect("Could not create localstore");
+
+    let mint_url = Url::parse("http://localhost:8080").expect("invalid url");
+    localstore.add_proofs(&mint_url, &tokens.proofs()).await?;
+    assert_eq!(64, localstore.get_proofs(&mint_url).await?.total_amount());
+
+    // the mint reserves 4 sats for fees, but the payment only costs 2 sats
+    let mock_client =
+        MockClient::with_signing_keyset(MintKeyset::legacy_new("mysecret", ""), 4, vec![2]);
+
+    let wallet = WalletBuilder::default()
+        .with_client(mock_client)
+        .with_localstore(localstore.clone())
+        .with_mint_url(mint_url.clone())
+        .build()
+        .await?;
+
+    // 20 sats
+    let invoice = "lnbcrt200n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqwgzyh9s8m5h45t790jejvyjlfrf0uv645sxwd5pj6nwm55mxy0c52np3z8xwcuy6umft9hjaxa0u6yfe32y99alcrjfv3s3j4e0f0lgq3a7fvq".to_string();
+
+    let result = wallet.pay_invoice(invoice).await?;
+    assert!(result.paid);
+    // 64 sats - 20 sats for the invoice - 2 sats fees
+    assert_eq!(42, localstore.get_proofs(&mint_url).await?.total_amount());
+    assert_eq!(42, wallet.get_balance().await?);
+    Ok(())
+}